@@ -0,0 +1,81 @@
+//! Measures how fast `Serializer` writes a torrent with 10,000 files, and how well it reuses its
+//! scratch buffers.
+//!
+//! ```text
+//! cargo run --release --example serialize
+//! ```
+
+use std::time::Instant;
+
+use beeenn::Serializer;
+use serde::Serialize;
+
+const FILES: usize = 10_000;
+const ROUNDS: u32 = 20;
+
+#[derive(Serialize)]
+struct File {
+    length: u64,
+    path: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct Info {
+    files: Vec<File>,
+    name: String,
+    #[serde(rename = "piece length")]
+    piece_length: u64,
+    #[serde(with = "serde_bytes_vec")]
+    pieces: Vec<u8>,
+}
+
+#[derive(Serialize)]
+struct Torrent {
+    announce: String,
+    info: Info,
+}
+
+// Writes a `Vec<u8>` as a bencode string rather than a list of integers.
+mod serde_bytes_vec {
+    pub fn serialize<S: serde::Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(bytes)
+    }
+}
+
+fn torrent() -> Torrent {
+    let files = (0..FILES)
+        .map(|i| File {
+            length: 1_000_000 + i as u64,
+            path: vec![format!("disc{}", i % 10), format!("track{:05}.flac", i)],
+        })
+        .collect();
+    Torrent {
+        announce: "http://tracker.example/announce".to_string(),
+        info: Info {
+            files,
+            name: "collection".to_string(),
+            piece_length: 1 << 20,
+            pieces: vec![0; 20 * FILES],
+        },
+    }
+}
+
+fn main() {
+    let torrent = torrent();
+
+    let mut serializer = Serializer::new();
+    serializer.append(&torrent).unwrap();
+    let len = serializer.bytes().len();
+    let stats = serializer.pool_stats();
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        beeenn::to_bytes(&torrent).unwrap();
+    }
+    let per_round = start.elapsed() / ROUNDS;
+
+    println!(
+        "{} files, {} bytes, {:?} per torrent, {} scratch buffers created, {} reused, {} pooled",
+        FILES, len, per_round, stats.created, stats.reused, stats.pooled
+    );
+}
@@ -0,0 +1,99 @@
+//! Measures how fast `BESliceReader` parses torrents, and how many heap allocations it makes.
+//!
+//! With no arguments it parses a generated multi-file torrent with 20,000 files. Otherwise it
+//! parses each file named on the command line.
+//!
+//! ```text
+//! cargo run --release --example throughput [FILE...]
+//! ```
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Instant;
+
+use beeenn::encode::KeyOrder;
+use beeenn::{BESliceReader, BEValue};
+
+// Counts allocations so the report can show how many a parse makes.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const FILES: usize = 20_000;
+const ROUNDS: u32 = 20;
+
+fn generated() -> Vec<u8> {
+    let mut files = Vec::with_capacity(FILES);
+    for i in 0..FILES {
+        let mut file = BEValue::BEDict(Vec::new());
+        file.insert("length", 1_000_000 + i as i64);
+        file.insert(
+            "path",
+            vec![
+                BEValue::from(format!("disc{}", i % 10)),
+                BEValue::from(format!("track{:05}.flac", i)),
+            ],
+        );
+        files.push(file);
+    }
+    let mut info = BEValue::BEDict(Vec::new());
+    info.insert("files", files);
+    info.insert("name", "collection");
+    info.insert("piece length", 1 << 20);
+    info.insert("pieces", vec![0u8; 20 * FILES]);
+    let mut torrent = BEValue::BEDict(Vec::new());
+    torrent.insert("announce", "http://tracker.example/announce");
+    torrent.insert("creation date", 1_600_000_000);
+    torrent.insert("info", info);
+    torrent.encode(KeyOrder::Require).unwrap()
+}
+
+fn measure(name: &str, input: &[u8]) {
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let value = BESliceReader::new(input).next_value().unwrap().unwrap();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    let heap = value.deep_size_of();
+    drop(value);
+
+    let start = Instant::now();
+    for _ in 0..ROUNDS {
+        BESliceReader::new(input).next_value().unwrap().unwrap();
+    }
+    let per_parse = start.elapsed() / ROUNDS;
+    let mib_per_sec = input.len() as f64 / per_parse.as_secs_f64() / (1 << 20) as f64;
+
+    println!(
+        "{}: {} bytes, {:?} per parse, {:.0} MiB/s, {} allocations, {} heap bytes",
+        name,
+        input.len(),
+        per_parse,
+        mib_per_sec,
+        allocations,
+        heap
+    );
+}
+
+fn main() {
+    let paths: Vec<String> = std::env::args().skip(1).collect();
+    if paths.is_empty() {
+        measure("generated", &generated());
+    }
+    for path in paths {
+        let input = std::fs::read(&path).unwrap();
+        measure(&path, &input);
+    }
+}
@@ -0,0 +1,349 @@
+//! `BEBytes`, the contents of a `BEValue::BEString`.
+
+use std::borrow::{Borrow, Cow};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::Deref;
+
+/// The bytes of a bencode string.
+///
+/// Bencode strings are bytes, but most of them are text. `BEBytes` checks once, when it is
+/// created, whether the bytes are UTF-8, so `as_str` and `eq_str` don't validate them again.
+/// Strings of up to 30 bytes, which are most of the strings in a torrent, are stored inline
+/// without a heap allocation. It derefs to `[u8]` for everything else.
+///
+/// ```
+/// use beeenn::{BESliceReader, BEValue};
+///
+/// let value = BESliceReader::new(b"l8:announce2:\x01\xffe").next_value().unwrap().unwrap();
+/// match &value[0] {
+///     BEValue::BEString(bytes) => {
+///         assert!(bytes.eq_str("announce"));
+///         assert_eq!(Some("announce"), bytes.as_str());
+///     }
+///     _ => unreachable!(),
+/// }
+/// match &value[1] {
+///     BEValue::BEString(bytes) => {
+///         assert_eq!(None, bytes.as_str());
+///         assert_eq!("01ff", bytes.to_hex());
+///         assert_eq!(2, bytes.len());
+///     }
+///     _ => unreachable!(),
+/// }
+/// ```
+#[derive(Clone)]
+pub struct BEBytes(Repr);
+
+// The most bytes stored inline. 30 is as many as fit, with the flag and length, without making
+// `BEBytes`, or `BEValue`, any bigger than a `String` plus a tag.
+const INLINE_LEN: usize = 30;
+
+// The bytes can't change after creation, so the variant stays right.
+#[derive(Clone)]
+enum Repr {
+    // `text` is whether `buf[..len]` is UTF-8.
+    Inline {
+        text: bool,
+        len: u8,
+        buf: [u8; INLINE_LEN],
+    },
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl BEBytes {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        if let Some(inline) = Self::inline(&bytes) {
+            return inline;
+        }
+        match String::from_utf8(bytes) {
+            Ok(text) => BEBytes(Repr::Text(text)),
+            Err(err) => BEBytes(Repr::Binary(err.into_bytes())),
+        }
+    }
+
+    fn inline(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() > INLINE_LEN {
+            return None;
+        }
+        let mut buf = [0; INLINE_LEN];
+        buf[..bytes.len()].copy_from_slice(bytes);
+        Some(BEBytes(Repr::Inline {
+            text: std::str::from_utf8(bytes).is_ok(),
+            len: bytes.len() as u8,
+            buf,
+        }))
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.0 {
+            Repr::Inline { len, buf, .. } => &buf[..*len as usize],
+            Repr::Text(text) => text.as_bytes(),
+            Repr::Binary(bytes) => bytes,
+        }
+    }
+
+    /// Returns the bytes as text, or `None` if they aren't valid UTF-8.
+    pub fn as_str(&self) -> Option<&str> {
+        match &self.0 {
+            Repr::Inline {
+                text: true,
+                len,
+                buf,
+            } => {
+                // SAFETY: `text` is only set when these bytes passed `from_utf8` in `inline`, and
+                // they can't change after that.
+                Some(unsafe { std::str::from_utf8_unchecked(&buf[..*len as usize]) })
+            }
+            Repr::Inline { text: false, .. } => None,
+            Repr::Text(text) => Some(text),
+            Repr::Binary(_) => None,
+        }
+    }
+
+    /// Whether the bytes are exactly the UTF-8 encoding of `s`.
+    pub fn eq_str(&self, s: &str) -> bool {
+        self.as_bytes() == s.as_bytes()
+    }
+
+    /// The bytes as lowercase hex, two digits per byte.
+    pub fn to_hex(&self) -> String {
+        format!("{:x}", self)
+    }
+
+    pub fn into_vec(self) -> Vec<u8> {
+        match self.0 {
+            Repr::Inline { len, buf, .. } => buf[..len as usize].to_vec(),
+            Repr::Text(text) => text.into_bytes(),
+            Repr::Binary(bytes) => bytes,
+        }
+    }
+
+    // The heap bytes held, for `BEValue::deep_size_of`.
+    pub(crate) fn heap_size(&self) -> usize {
+        match &self.0 {
+            Repr::Inline { .. } => 0,
+            Repr::Text(text) => text.capacity(),
+            Repr::Binary(bytes) => bytes.capacity(),
+        }
+    }
+}
+
+impl Default for BEBytes {
+    fn default() -> Self {
+        BEBytes::from("")
+    }
+}
+
+// Compared, ordered and hashed as bytes, like `Vec<u8>`.
+impl PartialEq for BEBytes {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for BEBytes {}
+
+impl PartialOrd for BEBytes {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for BEBytes {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl Hash for BEBytes {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_bytes().hash(state)
+    }
+}
+
+impl Deref for BEBytes {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl AsRef<[u8]> for BEBytes {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl Borrow<[u8]> for BEBytes {
+    fn borrow(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl From<Vec<u8>> for BEBytes {
+    fn from(bytes: Vec<u8>) -> Self {
+        BEBytes::new(bytes)
+    }
+}
+
+impl From<&[u8]> for BEBytes {
+    fn from(bytes: &[u8]) -> Self {
+        BEBytes::inline(bytes).unwrap_or_else(|| BEBytes::new(bytes.to_vec()))
+    }
+}
+
+impl<const N: usize> From<&[u8; N]> for BEBytes {
+    fn from(bytes: &[u8; N]) -> Self {
+        BEBytes::from(&bytes[..])
+    }
+}
+
+// Copies borrowed bytes straight into inline storage when they fit, rather than allocating a
+// `Vec` first.
+impl From<Cow<'_, [u8]>> for BEBytes {
+    fn from(bytes: Cow<'_, [u8]>) -> Self {
+        match bytes {
+            Cow::Borrowed(bytes) => BEBytes::from(bytes),
+            Cow::Owned(bytes) => BEBytes::new(bytes),
+        }
+    }
+}
+
+impl From<String> for BEBytes {
+    fn from(s: String) -> Self {
+        BEBytes::inline(s.as_bytes()).unwrap_or(BEBytes(Repr::Text(s)))
+    }
+}
+
+impl From<&str> for BEBytes {
+    fn from(s: &str) -> Self {
+        BEBytes::inline(s.as_bytes()).unwrap_or_else(|| BEBytes(Repr::Text(s.to_string())))
+    }
+}
+
+impl From<BEBytes> for Vec<u8> {
+    fn from(bytes: BEBytes) -> Self {
+        bytes.into_vec()
+    }
+}
+
+impl PartialEq<[u8]> for BEBytes {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_bytes() == other
+    }
+}
+
+impl PartialEq<&[u8]> for BEBytes {
+    fn eq(&self, other: &&[u8]) -> bool {
+        self.as_bytes() == *other
+    }
+}
+
+impl PartialEq<Vec<u8>> for BEBytes {
+    fn eq(&self, other: &Vec<u8>) -> bool {
+        self.as_bytes() == &other[..]
+    }
+}
+
+impl PartialEq<str> for BEBytes {
+    fn eq(&self, other: &str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+impl PartialEq<&str> for BEBytes {
+    fn eq(&self, other: &&str) -> bool {
+        self.eq_str(other)
+    }
+}
+
+/// Shows the bytes as a string when they are UTF-8, and as an escaped byte literal otherwise.
+impl fmt::Debug for BEBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.as_str() {
+            Some(s) => write!(f, "{:?}", s),
+            None => write!(f, "b\"{}\"", self.as_bytes().escape_ascii()),
+        }
+    }
+}
+
+impl fmt::LowerHex for BEBytes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for b in self.as_bytes() {
+            write!(f, "{:02x}", b)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::mem;
+
+    #[test]
+    fn test_text() {
+        let bytes = BEBytes::from("announce");
+        assert_eq!(Some("announce"), bytes.as_str());
+        assert!(bytes.eq_str("announce"));
+        assert!(!bytes.eq_str("announce-list"));
+        assert!(bytes == "announce");
+        assert_eq!(8, bytes.len());
+        assert!(bytes.starts_with(b"ann"));
+        assert_eq!("\"announce\"", format!("{:?}", bytes));
+    }
+
+    #[test]
+    fn test_binary() {
+        let bytes = BEBytes::new(vec![0x00, b'a', 0xff]);
+        assert_eq!(None, bytes.as_str());
+        assert!(!bytes.eq_str("a"));
+        assert_eq!("0061ff", bytes.to_hex());
+        assert_eq!("b\"\\x00a\\xff\"", format!("{:?}", bytes));
+        assert!(bytes == [0x00, b'a', 0xff][..]);
+        assert_eq!(vec![0x00, b'a', 0xff], bytes.into_vec());
+
+        assert_eq!(BEBytes::from(&b"x"[..]), BEBytes::from("x"));
+        assert_eq!("", BEBytes::default().to_hex());
+        assert_eq!(Some(""), BEBytes::default().as_str());
+    }
+
+    #[test]
+    fn test_inline() {
+        let short = "a".repeat(INLINE_LEN);
+        let long = "a".repeat(INLINE_LEN + 1);
+        for bytes in &[
+            BEBytes::from(short.as_str()),
+            BEBytes::from(short.clone()),
+            BEBytes::from(short.as_bytes()),
+            BEBytes::new(short.clone().into_bytes()),
+        ] {
+            assert!(matches!(bytes.0, Repr::Inline { text: true, .. }));
+            assert_eq!(0, bytes.heap_size());
+            assert_eq!(Some(short.as_str()), bytes.as_str());
+        }
+        let bytes = BEBytes::from(long.as_str());
+        assert!(matches!(bytes.0, Repr::Text(_)));
+        assert_eq!(INLINE_LEN + 1, bytes.heap_size());
+
+        // Inline and heap bytes compare by their contents.
+        let binary = BEBytes::new(vec![0xff, 0]);
+        assert!(binary < BEBytes::new(vec![0xff; INLINE_LEN + 1]));
+        assert_eq!(&[0xff, 0][..], &*binary);
+        assert!(matches!(binary.0, Repr::Inline { text: false, .. }));
+        assert_eq!(None, binary.as_str());
+        assert_eq!(vec![0xff, 0], binary.into_vec());
+    }
+
+    // Inline storage doesn't make a `BEValue` any bigger than a `Vec<u8>` string did.
+    #[cfg(target_pointer_width = "64")]
+    #[test]
+    fn test_size() {
+        assert_eq!(32, mem::size_of::<BEBytes>());
+        assert_eq!(32, mem::size_of::<crate::value::BEValue>());
+    }
+}
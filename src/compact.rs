@@ -0,0 +1,156 @@
+//! `BEValue::to_compact_string`, a one-line summary of a value for logs.
+
+use std::fmt::Write;
+
+use crate::value::BEValue;
+
+// The longest output, in chars, before it's cut off.
+const MAX_LEN: usize = 200;
+// Containers nested deeper than this are shown as `[…]` or `{…}`.
+const MAX_DEPTH: usize = 4;
+// Text strings longer than this, in chars, are shortened.
+const MAX_TEXT_LEN: usize = 32;
+
+impl BEValue {
+    /// A short, single-line summary of the value for log lines and error messages.
+    ///
+    /// Text is quoted and shortened past 32 characters, and strings that aren't printable UTF-8
+    /// appear as their length. Containers more than 4 deep are elided, and the whole summary is
+    /// cut off at 200 characters. Dict entries appear in stored order. The result is meant for
+    /// people; it can't be parsed back.
+    ///
+    /// ```
+    /// use beeenn::BESliceReader;
+    ///
+    /// let input = b"d8:announce5:udp:a4:infod6:lengthi7e6:pieces3:\x00\x01\x02ee";
+    /// let value = BESliceReader::new(input).next_value().unwrap().unwrap();
+    /// assert_eq!(
+    ///     r#"{announce: "udp:a", info: {length: 7, pieces: <3 bytes>}}"#,
+    ///     value.to_compact_string()
+    /// );
+    /// ```
+    pub fn to_compact_string(&self) -> String {
+        let mut out = String::new();
+        write_value(&mut out, self, 0);
+        if out.chars().count() > MAX_LEN {
+            out = out.chars().take(MAX_LEN).collect();
+            out.push('…');
+        }
+        out
+    }
+}
+
+// Returns early once the output is past the limit, since the rest will be cut off anyway.
+fn write_value(out: &mut String, value: &BEValue, depth: usize) {
+    if out.len() > MAX_LEN {
+        return;
+    }
+    match value {
+        BEValue::BEInteger(i) => {
+            let _ = write!(out, "{}", i);
+        }
+        BEValue::BEString(bytes) => write_string(out, bytes),
+        BEValue::BEList(list) if list.is_empty() => out.push_str("[]"),
+        BEValue::BEList(_) if depth >= MAX_DEPTH => out.push_str("[…]"),
+        BEValue::BEList(list) => {
+            out.push('[');
+            for (i, element) in list.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                write_value(out, element, depth + 1);
+            }
+            out.push(']');
+        }
+        BEValue::BEDict(dict) if dict.is_empty() => out.push_str("{}"),
+        BEValue::BEDict(_) if depth >= MAX_DEPTH => out.push_str("{…}"),
+        BEValue::BEDict(dict) => {
+            out.push('{');
+            for (i, (key, value)) in dict.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(", ");
+                }
+                match printable(key) {
+                    Some(key) => out.push_str(key),
+                    None => write_string(out, key),
+                }
+                out.push_str(": ");
+                write_value(out, value, depth + 1);
+            }
+            out.push('}');
+        }
+        BEValue::BEInvalid(bytes) => {
+            let _ = write!(out, "<invalid, {} bytes>", bytes.len());
+        }
+    }
+}
+
+fn write_string(out: &mut String, bytes: &[u8]) {
+    match printable(bytes) {
+        Some(text) if text.chars().count() > MAX_TEXT_LEN => {
+            let start: String = text.chars().take(MAX_TEXT_LEN).collect();
+            let _ = write!(out, "{:?}… ({} bytes)", start, bytes.len());
+        }
+        Some(text) => {
+            let _ = write!(out, "{:?}", text);
+        }
+        None => {
+            let _ = write!(out, "<{} bytes>", bytes.len());
+        }
+    }
+}
+
+// The bytes as text, if they are UTF-8 without control characters.
+fn printable(bytes: &[u8]) -> Option<&str> {
+    std::str::from_utf8(bytes)
+        .ok()
+        .filter(|text| !text.chars().any(char::is_control))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reader::BESliceReader;
+
+    fn compact(bytes: &[u8]) -> String {
+        BESliceReader::new(bytes)
+            .next_value()
+            .unwrap()
+            .unwrap()
+            .to_compact_string()
+    }
+
+    #[test]
+    fn test_compact() {
+        assert_eq!("-7", compact(b"i-7e"));
+        assert_eq!(r#""a \"quote\"""#, compact(b"9:a \"quote\""));
+        assert_eq!("<2 bytes>", compact(b"2:\xff\xfe"));
+        assert_eq!("<1 bytes>", compact(b"1:\n"));
+        assert_eq!("[1, [], {}]", compact(b"li1eledee"));
+        assert_eq!(r#"{<1 bytes>: "v", k: 1}"#, compact(b"d1:\x001:v1:ki1ee"));
+        assert_eq!(
+            "<invalid, 3 bytes>",
+            BEValue::BEInvalid(b"i1x".to_vec()).to_compact_string()
+        );
+    }
+
+    #[test]
+    fn test_bounds() {
+        let text = "x".repeat(40);
+        assert_eq!(
+            format!("\"{}\"… (40 bytes)", &text[..32]),
+            compact(format!("40:{}", text).as_bytes())
+        );
+
+        assert_eq!("[[[[[…]]]]]", compact(b"llllli1eeeeee"));
+        assert_eq!("[[[[{…}]]]]", compact(b"lllld1:ai1eeeeee"));
+        assert_eq!("[[[[[]]]]]", compact(b"llllleeeee"));
+
+        let long = format!("l{}e", "i1234567890e".repeat(100));
+        let summary = compact(long.as_bytes());
+        assert_eq!(MAX_LEN + 1, summary.chars().count());
+        assert!(summary.starts_with("[1234567890, "));
+        assert!(summary.ends_with('…'));
+        assert!(!summary.contains('\n'));
+    }
+}
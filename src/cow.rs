@@ -0,0 +1,190 @@
+//! A `BEValue` whose strings may borrow from the input.
+//!
+//! `BESliceReader::next_value_cow` slices every string and dict key out of its buffer instead of
+//! copying it, so a caller that only looks at part of a large document pays for the copies it
+//! keeps. `into_owned` turns the result into an ordinary `BEValue` once it has to outlive the
+//! buffer.
+//!
+//! ```
+//! use beeenn::{BESliceReader, BEValueCow};
+//! use std::borrow::Cow;
+//!
+//! let input = b"d6:lengthi7e4:name3:fooe";
+//! let value = BESliceReader::new(input).next_value_cow().unwrap().unwrap();
+//! match value.get("name") {
+//!     Some(BEValueCow::String(Cow::Borrowed(name))) => assert_eq!(b"foo", name),
+//!     other => panic!("{:?}", other),
+//! }
+//! assert_eq!(7, value.into_owned()["length"].integer());
+//! ```
+
+use std::borrow::Cow;
+
+use crate::value::BEValue;
+
+/// A bencode value with strings held as `Cow<'a, [u8]>`. See the module documentation.
+///
+/// Dicts keep the order they were read or built in, as `BEValue`'s do.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BEValueCow<'a> {
+    Integer(i64),
+    String(Cow<'a, [u8]>),
+    List(Vec<BEValueCow<'a>>),
+    Dict(Vec<(Cow<'a, [u8]>, BEValueCow<'a>)>),
+    /// The raw bytes of an unparseable value, as in `BEValue::BEInvalid`.
+    Invalid(Cow<'a, [u8]>),
+}
+
+impl<'a> BEValueCow<'a> {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            BEValueCow::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            BEValueCow::String(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes()
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    pub fn as_list(&self) -> Option<&[BEValueCow<'a>]> {
+        match self {
+            BEValueCow::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` in a dict. Returns `None` if the key is missing or if this is not a dict.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&BEValueCow<'a>> {
+        match self {
+            BEValueCow::Dict(dict) => dict
+                .iter()
+                .find(|(k, _)| &k[..] == key.as_ref())
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns true if no string or key in the value is borrowed.
+    pub fn is_owned(&self) -> bool {
+        match self {
+            BEValueCow::Integer(_) => true,
+            BEValueCow::String(bytes) | BEValueCow::Invalid(bytes) => {
+                matches!(bytes, Cow::Owned(_))
+            }
+            BEValueCow::List(list) => list.iter().all(Self::is_owned),
+            BEValueCow::Dict(dict) => dict
+                .iter()
+                .all(|(k, v)| matches!(k, Cow::Owned(_)) && v.is_owned()),
+        }
+    }
+
+    /// Converts to a `BEValue`, copying whatever is still borrowed.
+    pub fn into_owned(self) -> BEValue {
+        match self {
+            BEValueCow::Integer(i) => BEValue::BEInteger(i),
+            BEValueCow::String(bytes) => BEValue::BEString(bytes.into_owned().into()),
+            BEValueCow::List(list) => {
+                BEValue::BEList(list.into_iter().map(Self::into_owned).collect())
+            }
+            BEValueCow::Dict(dict) => BEValue::BEDict(
+                dict.into_iter()
+                    .map(|(k, v)| (k.into(), v.into_owned()))
+                    .collect(),
+            ),
+            BEValueCow::Invalid(bytes) => BEValue::BEInvalid(bytes.into_owned()),
+        }
+    }
+}
+
+impl<'a> From<BEValueCow<'a>> for BEValue {
+    fn from(value: BEValueCow<'a>) -> Self {
+        value.into_owned()
+    }
+}
+
+/// Borrows every string and key of `value`.
+impl<'a> From<&'a BEValue> for BEValueCow<'a> {
+    fn from(value: &'a BEValue) -> Self {
+        match value {
+            BEValue::BEInteger(i) => BEValueCow::Integer(*i),
+            BEValue::BEString(bytes) => BEValueCow::String(Cow::Borrowed(bytes)),
+            BEValue::BEList(list) => BEValueCow::List(list.iter().map(Self::from).collect()),
+            BEValue::BEDict(dict) => BEValueCow::Dict(
+                dict.iter()
+                    .map(|(k, v)| (Cow::Borrowed(&k[..]), Self::from(v)))
+                    .collect(),
+            ),
+            BEValue::BEInvalid(bytes) => BEValueCow::Invalid(Cow::Borrowed(bytes)),
+        }
+    }
+}
+
+/// Takes ownership of `value`'s strings without copying them.
+impl From<BEValue> for BEValueCow<'static> {
+    fn from(value: BEValue) -> Self {
+        match value {
+            BEValue::BEInteger(i) => BEValueCow::Integer(i),
+            BEValue::BEString(bytes) => BEValueCow::String(Cow::Owned(bytes.into_vec())),
+            BEValue::BEList(list) => BEValueCow::List(list.into_iter().map(Self::from).collect()),
+            BEValue::BEDict(dict) => BEValueCow::Dict(
+                dict.into_iter()
+                    .map(|(k, v)| (Cow::Owned(k.into_vec()), Self::from(v)))
+                    .collect(),
+            ),
+            BEValue::BEInvalid(bytes) => BEValueCow::Invalid(Cow::Owned(bytes)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reader::BESliceReader;
+
+    #[test]
+    fn test_slice_reader_borrows() {
+        let input = b"d1:ali-1e3:fooe1:bd1:c1:xeee";
+        let value = BESliceReader::new(input).next_value_cow().unwrap().unwrap();
+        assert!(!value.is_owned());
+        assert_eq!(
+            Some(-1),
+            value.get("a").unwrap().as_list().unwrap()[0].as_int()
+        );
+        assert_eq!(
+            Some("foo"),
+            value.get("a").unwrap().as_list().unwrap()[1].as_str()
+        );
+        match value.get("b").unwrap().get("c") {
+            Some(BEValueCow::String(Cow::Borrowed(bytes))) => {
+                assert_eq!(input[24..].as_ptr(), bytes.as_ptr())
+            }
+            other => panic!("{:?}", other),
+        }
+
+        let owned = BESliceReader::new(input).next_value().unwrap().unwrap();
+        assert_eq!(owned, value.clone().into_owned());
+        assert_eq!(value, BEValueCow::from(&owned));
+        assert!(BEValueCow::from(owned).is_owned());
+    }
+
+    #[test]
+    fn test_slice_reader_errors() {
+        // The same rules apply as for `next_value`.
+        let mut reader = BESliceReader::new(b"d1:bi1e1:ai2ee");
+        assert!(matches!(
+            reader.next_value_cow(),
+            Err(crate::Error::KeysOutOfOrder(_))
+        ));
+        let mut reader = BESliceReader::new(b"");
+        assert_eq!(None, reader.next_value_cow().unwrap());
+    }
+}
@@ -0,0 +1,197 @@
+//! Fallible navigation through a `BEValue` that remembers where it has been.
+//!
+//! Indexing a `BEValue` panics on a missing key or wrong type, which suits tests and scripts.
+//! `BECursor` returns errors instead, naming the full path that failed.
+//!
+//! ```
+//! use beeenn::{Error, BEReader};
+//!
+//! let value = BEReader::new(&b"d4:infod5:filesld6:lengthi7eeeee"[..])
+//!     .next_value()
+//!     .unwrap()
+//!     .unwrap();
+//! let cursor = value.cursor();
+//! let length = cursor.key("info")?.key("files")?.index(0)?.key("length")?.int()?;
+//! assert_eq!(7, length);
+//!
+//! match cursor.key("info")?.key("files")?.index(1) {
+//!     Err(Error::MissingKey(path)) => assert_eq!("info.files.1", path),
+//!     _ => unreachable!(),
+//! }
+//! # Ok::<(), Error>(())
+//! ```
+
+use crate::error::Error;
+use crate::extract::FromBEValue;
+use crate::value::BEValue;
+use crate::walk::push_component;
+
+/// A position in a `BEValue` tree, with the dotted path that led there.
+#[derive(Clone, Debug)]
+pub struct BECursor<'a> {
+    value: &'a BEValue,
+    path: String,
+}
+
+impl<'a> BECursor<'a> {
+    /// A cursor at the root of `value`.
+    pub fn new(value: &'a BEValue) -> Self {
+        BECursor {
+            value,
+            path: String::new(),
+        }
+    }
+
+    /// The value under the cursor.
+    pub fn value(&self) -> &'a BEValue {
+        self.value
+    }
+
+    /// The dotted path from the root to the cursor. Empty at the root.
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn child_path(&self, component: &str) -> String {
+        let mut path = self.path.clone();
+        push_component(&mut path, component);
+        path
+    }
+
+    fn wrong_type(&self, expected: &'static str) -> Error {
+        Error::WrongType(self.path.clone(), expected)
+    }
+
+    /// Moves to the value stored under `key` in a dict.
+    ///
+    /// Returns `Error::WrongType` if this isn't a dict and `Error::MissingKey` if the key
+    /// isn't there.
+    pub fn key<K: AsRef<[u8]>>(&self, key: K) -> Result<BECursor<'a>, Error> {
+        let key = key.as_ref();
+        self.try_key(key)?
+            .ok_or_else(|| Error::MissingKey(self.child_path(&String::from_utf8_lossy(key))))
+    }
+
+    /// Like `key`, but returns `None` if the key isn't there.
+    pub fn try_key<K: AsRef<[u8]>>(&self, key: K) -> Result<Option<BECursor<'a>>, Error> {
+        if !self.value.is_dict() {
+            return Err(self.wrong_type("dict"));
+        }
+        let key = key.as_ref();
+        let path = self.child_path(&String::from_utf8_lossy(key));
+        Ok(self.value.get(key).map(|value| BECursor { value, path }))
+    }
+
+    /// Moves to element `index` of a list.
+    ///
+    /// Returns `Error::WrongType` if this isn't a list and `Error::MissingKey` if the list is
+    /// too short.
+    pub fn index(&self, index: usize) -> Result<BECursor<'a>, Error> {
+        let list = match self.value {
+            BEValue::BEList(list) => list,
+            _ => return Err(self.wrong_type("list")),
+        };
+        let path = self.child_path(&index.to_string());
+        match list.get(index) {
+            Some(value) => Ok(BECursor { value, path }),
+            None => Err(Error::MissingKey(path)),
+        }
+    }
+
+    /// Returns a cursor for each element of a list.
+    pub fn elements(&self) -> Result<Vec<BECursor<'a>>, Error> {
+        match self.value {
+            BEValue::BEList(list) => Ok(list
+                .iter()
+                .enumerate()
+                .map(|(i, value)| BECursor {
+                    value,
+                    path: self.child_path(&i.to_string()),
+                })
+                .collect()),
+            _ => Err(self.wrong_type("list")),
+        }
+    }
+
+    /// Converts the value under the cursor, as `BEValue::extract` does.
+    pub fn get<T: FromBEValue<'a>>(&self) -> Result<T, Error> {
+        T::from_bevalue(self.value).ok_or_else(|| self.wrong_type(T::EXPECTED))
+    }
+
+    /// Shorthands for the common conversions.
+    pub fn int(&self) -> Result<i64, Error> {
+        self.get()
+    }
+
+    pub fn bytes(&self) -> Result<&'a [u8], Error> {
+        self.get()
+    }
+
+    pub fn str(&self) -> Result<&'a str, Error> {
+        self.get()
+    }
+}
+
+impl BEValue {
+    /// Returns a `BECursor` at this value.
+    pub fn cursor(&self) -> BECursor<'_> {
+        BECursor::new(self)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::BEReader;
+
+    fn torrent() -> BEValue {
+        BEReader::new(&b"d4:infod5:filesld6:lengthi7e4:pathl1:a1:beee4:name3:fooee"[..])
+            .next_value()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_navigation() {
+        let value = torrent();
+        let info = value.cursor().key("info").unwrap();
+        assert_eq!("info", info.path());
+        assert_eq!("foo", info.key("name").unwrap().str().unwrap());
+
+        let file = info.key("files").unwrap().index(0).unwrap();
+        assert_eq!("info.files.0", file.path());
+        assert_eq!(7u32, file.key("length").unwrap().get::<u32>().unwrap());
+
+        let path = file.key("path").unwrap().elements().unwrap();
+        assert_eq!("info.files.0.path.1", path[1].path());
+        assert_eq!(b"b", path[1].bytes().unwrap());
+        assert!(info.try_key("private").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_errors() {
+        let value = torrent();
+        let info = value.cursor().key("info").unwrap();
+
+        match info.key("missing") {
+            Err(Error::MissingKey(path)) => assert_eq!("info.missing", path),
+            other => panic!("unexpected {:?}", other),
+        }
+        match info.key("files").unwrap().index(3) {
+            Err(Error::MissingKey(path)) => assert_eq!("info.files.3", path),
+            other => panic!("unexpected {:?}", other),
+        }
+        match info.key("name").unwrap().index(0) {
+            Err(Error::WrongType(path, "list")) => assert_eq!("info.name", path),
+            other => panic!("unexpected {:?}", other),
+        }
+        match info.key("name").unwrap().key("x") {
+            Err(Error::WrongType(path, "dict")) => assert_eq!("info.name", path),
+            other => panic!("unexpected {:?}", other),
+        }
+        match info.key("name").unwrap().int() {
+            Err(Error::WrongType(path, "i64")) => assert_eq!("info.name", path),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+}
@@ -1,36 +1,323 @@
-use serde::de::{self, MapAccess, SeqAccess};
-use serde::{forward_to_deserialize_any, Deserialize};
+use serde::de::{self, DeserializeOwned, MapAccess, SeqAccess};
+use serde::Deserialize;
+use std::convert::TryFrom;
+use std::io::Read;
 
 use super::{Error, Result};
+use crate::limits::Limits;
+use crate::metrics;
+use crate::ser::{float_error, unsupported};
+use crate::syntax::{parse_integer_key, Digits};
+
+// One step into the value being deserialized. Keys borrow from the input so that tracking the
+// path is cheap; it's only formatted when an error needs it.
+enum PathSegment<'de> {
+    Key(&'de [u8]),
+    Index(usize),
+}
+
+/// What to do when a `String` or `&str` is deserialized from bytes that aren't valid UTF-8.
+///
+/// Applies to dict keys as well as values.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Utf8Policy {
+    /// Fail with `Error::Utf8Error`.
+    #[default]
+    Reject,
+    /// Replace invalid sequences with U+FFFD. The result can't borrow from the input, so a `&str`
+    /// target fails with `Error::InvalidType`.
+    Lossy,
+    /// Give the raw bytes to the target instead. Targets that don't accept bytes fail with
+    /// `Error::InvalidType` or `Error::InvalidValue`.
+    Bytes,
+}
 
+/// Deserializes bencode from a byte slice.
+///
+/// Dict keys match struct fields only if they are byte for byte the same as the field's name or
+/// one of its `#[serde(alias)]`es. Maps with integer keys, such as `HashMap<u32, T>`, read each
+/// key as a decimal string.
 pub struct Deserializer<'de> {
     bytes: &'de [u8],
+    // The length of the whole input, to turn what's left of `bytes` into an offset.
+    input_len: usize,
+
+    // Where we are in the value, for error messages.
+    path: Vec<PathSegment<'de>>,
+
+    // Only accept 0 and 1 for bools.
+    strict_bool: bool,
+
+    utf8_policy: Utf8Policy,
+
+    // Reject keys that don't name a field of the struct being deserialized.
+    deny_unknown_fields: bool,
+
+    // Set while a dict key is deserialized, so that integer targets parse it from the string.
+    in_key: bool,
+
+    limits: Limits,
+    // Containers currently open, and values started, counting the top-level value.
+    depth: usize,
+    items: usize,
 }
 
 impl<'de> Deserializer<'de> {
+    /// Creates a deserializer with the default `Limits`.
     pub fn from_bytes(bytes: &'de [u8]) -> Self {
-        Deserializer { bytes }
+        Deserializer {
+            bytes,
+            input_len: bytes.len(),
+            path: Vec::new(),
+            strict_bool: false,
+            utf8_policy: Utf8Policy::Reject,
+            deny_unknown_fields: false,
+            in_key: false,
+            limits: Limits::default(),
+            depth: 0,
+            items: 1,
+        }
+    }
+
+    /// When `deny` is set, a dict key that doesn't name a field of the struct being deserialized
+    /// is an `Error::UnknownField`, as if every struct had `#[serde(deny_unknown_fields)]`. Maps
+    /// deserialized into other types, such as `HashMap`, are not affected.
+    pub fn deny_unknown_fields(mut self, deny: bool) -> Self {
+        self.deny_unknown_fields = deny;
+        self
+    }
+
+    /// Sets the limits on depth, string length and item count.
+    ///
+    /// The deserializer already holds all of its input, so `max_total_bytes` is only checked by
+    /// `from_bytes_with` and `from_reader_with`.
+    pub fn limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// When `strict` is set, bools must be encoded as `i0e` or `i1e`, and any other integer is an
+    /// `Error::InvalidBool`. By default, any nonzero integer is `true`.
+    pub fn strict_bool(mut self, strict: bool) -> Self {
+        self.strict_bool = strict;
+        self
+    }
+
+    /// Sets what happens when a string target gets invalid UTF-8. The default is
+    /// `Utf8Policy::Reject`.
+    pub fn utf8_policy(mut self, policy: Utf8Policy) -> Self {
+        self.utf8_policy = policy;
+        self
+    }
+
+    /// Consumes the next value without building anything, applying the same syntax checks and
+    /// limits as deserializing it would.
+    ///
+    /// Useful in a custom `DeserializeSeed` or `Visitor` that wants to discard part of its input.
+    pub fn skip_value(&mut self) -> Result<()> {
+        match self.peek_byte()? {
+            // Integers must fit one of the types `deserialize_any` would produce.
+            b'i' if self.bytes.get(1) == Some(&b'-') => self.parse_signed().map(drop),
+            b'i' => self.parse_unsigned().map(drop),
+            b'0'..=b'9' | b'-' => self.parse_bytes().map(drop),
+            b'l' => {
+                let start = self.offset();
+                self.next_byte()?;
+                self.enter_container()?;
+                while self.peek_in_container("list", start)? != b'e' {
+                    self.count_item()?;
+                    self.skip_value()?;
+                }
+                self.next_byte()?;
+                self.leave_container();
+                Ok(())
+            }
+            b'd' => {
+                let start = self.offset();
+                self.next_byte()?;
+                self.enter_container()?;
+                while self.peek_in_container("dict", start)? != b'e' {
+                    self.count_item()?;
+                    let key = self.parse_bytes()?;
+                    if matches!(self.bytes.first(), None | Some(b'e')) {
+                        return Err(Error::MissingValueForKey(
+                            String::from_utf8_lossy(key).into_owned(),
+                        ));
+                    }
+                    self.count_item()?;
+                    self.skip_value()?;
+                }
+                self.next_byte()?;
+                self.leave_container();
+                Ok(())
+            }
+            mismatch => Err(Error::UnrecognizedPrefix(mismatch)),
+        }
+    }
+
+    /// Checks that all of the input was consumed.
+    pub fn end(&self) -> Result<()> {
+        if self.bytes.is_empty() {
+            Ok(())
+        } else {
+            Err(Error::TrailingInput)
+        }
     }
 }
 
+/// Deserializes a `T` from `bytes` with the default `Limits`.
+///
+/// Input deeper than 256 levels, with a string over 64 MiB, with more than 10 million values,
+/// or over 256 MiB in total is rejected. Earlier versions had no limits; to decode such input,
+/// call `from_bytes_with(bytes, Limits::unlimited())`.
+///
+/// Fields that are `&str` or `&[u8]` borrow from `bytes` when marked `#[serde(borrow)]`.
+///
+/// ```
+/// use serde::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Torrent<'a> {
+///     announce: &'a str,
+///     #[serde(rename = "announce-list", default)]
+///     announce_list: Vec<Vec<String>>,
+///     #[serde(borrow)]
+///     info: Info<'a>,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Info<'a> {
+///     name: &'a str,
+///     #[serde(rename = "piece length")]
+///     piece_length: u64,
+/// }
+///
+/// let bytes = b"d8:announce5:udp:a13:announce-listll5:udp:ael5:udp:b5:udp:cee\
+///               4:infod4:name3:foo12:piece lengthi16384eee";
+/// let torrent: Torrent = beeenn::from_bytes(bytes).unwrap();
+/// assert_eq!("udp:a", torrent.announce);
+/// assert_eq!(vec!["udp:b", "udp:c"], torrent.announce_list[1]);
+/// assert_eq!(("foo", 16384), (torrent.info.name, torrent.info.piece_length));
+/// ```
 pub fn from_bytes<'a, T>(bytes: &'a [u8]) -> Result<T>
 where
     T: Deserialize<'a>,
 {
-    let mut deserializer = Deserializer::from_bytes(bytes);
-    let t = T::deserialize(&mut deserializer)?;
-    if deserializer.bytes.is_empty() {
-        Ok(t)
+    from_bytes_with(bytes, Limits::default())
+}
+
+/// Deserializes a `T` from `bytes`, rejecting input that exceeds `limits`.
+pub fn from_bytes_with<'a, T>(bytes: &'a [u8], limits: Limits) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    decode(bytes, limits, |_| Error::TrailingInput)
+}
+
+/// Deserializes a `T` from one datagram, such as a DHT message received over UDP, with
+/// `Limits::datagram()`.
+///
+/// The datagram must hold exactly one value. Anything after it is an `Error::TrailingDatagram`
+/// giving the size of the value and of the whole datagram, which is worth logging when peers
+/// send malformed packets.
+///
+/// ```
+/// use beeenn::{from_datagram, Error};
+/// use std::collections::BTreeMap;
+///
+/// let ping: BTreeMap<String, String> = from_datagram(b"d1:q4:ping1:y1:qe").unwrap();
+/// assert_eq!("ping", ping["q"]);
+///
+/// match from_datagram::<BTreeMap<String, String>>(b"d1:y1:qede") {
+///     Err(Error::TrailingDatagram { parsed, received }) => assert_eq!((8, 10), (parsed, received)),
+///     other => panic!("{:?}", other),
+/// }
+/// ```
+pub fn from_datagram<'a, T>(datagram: &'a [u8]) -> Result<T>
+where
+    T: Deserialize<'a>,
+{
+    decode(datagram, Limits::datagram(), |parsed| {
+        Error::TrailingDatagram {
+            parsed,
+            received: datagram.len(),
+        }
+    })
+}
+
+// Deserializes the single value in `bytes`. `trailing` makes the error for input left after it,
+// given the number of bytes the value used.
+fn decode<'a, T, F>(bytes: &'a [u8], limits: Limits, trailing: F) -> Result<T>
+where
+    T: Deserialize<'a>,
+    F: FnOnce(usize) -> Error,
+{
+    let mut deserializer = Deserializer::from_bytes(bytes).limits(limits);
+    let result = if bytes.len() > limits.max_total_bytes {
+        Err(Error::InputTooLong(limits.max_total_bytes))
     } else {
-        Err(Error::TrailingInput)
-    }
+        T::deserialize(&mut deserializer).and_then(|t| match deserializer.end() {
+            Ok(()) => Ok(t),
+            Err(_) => Err(trailing(deserializer.offset())),
+        })
+    };
+    metrics::report_result(&result, bytes.len(), deserializer.items);
+    result
+}
+
+/// Reads all of `reader` and deserializes a `T` from it, rejecting input that exceeds `limits`.
+///
+/// At most `max_total_bytes + 1` bytes are read, so an endless reader can't exhaust memory.
+pub fn from_reader_with<R, T>(reader: R, limits: Limits) -> Result<T>
+where
+    R: Read,
+    T: DeserializeOwned,
+{
+    let mut bytes = Vec::new();
+    let max = limits.max_total_bytes as u64;
+    if let Err(err) = reader.take(max.saturating_add(1)).read_to_end(&mut bytes) {
+        let err = Error::from(err);
+        metrics::report(|metrics| metrics.error(&err));
+        return Err(err);
+    }
+    from_bytes_with(&bytes, limits)
 }
 
 impl<'de> Deserializer<'de> {
+    // The dotted path to the current value, e.g. "info.files.0.length".
+    fn path_string(&self) -> String {
+        let segments: Vec<String> = self
+            .path
+            .iter()
+            .map(|segment| match segment {
+                PathSegment::Key(key) => String::from_utf8_lossy(key).into_owned(),
+                PathSegment::Index(i) => i.to_string(),
+            })
+            .collect();
+        segments.join(".")
+    }
+
     fn peek_byte(&mut self) -> Result<u8> {
         self.bytes.first().copied().ok_or(Error::Eof)
     }
 
+    // Peeks inside the container that started at `start`, where running out of input means it was
+    // never terminated.
+    fn peek_in_container(&self, kind: &'static str, start: usize) -> Result<u8> {
+        self.bytes
+            .first()
+            .copied()
+            .ok_or(Error::UnterminatedContainer {
+                kind,
+                offset: start,
+            })
+    }
+
+    // How far into the input we are.
+    fn offset(&self) -> usize {
+        self.input_len - self.bytes.len()
+    }
+
     fn next_byte(&mut self) -> Result<u8> {
         let byte = self.peek_byte()?;
         self.bytes = &self.bytes[1..];
@@ -38,48 +325,35 @@ impl<'de> Deserializer<'de> {
     }
 
     fn parse_bytes(&mut self) -> Result<&'de [u8]> {
-        let length = self.parse_raw_integer()? as usize;
+        if self.peek_byte()? == b'-' {
+            return Err(Error::NegativeStringLength);
+        }
+        let length = self.parse_raw_integer()?;
+        let length = usize::try_from(length).map_err(|_| Error::LengthOverflow(length))?;
+        if length > self.limits.max_string_len {
+            return Err(Error::StringTooLong(length, self.limits.max_string_len));
+        }
         let colon = self.next_byte()?;
         if colon != b':' {
             return Err(Error::MissingColon(colon));
         }
+        // Checked before slicing, so an absurd length is just a truncated string.
+        if length > self.bytes.len() {
+            return Err(Error::Eof);
+        }
         let result = &self.bytes[..length];
         self.bytes = &self.bytes[length..];
         Ok(result)
     }
 
-    fn parse_str(&mut self) -> Result<&'de str> {
-        let bytes = self.parse_bytes()?;
-        Ok(std::str::from_utf8(bytes)?)
-    }
-
     // Because we also use this to parse strings,
     // parse raw_integer should NOT check/consume the terminating 'e'.
     fn parse_raw_integer(&mut self) -> Result<u64> {
-        let mut val = 0u64;
-        let mut leading_zero = false;
-        let mut got_digit = false;
-        loop {
-            let b = self.peek_byte()?;
-            if !b.is_ascii_digit() {
-                break;
-            }
-            if b == b'0' && val == 0 {
-                if leading_zero {
-                    return Err(Error::UnexpectedZeroPrefix);
-                }
-                leading_zero = true;
-            }
-            got_digit = true;
-            val = val * 10 + (self.next_byte()? - b'0') as u64
-        }
-        if val != 0 && leading_zero {
-            return Err(Error::UnexpectedZeroPrefix);
-        }
-        if !got_digit {
-            return Err(Error::NoDigitsInNumber);
+        let mut digits = Digits::default();
+        while self.peek_byte()?.is_ascii_digit() {
+            digits.push(self.next_byte()?)?;
         }
-        Ok(val)
+        digits.finish()
     }
 
     fn parse_unsigned(&mut self) -> Result<u64> {
@@ -99,14 +373,15 @@ impl<'de> Deserializer<'de> {
         Ok(val)
     }
 
-    fn parse_signed(&mut self) -> Result<i64> {
+    // Parses any integer, signed or not, whose magnitude fits in a u64.
+    fn parse_integer(&mut self) -> Result<i128> {
         let b = self.next_byte()?;
         if b != b'i' {
             return Err(Error::UnexpectedPrefix(b as char, 'i'));
         }
 
         let sign = self.peek_byte()?;
-        let multiplier: i64 = if sign == b'-' {
+        let multiplier: i128 = if sign == b'-' {
             self.next_byte()?;
             -1
         } else {
@@ -114,31 +389,94 @@ impl<'de> Deserializer<'de> {
         };
 
         let uval = self.parse_raw_integer()?;
+        if multiplier < 0 && uval == 0 {
+            return Err(Error::NegativeZero);
+        }
         if self.next_byte()? != b'e' {
             return Err(Error::ExpectedNumEnd);
         }
-        Ok(multiplier * uval as i64)
+        Ok(multiplier * uval as i128)
+    }
+
+    // Called after reading the prefix of a list or dict. Must be paired with `leave_container`.
+    fn enter_container(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > self.limits.max_depth {
+            return Err(Error::DepthLimitExceeded(self.limits.max_depth));
+        }
+        Ok(())
+    }
+
+    fn leave_container(&mut self) {
+        self.depth -= 1;
+    }
+
+    // Called before each value inside a container.
+    fn count_item(&mut self) -> Result<()> {
+        self.items += 1;
+        if self.items > self.limits.max_items {
+            return Err(Error::TooManyItems(self.limits.max_items));
+        }
+        Ok(())
+    }
+
+    fn parse_signed(&mut self) -> Result<i64> {
+        let value = self.parse_integer()?;
+        i64::try_from(value).map_err(|_| Error::IntegerOutOfRange {
+            value,
+            target: "i64",
+            path: self.path_string(),
+        })
     }
 }
 
-impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident($t:ty),)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: de::Visitor<'de>,
+            {
+                let value = if std::mem::take(&mut self.in_key) {
+                    parse_integer_key(self.parse_bytes()?)?
+                } else {
+                    self.parse_integer()?
+                };
+                match <$t>::try_from(value) {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(Error::IntegerOutOfRange {
+                        value,
+                        target: stringify!($t),
+                        path: self.path_string(),
+                    }),
+                }
+            }
+        )*
+    };
+}
+
+impl<'de> de::Deserializer<'de> for &mut Deserializer<'de> {
     type Error = Error;
 
-    forward_to_deserialize_any!(i8 i16 i32 i64 u8 u16 u32 u64);
+    deserialize_integer! {
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+    }
 
     fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        // println!(
-        //     "ANY:[{}] {:?}",
-        //     self.bytes.len(),
-        //     String::from_utf8_lossy(&self.bytes[..20])
-        // );
         match self.peek_byte()? {
             b'd' => self.deserialize_map(visitor),
             b'i' => {
-                if self.bytes[1] == b'-' {
+                if self.bytes.get(1) == Some(&b'-') {
                     visitor.visit_i64(self.parse_signed()?)
                 } else {
                     visitor.visit_u64(self.parse_unsigned()?)
@@ -154,35 +492,63 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_bool(self.parse_unsigned()? != 0)
+        if self.strict_bool {
+            match self.parse_signed()? {
+                0 => visitor.visit_bool(false),
+                1 => visitor.visit_bool(true),
+                val => Err(Error::InvalidBool(val)),
+            }
+        } else {
+            visitor.visit_bool(self.parse_unsigned()? != 0)
+        }
     }
 
     fn deserialize_f32<V>(self, _visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        Err(float_error())
     }
 
     fn deserialize_f64<V>(self, _visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        Err(float_error())
     }
 
-    fn deserialize_char<V>(self, _visitor: V) -> Result<V::Value>
+    // Read from a one-character string.
+    fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        let bytes = self.parse_bytes()?;
+        let s = std::str::from_utf8(bytes)?;
+        let mut chars = s.chars();
+        match (chars.next(), chars.next()) {
+            (Some(c), None) => visitor.visit_char(c),
+            _ => Err(de::Error::invalid_value(
+                de::Unexpected::Str(s),
+                &"a character",
+            )),
+        }
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        visitor.visit_borrowed_str(self.parse_str()?)
+        let bytes = self.parse_bytes()?;
+        match std::str::from_utf8(bytes) {
+            Ok(s) => visitor.visit_borrowed_str(s),
+            Err(err) => match self.utf8_policy {
+                Utf8Policy::Reject => Err(err.into()),
+                Utf8Policy::Lossy => {
+                    visitor.visit_string(String::from_utf8_lossy(bytes).into_owned())
+                }
+                Utf8Policy::Bytes => visitor.visit_borrowed_bytes(bytes),
+            },
+        }
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
@@ -217,96 +583,99 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        Err(unsupported("()"))
     }
 
-    fn deserialize_unit_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    fn deserialize_unit_struct<V>(self, name: &'static str, _visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        Err(unsupported(&format!("the unit struct {}", name)))
     }
 
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, _visitor: V) -> Result<V::Value>
+    // Newtypes are read as the value they wrap.
+    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        visitor.visit_newtype_struct(self)
     }
 
-    fn deserialize_seq<V>(mut self, visitor: V) -> Result<V::Value>
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
+        let start = self.offset();
         if self.next_byte()? != b'l' {
             return Err(Error::ExpectedList);
         }
+        self.enter_container()?;
 
-        let value = visitor.visit_seq(List::new(&mut self))?;
+        let value = visitor.visit_seq(List::new(&mut *self, start))?;
 
-        if self.next_byte()? != b'e' {
+        if self.peek_in_container("list", start)? != b'e' {
             return Err(Error::ExpectedListEnd);
         }
+        self.next_byte()?;
+        self.leave_container();
         Ok(value)
     }
 
-    fn deserialize_tuple<V>(self, _len: usize, _visitor: V) -> Result<V::Value>
+    // Tuples and tuple structs are read from lists. A list with extra elements fails with
+    // `ExpectedListEnd`.
+    fn deserialize_tuple<V>(self, _len: usize, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        self.deserialize_seq(visitor)
     }
 
     fn deserialize_tuple_struct<V>(
         self,
         _name: &'static str,
         _len: usize,
-        _visitor: V,
+        visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        self.deserialize_seq(visitor)
     }
 
-    fn deserialize_map<V>(mut self, visitor: V) -> Result<V::Value>
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        if self.next_byte()? != b'd' {
-            return Err(Error::ExpectedMap);
-        }
-
-        let value = visitor.visit_map(Map::new(&mut self))?;
-
-        if self.next_byte()? != b'e' {
-            return Err(Error::ExpectedMapEnd);
-        }
-        Ok(value)
+        self.deserialize_dict(visitor, None)
     }
 
     fn deserialize_struct<V>(
         self,
         _name: &'static str,
-        _fields: &'static [&'static str],
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        let fields = if self.deny_unknown_fields {
+            Some(fields)
+        } else {
+            None
+        };
+        self.deserialize_dict(visitor, fields)
     }
 
     fn deserialize_enum<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variants: &'static [&'static str],
         _visitor: V,
     ) -> Result<V::Value>
     where
         V: de::Visitor<'de>,
     {
-        todo!()
+        Err(unsupported(&format!("the enum {}", name)))
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
@@ -320,18 +689,54 @@ impl<'de, 'a> de::Deserializer<'de> for &'a mut Deserializer<'de> {
     where
         V: de::Visitor<'de>,
     {
-        // TODO: write a unit test for this.
-        self.deserialize_any(visitor)
+        self.skip_value()?;
+        visitor.visit_unit()
+    }
+}
+
+impl<'de> Deserializer<'de> {
+    // Deserializes a dict. If `fields` is given, every key must be one of them.
+    fn deserialize_dict<V>(
+        &mut self,
+        visitor: V,
+        fields: Option<&'static [&'static str]>,
+    ) -> Result<V::Value>
+    where
+        V: de::Visitor<'de>,
+    {
+        let start = self.offset();
+        if self.next_byte()? != b'd' {
+            return Err(Error::ExpectedMap);
+        }
+        self.enter_container()?;
+
+        let value = visitor
+            .visit_map(Map::new(&mut *self, fields, start))
+            .map_err(|err| err.with_path(|| self.path_string()))?;
+
+        if self.peek_in_container("dict", start)? != b'e' {
+            return Err(Error::ExpectedMapEnd);
+        }
+        self.next_byte()?;
+        self.leave_container();
+        Ok(value)
     }
 }
 
 struct List<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
+    index: usize,
+    // The offset of the 'l'.
+    start: usize,
 }
 
 impl<'a, 'de> List<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        List { de }
+    fn new(de: &'a mut Deserializer<'de>, start: usize) -> Self {
+        List {
+            de,
+            index: 0,
+            start,
+        }
     }
 }
 
@@ -342,21 +747,47 @@ impl<'de, 'a> SeqAccess<'de> for List<'a, 'de> {
     where
         T: de::DeserializeSeed<'de>,
     {
-        if self.de.peek_byte()? == b'e' {
+        if self.de.peek_in_container("list", self.start)? == b'e' {
             return Ok(None);
         }
+        self.de.count_item()?;
 
-        seed.deserialize(&mut *self.de).map(Some)
+        self.de.path.push(PathSegment::Index(self.index));
+        let result = seed
+            .deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|err| err.with_path(|| self.de.path_string()));
+        self.de.path.pop();
+        self.index += 1;
+        result
     }
 }
 
 struct Map<'a, 'de: 'a> {
     de: &'a mut Deserializer<'de>,
+
+    // The raw bytes of the most recent key, used for the path of its value.
+    key: &'de [u8],
+
+    // The only keys allowed, when unknown fields are denied.
+    fields: Option<&'static [&'static str]>,
+
+    // The offset of the 'd'.
+    start: usize,
 }
 
 impl<'a, 'de> Map<'a, 'de> {
-    fn new(de: &'a mut Deserializer<'de>) -> Self {
-        Map { de }
+    fn new(
+        de: &'a mut Deserializer<'de>,
+        fields: Option<&'static [&'static str]>,
+        start: usize,
+    ) -> Self {
+        Map {
+            de,
+            key: &[],
+            fields,
+            start,
+        }
     }
 }
 
@@ -367,17 +798,52 @@ impl<'de, 'a> MapAccess<'de> for Map<'a, 'de> {
     where
         K: de::DeserializeSeed<'de>,
     {
-        if self.de.peek_byte()? == b'e' {
+        if self.de.peek_in_container("dict", self.start)? == b'e' {
             return Ok(None);
         }
-
-        seed.deserialize(&mut *self.de).map(Some)
+        self.de.count_item()?;
+
+        // Parse the key from a copy of the input so that we know it regardless of how the seed
+        // deserializes it. Keys must be strings, so an error here is the real one.
+        self.key = Deserializer::from_bytes(self.de.bytes)
+            .limits(self.de.limits)
+            .parse_bytes()
+            .map_err(|err| err.with_path(|| self.de.path_string()))?;
+        if let Some(fields) = self.fields {
+            if !fields.iter().any(|field| field.as_bytes() == self.key) {
+                return Err(Error::UnknownField {
+                    key: String::from_utf8_lossy(self.key).into_owned(),
+                    path: self.de.path_string(),
+                });
+            }
+        }
+        self.de.in_key = true;
+        let result = seed
+            .deserialize(&mut *self.de)
+            .map(Some)
+            .map_err(|err| err.with_path(|| self.de.path_string()));
+        self.de.in_key = false;
+        result
     }
 
     fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value>
     where
         V: de::DeserializeSeed<'de>,
     {
-        seed.deserialize(&mut *self.de)
+        // A dict that ends, or input that stops, right after a key would otherwise fail later
+        // with an error about whatever type the value was expected to be.
+        if matches!(self.de.bytes.first(), None | Some(b'e')) {
+            return Err(Error::MissingValueForKey(
+                String::from_utf8_lossy(self.key).into_owned(),
+            ));
+        }
+
+        self.de.count_item()?;
+        self.de.path.push(PathSegment::Key(self.key));
+        let result = seed
+            .deserialize(&mut *self.de)
+            .map_err(|err| err.with_path(|| self.de.path_string()));
+        self.de.path.pop();
+        result
     }
 }
@@ -0,0 +1,419 @@
+//! Encoding a `BEValue` back into bencode.
+//!
+//! Canonical bencode requires dict keys to be unique and sorted by their raw bytes. `BEReader`
+//! enforces this, but a `BEValue` built by hand may not, so the encoder can either sort keys as it
+//! goes or refuse to encode a non-canonical value.
+
+use std::cmp::Ordering;
+use std::io::Write;
+
+use crate::be_bytes::BEBytes;
+use crate::error::Error;
+use crate::keys::cmp_keys;
+use crate::value::BEValue;
+
+/// How the encoder treats dicts whose keys are not already in canonical order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum KeyOrder {
+    /// Sort dict keys while encoding.
+    Sort,
+    /// Return an error if a dict's keys are not already sorted.
+    Require,
+}
+
+/// A reason that a `BEValue` cannot be encoded canonically as-is.
+///
+/// Each violation carries the dotted path (see `BEValue::get_path`) of the offending key.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CanonicalViolation {
+    /// The key sorts before the key preceding it.
+    UnsortedKey(String),
+    /// The key is the same as the key preceding it. A repeated key elsewhere in the dict is
+    /// reported as an `UnsortedKey`, since that dict can't be sorted either.
+    DuplicateKey(String),
+    /// The value is a `BEInvalid` placeholder.
+    InvalidValue(String),
+}
+
+fn child_path(path: &str, component: &str) -> String {
+    if path.is_empty() {
+        component.to_string()
+    } else {
+        format!("{}.{}", path, component)
+    }
+}
+
+fn key_path(path: &str, key: &[u8]) -> String {
+    child_path(path, &String::from_utf8_lossy(key))
+}
+
+impl BEValue {
+    /// Returns true if the value can be encoded without reordering any dict keys.
+    pub fn is_canonical(&self) -> bool {
+        self.canonical_violations().is_empty()
+    }
+
+    /// Walks the tree and reports every dict key that is out of order or duplicated.
+    pub fn canonical_violations(&self) -> Vec<CanonicalViolation> {
+        let mut violations = Vec::new();
+        self.collect_violations("", &mut violations);
+        violations
+    }
+
+    /// Returns the first of the `canonical_violations` as the error `encode` with
+    /// `KeyOrder::Require` would return for it, without encoding anything.
+    ///
+    /// ```
+    /// use beeenn::{BEValue, Error};
+    ///
+    /// let mut value = BEValue::BEDict(vec![("b".into(), 1.into()), ("a".into(), 2.into())]);
+    /// assert_eq!(
+    ///     Err(Error::KeysOutOfOrder("a".to_string())),
+    ///     value.assert_canonical()
+    /// );
+    /// value = BEValue::BEDict(vec![("a".into(), 2.into()), ("b".into(), 1.into())]);
+    /// assert_eq!(Ok(()), value.assert_canonical());
+    /// ```
+    pub fn assert_canonical(&self) -> Result<(), Error> {
+        match self.canonical_violations().into_iter().next() {
+            None => Ok(()),
+            Some(CanonicalViolation::UnsortedKey(path)) => Err(Error::KeysOutOfOrder(path)),
+            Some(CanonicalViolation::DuplicateKey(path)) => Err(Error::DuplicateKey(path)),
+            Some(CanonicalViolation::InvalidValue(path)) => Err(Error::EncodeInvalidValue(path)),
+        }
+    }
+
+    fn collect_violations(&self, path: &str, violations: &mut Vec<CanonicalViolation>) {
+        match self {
+            BEValue::BEInteger(_) | BEValue::BEString(_) => {}
+            BEValue::BEInvalid(_) => {
+                violations.push(CanonicalViolation::InvalidValue(path.to_string()));
+            }
+            BEValue::BEList(list) => {
+                for (i, value) in list.iter().enumerate() {
+                    value.collect_violations(&child_path(path, &i.to_string()), violations);
+                }
+            }
+            BEValue::BEDict(dict) => {
+                for (i, (key, value)) in dict.iter().enumerate() {
+                    let path = key_path(path, key);
+                    if i > 0 {
+                        match cmp_keys(key, &dict[i - 1].0) {
+                            Ordering::Greater => {}
+                            Ordering::Equal => {
+                                violations.push(CanonicalViolation::DuplicateKey(path.clone()))
+                            }
+                            Ordering::Less => {
+                                violations.push(CanonicalViolation::UnsortedKey(path.clone()))
+                            }
+                        }
+                    }
+                    value.collect_violations(&path, violations);
+                }
+            }
+        }
+    }
+
+    /// Returns the number of bytes `encode` would produce, without encoding anything.
+    ///
+    /// For a `BEInvalid` value, this is the length of its raw bytes.
+    pub fn encoded_len(&self) -> usize {
+        match self {
+            BEValue::BEInteger(i) => i.to_string().len() + 2,
+            BEValue::BEString(s) => string_len(s),
+            BEValue::BEList(list) => 2 + list.iter().map(BEValue::encoded_len).sum::<usize>(),
+            BEValue::BEDict(dict) => {
+                2 + dict
+                    .iter()
+                    .map(|(key, value)| string_len(key) + value.encoded_len())
+                    .sum::<usize>()
+            }
+            BEValue::BEInvalid(raw) => raw.len(),
+        }
+    }
+
+    /// Encodes the value as bencode.
+    ///
+    /// Duplicate dict keys and `BEInvalid` values are always an error. Unsorted keys are sorted
+    /// or rejected according to `order`.
+    pub fn encode(&self, order: KeyOrder) -> Result<Vec<u8>, Error> {
+        let mut bytes = Vec::new();
+        self.write_to(&mut bytes, order)?;
+        Ok(bytes)
+    }
+
+    /// Encodes the value as bencode directly into `writer`.
+    ///
+    /// Errors are reported as for `encode`. Output is streamed as the tree is walked, so on error
+    /// `writer` may already hold part of the value.
+    pub fn write_to<W: Write>(&self, writer: &mut W, order: KeyOrder) -> Result<(), Error> {
+        match self {
+            BEValue::BEInteger(i) => write!(writer, "i{}e", i)?,
+            BEValue::BEString(s) => write_string(writer, s)?,
+            BEValue::BEInvalid(_) => return Err(Error::EncodeInvalidValue(String::new())),
+            BEValue::BEList(list) => {
+                writer.write_all(b"l")?;
+                for (i, value) in list.iter().enumerate() {
+                    value
+                        .write_to(writer, order)
+                        .map_err(|err| prefix_path(err, &i.to_string()))?;
+                }
+                writer.write_all(b"e")?;
+            }
+            BEValue::BEDict(dict) => {
+                let mut entries: Vec<&(BEBytes, BEValue)> = dict.iter().collect();
+                if order == KeyOrder::Sort {
+                    entries.sort_by(|a, b| cmp_keys(&a.0, &b.0));
+                }
+
+                writer.write_all(b"d")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        let prev = &entries[i - 1].0;
+                        match cmp_keys(key, prev) {
+                            Ordering::Greater => {}
+                            Ordering::Equal => {
+                                return Err(Error::DuplicateKey(key_path("", key)));
+                            }
+                            Ordering::Less => {
+                                return Err(Error::KeysOutOfOrder(key_path("", key)));
+                            }
+                        }
+                    }
+                    write_string(writer, key)?;
+                    value
+                        .write_to(writer, order)
+                        .map_err(|err| prefix_path(err, &String::from_utf8_lossy(key)))?;
+                }
+                writer.write_all(b"e")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// Paths in encoding errors are built up as the error propagates out of nested values, so that
+// successful encodes don't pay for path bookkeeping.
+fn prefix_path(err: Error, component: &str) -> Error {
+    match err {
+        Error::DuplicateKey(path) => Error::DuplicateKey(child_path(component, &path)),
+        Error::KeysOutOfOrder(path) => Error::KeysOutOfOrder(child_path(component, &path)),
+        // An invalid value reports its own, empty, path.
+        Error::EncodeInvalidValue(path) if path.is_empty() => {
+            Error::EncodeInvalidValue(component.to_string())
+        }
+        Error::EncodeInvalidValue(path) => Error::EncodeInvalidValue(child_path(component, &path)),
+        err => err,
+    }
+}
+
+// The encoded length of a string: the length prefix, the ':', and the bytes.
+pub(crate) fn string_len(s: &[u8]) -> usize {
+    s.len().to_string().len() + 1 + s.len()
+}
+
+fn write_string<W: Write>(writer: &mut W, s: &[u8]) -> Result<(), Error> {
+    write!(writer, "{}:", s.len())?;
+    writer.write_all(s)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn string(s: &str) -> BEValue {
+        BEValue::from(s)
+    }
+
+    fn unsorted() -> BEValue {
+        BEValue::BEDict(vec![
+            (b"zzz".into(), BEValue::BEInteger(1)),
+            (
+                b"info".into(),
+                BEValue::BEDict(vec![
+                    (b"b".into(), string("two")),
+                    (b"a".into(), BEValue::BEList(vec![BEValue::BEInteger(-3)])),
+                ]),
+            ),
+        ])
+    }
+
+    #[test]
+    fn test_canonical() {
+        let value = BEValue::BEDict(vec![
+            (b"a".into(), BEValue::BEInteger(1)),
+            (b"b".into(), BEValue::BEList(vec![string("x")])),
+        ]);
+        assert!(value.is_canonical());
+        assert!(BEValue::BEInteger(5).is_canonical());
+    }
+
+    #[test]
+    fn test_violations() {
+        assert_eq!(
+            vec![
+                CanonicalViolation::UnsortedKey("info".to_string()),
+                CanonicalViolation::UnsortedKey("info.a".to_string()),
+            ],
+            unsorted().canonical_violations()
+        );
+
+        let dup = BEValue::BEList(vec![BEValue::BEDict(vec![
+            (b"a".into(), BEValue::BEInteger(1)),
+            (b"a".into(), BEValue::BEInteger(2)),
+            (b"b".into(), BEValue::BEInteger(3)),
+            (b"a".into(), BEValue::BEInteger(4)),
+        ])]);
+        assert_eq!(
+            vec![
+                CanonicalViolation::DuplicateKey("0.a".to_string()),
+                CanonicalViolation::UnsortedKey("0.a".to_string()),
+            ],
+            dup.canonical_violations()
+        );
+    }
+
+    #[test]
+    fn test_assert_canonical() {
+        assert_eq!(
+            Err(Error::KeysOutOfOrder("info".to_string())),
+            unsorted().assert_canonical()
+        );
+        let dup = BEValue::BEDict(vec![
+            (b"a".into(), BEValue::BEInteger(1)),
+            (b"a".into(), BEValue::BEInteger(2)),
+        ]);
+        assert_eq!(
+            Err(dup.encode(KeyOrder::Require).unwrap_err()),
+            dup.assert_canonical()
+        );
+        let invalid = BEValue::BEList(vec![BEValue::BEInvalid(b"x".to_vec())]);
+        assert_eq!(
+            Err(Error::EncodeInvalidValue("0".to_string())),
+            invalid.assert_canonical()
+        );
+        assert_eq!(Ok(()), BEValue::from("x").assert_canonical());
+    }
+
+    #[test]
+    fn test_non_ascii_keys() {
+        let value = BEValue::BEDict(vec![
+            ("\u{3a9}".as_bytes().into(), BEValue::BEInteger(1)),
+            ("\u{f1}".as_bytes().into(), BEValue::BEInteger(2)),
+            (b"z".into(), BEValue::BEInteger(3)),
+        ]);
+        assert_eq!(
+            vec![
+                CanonicalViolation::UnsortedKey("\u{f1}".to_string()),
+                CanonicalViolation::UnsortedKey("z".to_string()),
+            ],
+            value.canonical_violations()
+        );
+        assert_eq!(
+            "d1:zi3e2:\u{f1}i2e2:\u{3a9}i1ee".as_bytes(),
+            value.encode(KeyOrder::Sort).unwrap().as_slice()
+        );
+    }
+
+    #[test]
+    fn test_encode_sort() {
+        assert_eq!(
+            b"d4:infod1:ali-3ee1:b3:twoe3:zzzi1ee".to_vec(),
+            unsorted().encode(KeyOrder::Sort).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_encoded_len() {
+        for value in [
+            unsorted(),
+            BEValue::BEInteger(-1234),
+            BEValue::BEInteger(0),
+            string(&"x".repeat(100)),
+            BEValue::BEList(vec![]),
+        ] {
+            assert_eq!(
+                value.encode(KeyOrder::Sort).unwrap().len(),
+                value.encoded_len()
+            );
+        }
+    }
+
+    #[test]
+    fn test_write_to() {
+        let mut out = Vec::new();
+        BEValue::BEList(vec![BEValue::BEInteger(7), string("seven")])
+            .write_to(&mut out, KeyOrder::Require)
+            .unwrap();
+        assert_eq!(b"li7e5:sevene".to_vec(), out);
+
+        // Errors from the writer come through as Io, keeping their kind and source.
+        let mut full = [0u8; 4];
+        let err = string("too long")
+            .write_to(&mut &mut full[..], KeyOrder::Sort)
+            .unwrap_err();
+        assert_eq!(Some(std::io::ErrorKind::WriteZero), err.io_kind());
+        let source = std::error::Error::source(&err).and_then(|e| e.source());
+        assert!(source.unwrap().downcast_ref::<std::io::Error>().is_some());
+    }
+
+    #[test]
+    fn test_nested_error_path() {
+        let value = BEValue::BEList(vec![unsorted()]);
+        match value.encode(KeyOrder::Require) {
+            Err(Error::KeysOutOfOrder(path)) => assert_eq!("0.info", path),
+            _ => panic!("expected KeysOutOfOrder"),
+        }
+
+        let value = BEValue::BEDict(vec![(b"outer".into(), unsorted())]);
+        match value.encode(KeyOrder::Sort) {
+            Ok(bytes) => assert!(bytes.starts_with(b"d5:outerd4:info")),
+            _ => panic!("expected sorted output"),
+        }
+        let dup = BEValue::BEDict(vec![(
+            b"outer".into(),
+            BEValue::BEDict(vec![
+                (b"a".into(), BEValue::BEInteger(1)),
+                (b"a".into(), BEValue::BEInteger(3)),
+            ]),
+        )]);
+        match dup.encode(KeyOrder::Sort) {
+            Err(Error::DuplicateKey(path)) => assert_eq!("outer.a", path),
+            _ => panic!("expected DuplicateKey"),
+        }
+    }
+
+    #[test]
+    fn test_invalid_value() {
+        let value = BEValue::BEDict(vec![(
+            b"info".into(),
+            BEValue::BEList(vec![BEValue::BEInvalid(b"i0".to_vec())]),
+        )]);
+        assert_eq!(
+            vec![CanonicalViolation::InvalidValue("info.0".to_string())],
+            value.canonical_violations()
+        );
+        match value.encode(KeyOrder::Sort) {
+            Err(Error::EncodeInvalidValue(path)) => assert_eq!("info.0", path),
+            _ => panic!("expected InvalidValue"),
+        }
+    }
+
+    #[test]
+    fn test_encode_require() {
+        match unsorted().encode(KeyOrder::Require) {
+            Err(Error::KeysOutOfOrder(path)) => assert_eq!("info", path),
+            _ => panic!("expected KeysOutOfOrder"),
+        }
+
+        let dup = BEValue::BEDict(vec![
+            (b"a".into(), BEValue::BEInteger(1)),
+            (b"a".into(), BEValue::BEInteger(3)),
+        ]);
+        match dup.encode(KeyOrder::Sort) {
+            Err(Error::DuplicateKey(path)) => assert_eq!("a", path),
+            _ => panic!("expected DuplicateKey"),
+        }
+    }
+}
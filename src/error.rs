@@ -0,0 +1,461 @@
+use std::fmt;
+
+use thiserror::Error as ThisError;
+
+#[cfg(feature = "value")]
+use crate::value::BEValue;
+
+/// Errors from every part of the crate: the `BEReader` DOM layer, the scanners, and the serde
+/// `Serializer` and `Deserializer`.
+///
+/// Some variants only come from one layer; the docs on each say which when it isn't obvious.
+#[derive(Debug, ThisError, PartialEq)]
+pub enum Error {
+    #[error("error from serde: {0}")]
+    Message(String),
+
+    #[error("values are nested more than {0} deep")]
+    DepthLimitExceeded(usize),
+
+    /// A repeated dict key, read by the DOM layer or written by the serializer.
+    #[error("dict key '{0}' appears more than once")]
+    DuplicateKey(String),
+
+    /// A repeated struct field, from serde.
+    #[error("duplicate field '{field}'{}", at_path(.path))]
+    DuplicateField { field: &'static str, path: String },
+
+    /// A `BEInvalid` value can't be encoded. Holds its path.
+    #[error("cannot encode the invalid value at '{0}'")]
+    EncodeInvalidValue(String),
+
+    #[error("reached end of input before finishing")]
+    Eof,
+
+    #[error("expected a 'l' to start the list")]
+    ExpectedList,
+
+    #[error("expected a 'e' to end the list")]
+    ExpectedListEnd,
+
+    #[error("expected a 'e' to end the number")]
+    ExpectedNumEnd,
+
+    #[error("expected a 'd' to start the map")]
+    ExpectedMap,
+
+    #[error("expected a 'e' to end the map")]
+    ExpectedMapEnd,
+
+    #[error("input is longer than the limit of {0} bytes")]
+    InputTooLong(usize),
+
+    /// An integer or string length too large to represent: more than an `i64` in the DOM layer,
+    /// or more than a `u64` in magnitude anywhere.
+    #[error("integer is too large to represent")]
+    IntegerOverflow,
+
+    #[error("integer {value} is out of range for {target} at '{path}'")]
+    IntegerOutOfRange {
+        value: i128,
+        target: &'static str,
+        path: String,
+    },
+
+    #[error("expected 0 or 1 for a bool, found {0}")]
+    InvalidBool(i64),
+
+    #[error("invalid length {len}, expected {expected}{}", at_path(.path))]
+    InvalidLength {
+        len: usize,
+        expected: String,
+        path: String,
+    },
+
+    #[error("invalid type: {unexpected}, expected {expected}{}", at_path(.path))]
+    InvalidType {
+        unexpected: String,
+        expected: String,
+        path: String,
+    },
+
+    #[error("invalid value: {unexpected}, expected {expected}{}", at_path(.path))]
+    InvalidValue {
+        unexpected: String,
+        expected: String,
+        path: String,
+    },
+
+    /// An error from a reader or writer. `source()` returns the `IoError`, and its `source()` the
+    /// original `io::Error`.
+    #[error("I/O error: {0}")]
+    Io(#[source] IoError),
+
+    #[error("dict keys must serialize as strings or integers")]
+    KeyMustBeString,
+
+    /// A dict key deserialized into an integer type wasn't a decimal integer.
+    #[error("dict key '{0}' is not an integer")]
+    KeyNotInteger(String),
+
+    #[cfg(feature = "value")]
+    #[error("dict keys must be strings, found {0:?}")]
+    KeyNotString(BEValue),
+
+    #[error("dict keys must be in sorted order, found '{0}'")]
+    KeysOutOfOrder(String),
+
+    #[error("string length {0} does not fit in a usize")]
+    LengthOverflow(u64),
+
+    #[error("expected colon, ':', to separate length from bytes. Found {0}")]
+    MissingColon(u8),
+
+    #[error("missing field '{field}'{}", at_path(.path))]
+    MissingField { field: &'static str, path: String },
+
+    /// Nothing exists at a path looked up with `BEValue::extract` or `BECursor`.
+    #[error("missing key '{0}'")]
+    MissingKey(String),
+
+    #[error("expected '{1}' to end the value, found '{0}'")]
+    MissingSuffixError(u8, u8),
+
+    #[error("dict key '{0}' is missing a value")]
+    MissingValueForKey(String),
+
+    #[error("string lengths cannot be negative")]
+    NegativeStringLength,
+
+    #[error("'-0' is not a valid integer")]
+    NegativeZero,
+
+    #[error("every number must have at least one digit")]
+    NoDigitsInNumber,
+
+    #[error("cannot serialize None as element {index} of a {container}")]
+    NoneInContainer {
+        container: &'static str,
+        index: usize,
+    },
+
+    #[error("cannot serialize None outside of a struct field")]
+    NoneValue,
+
+    #[error("string of {0} bytes is longer than the limit of {1}")]
+    StringTooLong(usize, usize),
+
+    #[error("value has more than {0} items")]
+    TooManyItems(usize),
+
+    #[error("trailing input remains after the value")]
+    TrailingInput,
+
+    /// A datagram held more than one value. `parsed` bytes made up the first value, out of the
+    /// `received` bytes in the datagram.
+    #[error("datagram of {received} bytes has trailing input after the value's {parsed}")]
+    TrailingDatagram { parsed: usize, received: usize },
+
+    /// The input ended where a list or dict needed another element or its closing 'e'. Running
+    /// out inside one of the elements is `Eof` instead.
+    #[error("{kind} starting at offset {offset} is never terminated")]
+    UnterminatedContainer { kind: &'static str, offset: usize },
+
+    #[error("unknown field '{key}'{}", at_path(.path))]
+    UnknownField { key: String, path: String },
+
+    /// A byte that can't appear where it was found, from the DOM layer and scanners.
+    #[error("unexpected character, '{0}'")]
+    UnexpectedCharError(char),
+
+    #[error("unrecognized prefix character, '{0}'")]
+    UnrecognizedPrefix(u8),
+
+    #[error("expected {1}, found: {0}")]
+    UnexpectedPrefix(char, char),
+
+    #[error("unexpected negative sign for unsigned value")]
+    UnexpectedSigned,
+
+    #[error("integers cannot start with '0' unless they are 0")]
+    UnexpectedZeroPrefix,
+
+    #[error("Utf8Error: {0}")]
+    Utf8Error(#[from] std::str::Utf8Error),
+
+    /// A value at a path has the wrong type for `BEValue::extract` or `BECursor`.
+    #[error("expected {1} at '{0}'")]
+    WrongType(String, &'static str),
+}
+
+/// What sort of problem an `Error` is, for callers that handle whole groups of variants alike.
+///
+/// More kinds may be added, so matches need a wildcard arm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Kind {
+    /// The input isn't well-formed bencode.
+    Syntax,
+    /// The input ended in the middle of a value.
+    Eof,
+    /// The input went over one of the `Limits`, or holds a number too large to represent.
+    Limit,
+    /// The bencode is well-formed but doesn't have the type or shape asked for.
+    Data,
+    /// A value can't be written as bencode.
+    Serialize,
+    /// Reading or writing failed.
+    Io,
+    /// A message from serde or from a `Serialize` or `Deserialize` impl.
+    Custom,
+}
+
+/// The errors the DOM layer used to report, now part of `Error`.
+#[deprecated(note = "use `Error`, which covers both layers")]
+pub type BEError = Error;
+
+/// The errors the serde layer used to report, now part of `Error`.
+#[deprecated(note = "use `Error`, which covers both layers")]
+pub type SerbeError = Error;
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// An `io::Error` from a reader or writer.
+///
+/// Two `IoError`s are equal if they have the same kind and message, so that `Error` can stay
+/// `PartialEq`. The original error is kept, so its kind and source are still available.
+#[derive(Debug)]
+pub struct IoError(std::io::Error);
+
+impl IoError {
+    pub fn kind(&self) -> std::io::ErrorKind {
+        self.0.kind()
+    }
+
+    pub fn get_ref(&self) -> &std::io::Error {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> std::io::Error {
+        self.0
+    }
+}
+
+impl PartialEq for IoError {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.kind() == other.0.kind() && self.0.to_string() == other.0.to_string()
+    }
+}
+
+impl fmt::Display for IoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl std::error::Error for IoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        Some(&self.0)
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(IoError(err))
+    }
+}
+
+// Where an error happened, for messages. Nothing is added at the root.
+fn at_path(path: &str) -> String {
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!(" at '{}'", path)
+    }
+}
+
+impl Error {
+    /// Which `Kind` of error this is.
+    ///
+    /// A repeated dict key is `Kind::Syntax` whether it was read or written.
+    pub fn kind(&self) -> Kind {
+        match self {
+            Error::Message(_) => Kind::Custom,
+
+            Error::DuplicateKey(_)
+            | Error::ExpectedList
+            | Error::ExpectedListEnd
+            | Error::ExpectedNumEnd
+            | Error::ExpectedMap
+            | Error::ExpectedMapEnd
+            | Error::KeysOutOfOrder(_)
+            | Error::MissingColon(_)
+            | Error::MissingSuffixError(..)
+            | Error::MissingValueForKey(_)
+            | Error::NegativeStringLength
+            | Error::NegativeZero
+            | Error::NoDigitsInNumber
+            | Error::TrailingInput
+            | Error::TrailingDatagram { .. }
+            | Error::UnexpectedCharError(_)
+            | Error::UnrecognizedPrefix(_)
+            | Error::UnexpectedPrefix(..)
+            | Error::UnexpectedZeroPrefix => Kind::Syntax,
+            #[cfg(feature = "value")]
+            Error::KeyNotString(_) => Kind::Syntax,
+
+            Error::Eof | Error::UnterminatedContainer { .. } => Kind::Eof,
+
+            Error::DepthLimitExceeded(_)
+            | Error::InputTooLong(_)
+            | Error::IntegerOverflow
+            | Error::LengthOverflow(_)
+            | Error::StringTooLong(..)
+            | Error::TooManyItems(_) => Kind::Limit,
+
+            Error::DuplicateField { .. }
+            | Error::IntegerOutOfRange { .. }
+            | Error::InvalidBool(_)
+            | Error::InvalidLength { .. }
+            | Error::InvalidType { .. }
+            | Error::InvalidValue { .. }
+            | Error::KeyNotInteger(_)
+            | Error::MissingField { .. }
+            | Error::MissingKey(_)
+            | Error::UnknownField { .. }
+            | Error::UnexpectedSigned
+            | Error::Utf8Error(_)
+            | Error::WrongType(..) => Kind::Data,
+
+            Error::EncodeInvalidValue(_)
+            | Error::KeyMustBeString
+            | Error::NoneInContainer { .. }
+            | Error::NoneValue => Kind::Serialize,
+
+            Error::Io(_) => Kind::Io,
+        }
+    }
+
+    /// The kind of the underlying `io::Error`, if this is `Error::Io`.
+    ///
+    /// Lets callers retry on `WouldBlock` or `Interrupted`, or treat `UnexpectedEof` as a
+    /// truncated stream.
+    pub fn io_kind(&self) -> Option<std::io::ErrorKind> {
+        match self {
+            Error::Io(err) => Some(err.kind()),
+            _ => None,
+        }
+    }
+
+    // Serde reports type and field errors without knowing where they are. Deserializers call
+    // this as the error passes each container, so the innermost one that knows the path fills it.
+    #[cfg(feature = "serde")]
+    pub(crate) fn with_path<F: FnOnce() -> String>(mut self, path: F) -> Self {
+        match &mut self {
+            Error::DuplicateField { path: p, .. }
+            | Error::InvalidLength { path: p, .. }
+            | Error::InvalidType { path: p, .. }
+            | Error::InvalidValue { path: p, .. }
+            | Error::MissingField { path: p, .. }
+            | Error::UnknownField { path: p, .. }
+                if p.is_empty() =>
+            {
+                *p = path();
+            }
+            _ => {}
+        }
+        self
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::de::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        Error::Message(msg.to_string())
+    }
+
+    fn invalid_type(unexp: serde::de::Unexpected, exp: &dyn serde::de::Expected) -> Self {
+        Error::InvalidType {
+            unexpected: unexp.to_string(),
+            expected: exp.to_string(),
+            path: String::new(),
+        }
+    }
+
+    fn invalid_value(unexp: serde::de::Unexpected, exp: &dyn serde::de::Expected) -> Self {
+        Error::InvalidValue {
+            unexpected: unexp.to_string(),
+            expected: exp.to_string(),
+            path: String::new(),
+        }
+    }
+
+    fn invalid_length(len: usize, exp: &dyn serde::de::Expected) -> Self {
+        Error::InvalidLength {
+            len,
+            expected: exp.to_string(),
+            path: String::new(),
+        }
+    }
+
+    fn unknown_field(field: &str, _expected: &'static [&'static str]) -> Self {
+        Error::UnknownField {
+            key: field.to_string(),
+            path: String::new(),
+        }
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Error::MissingField {
+            field,
+            path: String::new(),
+        }
+    }
+
+    fn duplicate_field(field: &'static str) -> Self {
+        Error::DuplicateField {
+            field,
+            path: String::new(),
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::ser::Error for Error {
+    fn custom<T>(msg: T) -> Self
+    where
+        T: std::fmt::Display,
+    {
+        Error::Message(msg.to_string())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(Kind::Syntax, Error::NegativeZero.kind());
+        assert_eq!(Kind::Syntax, Error::KeysOutOfOrder("a".to_string()).kind());
+        assert_eq!(Kind::Eof, Error::Eof.kind());
+        assert_eq!(
+            Kind::Eof,
+            Error::UnterminatedContainer {
+                kind: "list",
+                offset: 0
+            }
+            .kind()
+        );
+        assert_eq!(Kind::Limit, Error::StringTooLong(5, 4).kind());
+        assert_eq!(Kind::Data, Error::MissingKey("a".to_string()).kind());
+        assert_eq!(Kind::Serialize, Error::NoneValue.kind());
+        assert_eq!(Kind::Custom, Error::Message("x".to_string()).kind());
+
+        let io = std::io::Error::other("x");
+        assert_eq!(Kind::Io, Error::from(io).kind());
+    }
+}
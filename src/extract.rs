@@ -0,0 +1,292 @@
+//! Typed extraction of values from a `BEValue` tree.
+//!
+//! ```
+//! use beeenn::{be_extract, BEReader};
+//!
+//! let value = BEReader::new(&b"d8:announce3:url4:infod6:lengthi42eee"[..])
+//!     .next_value()
+//!     .unwrap()
+//!     .unwrap();
+//! let (announce, length) =
+//!     be_extract!(value, { "announce" => String, "info.length" => u64 }).unwrap();
+//! assert_eq!("url", announce);
+//! assert_eq!(42, length);
+//! ```
+
+use std::convert::TryFrom;
+
+use crate::error::Error;
+use crate::value::BEValue;
+
+/// Conversion from a borrowed `BEValue` into a Rust type.
+///
+/// Byte strings convert to `&[u8]`; `Vec<T>` always means a bencode list.
+pub trait FromBEValue<'a>: Sized {
+    /// A short description of the expected type, used in error messages.
+    const EXPECTED: &'static str;
+
+    /// Converts `value`, returning `None` if it has the wrong type or is out of range.
+    fn from_bevalue(value: &'a BEValue) -> Option<Self>;
+
+    /// The result to use when the value is missing entirely. Only `Option` accepts this.
+    fn from_missing() -> Option<Self> {
+        None
+    }
+}
+
+impl BEValue {
+    /// Looks up the dotted `path` (see `get_path`) and converts the result to `T`.
+    ///
+    /// Returns `Error::MissingKey` if nothing exists at `path` and `Error::WrongType` if the
+    /// value cannot be converted.
+    pub fn extract<'a, T: FromBEValue<'a>>(&'a self, path: &str) -> Result<T, Error> {
+        match self.get_path(path) {
+            Some(value) => T::from_bevalue(value)
+                .ok_or_else(|| Error::WrongType(path.to_string(), T::EXPECTED)),
+            None => T::from_missing().ok_or_else(|| Error::MissingKey(path.to_string())),
+        }
+    }
+}
+
+/// A tuple of `FromBEValue` types that `extract` can fill in from one path each.
+///
+/// Implemented for tuples of up to eight elements.
+pub trait FromPaths<'a>: Sized {
+    /// The number of paths the tuple needs.
+    const LEN: usize;
+
+    /// Extracts each element from the matching path. `paths` must hold `LEN` paths.
+    fn from_paths(value: &'a BEValue, paths: &[&str]) -> Result<Self, Error>;
+}
+
+macro_rules! impl_from_paths {
+    ($len:expr => $($t:ident $i:tt),+) => {
+        impl<'a, $($t: FromBEValue<'a>),+> FromPaths<'a> for ($($t,)+) {
+            const LEN: usize = $len;
+
+            fn from_paths(value: &'a BEValue, paths: &[&str]) -> Result<Self, Error> {
+                Ok(($(value.extract::<$t>(paths[$i])?,)+))
+            }
+        }
+    };
+}
+
+impl_from_paths!(1 => A 0);
+impl_from_paths!(2 => A 0, B 1);
+impl_from_paths!(3 => A 0, B 1, C 2);
+impl_from_paths!(4 => A 0, B 1, C 2, D 3);
+impl_from_paths!(5 => A 0, B 1, C 2, D 3, E 4);
+impl_from_paths!(6 => A 0, B 1, C 2, D 3, E 4, F 5);
+impl_from_paths!(7 => A 0, B 1, C 2, D 3, E 4, F 5, G 6);
+impl_from_paths!(8 => A 0, B 1, C 2, D 3, E 4, F 5, G 6, H 7);
+
+/// Extracts a tuple of typed fields, one from each of the dotted `paths`, in order.
+///
+/// Returns the error for the first field that was missing or had the wrong type, as
+/// `BEValue::extract` would. `be_extract!` does the same with each type written next to its
+/// path.
+///
+/// Panics if the number of paths doesn't match the size of the tuple.
+///
+/// ```
+/// use beeenn::extract::extract;
+/// use beeenn::BESliceReader;
+///
+/// let input = b"d4:infod6:lengthi42e4:name3:fooee";
+/// let value = BESliceReader::new(input).next_value().unwrap().unwrap();
+/// let (length, name) = extract::<(u64, String)>(&value, &["info.length", "info.name"]).unwrap();
+/// assert_eq!((42, "foo".to_string()), (length, name));
+/// ```
+pub fn extract<'a, T: FromPaths<'a>>(value: &'a BEValue, paths: &[&str]) -> Result<T, Error> {
+    assert_eq!(
+        T::LEN,
+        paths.len(),
+        "extract() needs one path per tuple element"
+    );
+    T::from_paths(value, paths)
+}
+
+/// Extracts several typed fields from a `BEValue` at once.
+///
+/// Evaluates to a `Result` holding a tuple of the fields, in order, or the error for the first
+/// field that was missing or had the wrong type.
+#[macro_export]
+macro_rules! be_extract {
+    ($value:expr, { $($path:expr => $t:ty),+ $(,)? }) => {{
+        let value: &$crate::BEValue = &$value;
+        let extract = || -> ::std::result::Result<_, $crate::Error> {
+            Ok(($(value.extract::<$t>($path)?,)+))
+        };
+        extract()
+    }};
+}
+
+impl<'a> FromBEValue<'a> for &'a BEValue {
+    const EXPECTED: &'static str = "any value";
+
+    fn from_bevalue(value: &'a BEValue) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl<'a> FromBEValue<'a> for BEValue {
+    const EXPECTED: &'static str = "any value";
+
+    fn from_bevalue(value: &'a BEValue) -> Option<Self> {
+        Some(value.clone())
+    }
+}
+
+macro_rules! impl_from_bevalue_int {
+    ($($t:ty),*) => {
+        $(
+            impl<'a> FromBEValue<'a> for $t {
+                const EXPECTED: &'static str = stringify!($t);
+
+                fn from_bevalue(value: &'a BEValue) -> Option<Self> {
+                    match value {
+                        BEValue::BEInteger(i) => <$t>::try_from(*i).ok(),
+                        _ => None,
+                    }
+                }
+            }
+        )*
+    };
+}
+
+impl_from_bevalue_int!(i8, i16, i32, i64, u8, u16, u32, u64, usize);
+
+impl<'a> FromBEValue<'a> for &'a [u8] {
+    const EXPECTED: &'static str = "string";
+
+    fn from_bevalue(value: &'a BEValue) -> Option<Self> {
+        match value {
+            BEValue::BEString(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromBEValue<'a> for &'a str {
+    const EXPECTED: &'static str = "UTF-8 string";
+
+    fn from_bevalue(value: &'a BEValue) -> Option<Self> {
+        <&[u8]>::from_bevalue(value).and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+}
+
+impl<'a> FromBEValue<'a> for String {
+    const EXPECTED: &'static str = "UTF-8 string";
+
+    fn from_bevalue(value: &'a BEValue) -> Option<Self> {
+        <&str>::from_bevalue(value).map(str::to_string)
+    }
+}
+
+impl<'a, T: FromBEValue<'a>> FromBEValue<'a> for Vec<T> {
+    const EXPECTED: &'static str = "list";
+
+    fn from_bevalue(value: &'a BEValue) -> Option<Self> {
+        match value {
+            BEValue::BEList(list) => list.iter().map(T::from_bevalue).collect(),
+            _ => None,
+        }
+    }
+}
+
+impl<'a, T: FromBEValue<'a>> FromBEValue<'a> for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn from_bevalue(value: &'a BEValue) -> Option<Self> {
+        T::from_bevalue(value).map(Some)
+    }
+
+    fn from_missing() -> Option<Self> {
+        Some(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::BEReader;
+
+    fn torrent() -> BEValue {
+        BEReader::new(
+            &b"d8:announce3:url4:infod5:filesld6:lengthi7eee6:lengthi-1e4:name3:fooee"[..],
+        )
+        .next_value()
+        .unwrap()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_extract() {
+        let value = torrent();
+        assert_eq!("url", value.extract::<&str>("announce").unwrap());
+        assert_eq!(-1, value.extract::<i64>("info.length").unwrap());
+        assert_eq!(7u8, value.extract::<u8>("info.files.0.length").unwrap());
+        assert_eq!(None, value.extract::<Option<u64>>("comment").unwrap());
+    }
+
+    #[test]
+    fn test_extract_errors() {
+        let value = torrent();
+        match value.extract::<u64>("info.length") {
+            Err(Error::WrongType(path, expected)) => {
+                assert_eq!("info.length", path);
+                assert_eq!("u64", expected);
+            }
+            _ => panic!("expected WrongType"),
+        }
+        match value.extract::<String>("info.missing") {
+            Err(Error::MissingKey(path)) => assert_eq!("info.missing", path),
+            _ => panic!("expected MissingKey"),
+        }
+        // Present but mistyped is still an error for an Option.
+        assert!(value.extract::<Option<u64>>("announce").is_err());
+    }
+
+    #[test]
+    fn test_be_extract() {
+        let value = torrent();
+        let (announce, name, lengths) = be_extract!(value, {
+            "announce" => String,
+            "info.name" => &str,
+            "info.files" => Vec<&BEValue>,
+        })
+        .unwrap();
+        assert_eq!("url", announce);
+        assert_eq!("foo", name);
+        assert_eq!(1, lengths.len());
+
+        let result = be_extract!(value, { "announce" => String, "info.nope" => u64 });
+        match result {
+            Err(Error::MissingKey(path)) => assert_eq!("info.nope", path),
+            _ => panic!("expected MissingKey"),
+        }
+    }
+
+    #[test]
+    fn test_extract_paths() {
+        let value = torrent();
+        let (announce, length, comment) =
+            extract::<(&str, i64, Option<String>)>(&value, &["announce", "info.length", "x"])
+                .unwrap();
+        assert_eq!(("url", -1, None), (announce, length, comment));
+
+        let (files,) = extract::<(Vec<&BEValue>,)>(&value, &["info.files"]).unwrap();
+        assert_eq!(1, files.len());
+
+        match extract::<(String, u64)>(&value, &["announce", "info.length"]) {
+            Err(Error::WrongType(path, "u64")) => assert_eq!("info.length", path),
+            other => panic!("unexpected {:?}", other),
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "one path per tuple element")]
+    fn test_extract_paths_count() {
+        let _ = extract::<(String, u64)>(&torrent(), &["announce"]);
+    }
+}
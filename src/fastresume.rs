@@ -0,0 +1,116 @@
+//! Typed access to libtorrent resume data (`.fastresume` files).
+//!
+//! Only the fields that backup and migration tools usually need are modelled. Everything else
+//! is ignored when reading, and so is dropped if a `ResumeData` is written back out. To change a
+//! field while keeping the rest of a file intact, edit it as a `BEValue` instead.
+//!
+//! ```
+//! use beeenn::fastresume::ResumeData;
+//!
+//! let input = b"d11:file-format22:libtorrent resume file12:file-versioni1e\
+//!               6:pausedi1e6:pieces3:\x01\x00\x019:save_path9:/downloade";
+//! let resume: ResumeData = beeenn::from_bytes(input).unwrap();
+//! assert!(resume.is_libtorrent());
+//! assert_eq!(Some("/download"), resume.save_path.as_deref());
+//! assert!(resume.paused);
+//! assert_eq!(2, resume.pieces_have());
+//! ```
+
+use serde::{Deserialize, Serialize};
+
+/// The `file-format` libtorrent writes.
+pub const FILE_FORMAT: &str = "libtorrent resume file";
+
+/// The commonly used fields of a libtorrent resume file.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResumeData {
+    #[serde(rename = "file-format")]
+    pub file_format: String,
+    #[serde(rename = "file-version")]
+    pub file_version: i64,
+    /// The v1 infohash, 20 raw bytes.
+    #[serde(
+        rename = "info-hash",
+        with = "raw_bytes",
+        default,
+        skip_serializing_if = "Vec::is_empty"
+    )]
+    pub info_hash: Vec<u8>,
+    pub name: Option<String>,
+    pub save_path: Option<String>,
+    /// One byte per piece. Bit 0 is set if the piece has been downloaded and checked.
+    #[serde(with = "raw_bytes", default, skip_serializing_if = "Vec::is_empty")]
+    pub pieces: Vec<u8>,
+    /// One priority per file, from 0 (don't download) to 7.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub file_priority: Vec<u8>,
+    /// Tracker URLs, grouped into tiers.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trackers: Vec<Vec<String>>,
+    /// Web seed URLs.
+    #[serde(rename = "url-list", default, skip_serializing_if = "Vec::is_empty")]
+    pub url_list: Vec<String>,
+    #[serde(with = "crate::as_bool_int", default)]
+    pub paused: bool,
+    #[serde(with = "crate::as_bool_int", default)]
+    pub auto_managed: bool,
+    /// Seconds since the Unix epoch.
+    pub added_time: Option<i64>,
+    /// Seconds since the Unix epoch, or 0 if the torrent has not completed.
+    pub completed_time: Option<i64>,
+    pub total_downloaded: Option<u64>,
+    pub total_uploaded: Option<u64>,
+}
+
+impl ResumeData {
+    /// Returns true if `file_format` is the one libtorrent writes.
+    pub fn is_libtorrent(&self) -> bool {
+        self.file_format == FILE_FORMAT
+    }
+
+    /// Returns true if piece `index` has been downloaded. Pieces past the end of the bitfield
+    /// have not.
+    pub fn has_piece(&self, index: usize) -> bool {
+        self.pieces.get(index).is_some_and(|b| b & 1 != 0)
+    }
+
+    /// Counts the downloaded pieces.
+    pub fn pieces_have(&self) -> usize {
+        self.pieces.iter().filter(|b| *b & 1 != 0).count()
+    }
+}
+
+// Byte strings that aren't text. A `Vec<u8>` on its own would be (de)serialized as a list of
+// integers.
+mod raw_bytes {
+    use std::fmt;
+
+    use serde::de::{self, Deserializer, Visitor};
+    use serde::ser::Serializer;
+
+    pub fn serialize<S: Serializer>(value: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_byte_buf(BytesVisitor)
+    }
+
+    struct BytesVisitor;
+
+    impl Visitor<'_> for BytesVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a byte string")
+        }
+
+        fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<u8>, E> {
+            Ok(v.to_vec())
+        }
+
+        fn visit_byte_buf<E: de::Error>(self, v: Vec<u8>) -> Result<Vec<u8>, E> {
+            Ok(v)
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Floats stored as decimal strings.
+
+use std::fmt;
+
+use serde::de::{self, Deserialize, Deserializer, Visitor};
+use serde::ser::{self, Serialize, Serializer};
+
+/// An `f64` written as a decimal string, such as `3:0.5`.
+///
+/// Bencode has no floats, so a plain `f64` field is an `Error::Message` naming this type. This is a non-standard
+/// extension: other bencode readers will see a string, and only programs that expect it will
+/// read it back as a number. It suits ratios and fractional timestamps in an application's own
+/// files, not fields defined by a BitTorrent spec.
+///
+/// The string is the shortest one that reads back as exactly the same `f64`. Reading also
+/// accepts an integer, so a field can start out as one. NaN and infinities can't be written.
+///
+/// ```
+/// use beeenn::BeFloat;
+///
+/// let bytes = beeenn::to_bytes(&BeFloat(0.25)).unwrap();
+/// assert_eq!(b"4:0.25", &bytes[..]);
+/// assert_eq!(BeFloat(0.25), beeenn::from_bytes(&bytes).unwrap());
+/// assert_eq!(BeFloat(3.0), beeenn::from_bytes(b"i3e").unwrap());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd)]
+pub struct BeFloat(pub f64);
+
+impl BeFloat {
+    pub fn into_inner(self) -> f64 {
+        self.0
+    }
+}
+
+impl From<f64> for BeFloat {
+    fn from(f: f64) -> Self {
+        BeFloat(f)
+    }
+}
+
+impl From<BeFloat> for f64 {
+    fn from(f: BeFloat) -> Self {
+        f.0
+    }
+}
+
+impl Serialize for BeFloat {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if !self.0.is_finite() {
+            return Err(ser::Error::custom(format!(
+                "cannot write {} as a BeFloat",
+                self.0
+            )));
+        }
+        serializer.serialize_str(&self.0.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for BeFloat {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(BeFloatVisitor).map(BeFloat)
+    }
+}
+
+struct BeFloatVisitor;
+
+impl BeFloatVisitor {
+    fn parse<E: de::Error>(&self, text: &str) -> Result<f64, E> {
+        match text.parse::<f64>() {
+            Ok(f) if f.is_finite() => Ok(f),
+            _ => Err(E::invalid_value(de::Unexpected::Str(text), self)),
+        }
+    }
+}
+
+impl<'de> Visitor<'de> for BeFloatVisitor {
+    type Value = f64;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a decimal string or an integer")
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<f64, E> {
+        Ok(v as f64)
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<f64, E> {
+        Ok(v as f64)
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<f64, E> {
+        self.parse(v)
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<f64, E> {
+        match std::str::from_utf8(v) {
+            Ok(text) => self.parse(text),
+            Err(_) => Err(E::invalid_value(de::Unexpected::Bytes(v), &self)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{from_bytes, to_bytes, Error};
+
+    #[test]
+    fn test_round_trip() {
+        for f in [
+            0.0,
+            -0.0,
+            1.5,
+            -2.25,
+            0.1,
+            1e-7,
+            1e300,
+            f64::MAX,
+            f64::MIN_POSITIVE,
+        ] {
+            let bytes = to_bytes(&BeFloat(f)).unwrap();
+            let back: BeFloat = from_bytes(&bytes).unwrap();
+            assert_eq!(f.to_bits(), back.0.to_bits(), "{}", f);
+        }
+        assert_eq!(&b"3:0.1"[..], &to_bytes(&BeFloat(0.1)).unwrap()[..]);
+        assert_eq!(BeFloat(-4.0), from_bytes(b"i-4e").unwrap());
+    }
+
+    #[test]
+    fn test_errors() {
+        for f in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            assert!(matches!(to_bytes(&BeFloat(f)), Err(Error::Message(_))));
+        }
+        for bytes in [
+            &b"3:abc"[..],
+            b"3:inf",
+            b"3:NaN",
+            b"0:",
+            b"2:\xff\xfe",
+            b"le",
+        ] {
+            assert!(from_bytes::<BeFloat>(bytes).is_err());
+        }
+    }
+
+    #[test]
+    fn test_plain_floats() {
+        let err = to_bytes(&1.5f64).unwrap_err();
+        assert!(err.to_string().contains("BeFloat"), "{}", err);
+        assert!(matches!(to_bytes(&1.5f32), Err(Error::Message(_))));
+        assert!(matches!(
+            from_bytes::<f64>(b"3:1.5"),
+            Err(Error::Message(_))
+        ));
+        assert!(matches!(from_bytes::<f32>(b"i1e"), Err(Error::Message(_))));
+    }
+
+    #[test]
+    fn test_in_struct() {
+        use serde::{Deserialize, Serialize};
+
+        #[derive(Debug, PartialEq, Serialize, Deserialize)]
+        struct Stats {
+            ratio: BeFloat,
+            uploaded: u64,
+        }
+        let stats = Stats {
+            ratio: BeFloat(1.75),
+            uploaded: 7,
+        };
+        let bytes = to_bytes(&stats).unwrap();
+        assert_eq!(&b"d5:ratio4:1.758:uploadedi7ee"[..], &bytes[..]);
+        assert_eq!(stats, from_bytes(&bytes).unwrap());
+    }
+}
@@ -0,0 +1,186 @@
+//! An `xxd`-style dump of bencode that labels every token with its path.
+//!
+//! Each structural token (a container's start or end, a key, an integer, a string) starts a new
+//! line, showing its offset, hex and ASCII bytes, and what it decodes to. Tokens longer than 16
+//! bytes continue on unlabelled lines.
+//!
+//! ```
+//! let dump = beeenn::hexdump::hexdump(b"d3:fooi42ee");
+//! assert_eq!(
+//!     dump,
+//!     "\
+//! 00000000  64                                               |d               |  dict
+//! 00000001  33 3a 66 6f 6f                                   |3:foo           |  foo: key
+//! 00000006  69 34 32 65                                      |i42e            |  foo: 42
+//! 0000000a  65                                               |e               |  end dict
+//! "
+//! );
+//! ```
+//!
+//! If the input stops parsing, the rest of it is dumped with the error as its label.
+
+use std::fmt::Write;
+use std::ops::Range;
+
+use crate::reader::BESliceReader;
+use crate::span::Spans;
+use crate::value::BEValue;
+
+const BYTES_PER_LINE: usize = 16;
+
+// Strings longer than this are summarized rather than quoted in labels.
+const MAX_QUOTED_LEN: usize = 32;
+
+/// Dumps every value in `bytes`.
+pub fn hexdump(bytes: &[u8]) -> String {
+    let mut tokens = Vec::new();
+    let mut reader = BESliceReader::new(bytes);
+    loop {
+        let start = reader.offset();
+        match reader.next_spanned() {
+            Ok(Some(spanned)) => collect_tokens(&spanned.value, &spanned.spans, "", &mut tokens),
+            Ok(None) => break,
+            Err(err) => {
+                tokens.push((start..bytes.len(), format!("error: {}", err)));
+                break;
+            }
+        }
+    }
+
+    let mut out = String::new();
+    for (range, label) in tokens {
+        write_token(&mut out, bytes, range, &label);
+    }
+    out
+}
+
+fn child_path(path: &str, component: &str) -> String {
+    if path.is_empty() {
+        component.to_string()
+    } else {
+        format!("{}.{}", path, component)
+    }
+}
+
+// Labels are "path: description", or just the description at the top level.
+fn label(path: &str, description: &str) -> String {
+    if path.is_empty() {
+        description.to_string()
+    } else {
+        format!("{}: {}", path, description)
+    }
+}
+
+fn describe_string(bytes: &[u8]) -> String {
+    match std::str::from_utf8(bytes) {
+        Ok(s) if s.len() <= MAX_QUOTED_LEN => format!("{:?}", s),
+        _ => format!("{} bytes", bytes.len()),
+    }
+}
+
+fn collect_tokens(
+    value: &BEValue,
+    spans: &Spans,
+    path: &str,
+    tokens: &mut Vec<(Range<usize>, String)>,
+) {
+    let span = spans.span.clone();
+    match value {
+        BEValue::BEInteger(i) => tokens.push((span, label(path, &i.to_string()))),
+        BEValue::BEString(s) => tokens.push((span, label(path, &describe_string(s)))),
+        BEValue::BEInvalid(_) => tokens.push((span, label(path, "invalid"))),
+        BEValue::BEList(list) => {
+            tokens.push((span.start..span.start + 1, label(path, "list")));
+            for (i, (element, child)) in list.iter().zip(&spans.children).enumerate() {
+                collect_tokens(element, child, &child_path(path, &i.to_string()), tokens);
+            }
+            tokens.push((span.end - 1..span.end, label(path, "end list")));
+        }
+        BEValue::BEDict(dict) => {
+            tokens.push((span.start..span.start + 1, label(path, "dict")));
+            for ((key, element), child) in dict.iter().zip(&spans.children) {
+                let path = child_path(path, &String::from_utf8_lossy(key));
+                if let Some(key_span) = &child.key {
+                    tokens.push((key_span.clone(), label(&path, "key")));
+                }
+                collect_tokens(element, child, &path, tokens);
+            }
+            tokens.push((span.end - 1..span.end, label(path, "end dict")));
+        }
+    }
+}
+
+fn write_token(out: &mut String, bytes: &[u8], range: Range<usize>, label: &str) {
+    let start = range.start;
+    for (i, chunk) in bytes[range].chunks(BYTES_PER_LINE).enumerate() {
+        let hex: Vec<String> = chunk.iter().map(|b| format!("{:02x}", b)).collect();
+        let ascii: String = chunk
+            .iter()
+            .map(|b| {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    *b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect();
+        let label = if i == 0 { label } else { "" };
+        let line = format!(
+            "{:08x}  {:<width$} |{:<16}|  {}",
+            start + i * BYTES_PER_LINE,
+            hex.join(" "),
+            ascii,
+            label,
+            width = BYTES_PER_LINE * 3,
+        );
+        writeln!(out, "{}", line.trim_end()).unwrap();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_nested() {
+        let dump = hexdump(b"d4:infold1:ai-1eeee");
+        let labels: Vec<&str> = dump.lines().map(|line| &line[79..]).collect();
+        assert_eq!(
+            vec![
+                "dict",
+                "info: key",
+                "info: list",
+                "info.0: dict",
+                "info.0.a: key",
+                "info.0.a: -1",
+                "info.0: end dict",
+                "info: end list",
+                "end dict",
+            ],
+            labels
+        );
+    }
+
+    #[test]
+    fn test_long_string() {
+        let input = format!("{}:{}", 40, "x".repeat(40));
+        let dump = hexdump(input.as_bytes());
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(3, lines.len());
+        assert!(lines[0].starts_with("00000000  34 30 3a 78"));
+        assert!(lines[0].ends_with("|  40 bytes"));
+        assert!(lines[1].starts_with("00000010  78"));
+        assert!(lines[2].starts_with("00000020  78"));
+        assert!(lines[2].ends_with('|'));
+    }
+
+    #[test]
+    fn test_error() {
+        let dump = hexdump(b"i1ei0x");
+        let lines: Vec<&str> = dump.lines().collect();
+        assert_eq!(2, lines.len());
+        assert!(lines[0].ends_with("|  1"));
+        assert!(lines[1].starts_with("00000003  69 30 78"));
+        assert!(lines[1].contains("error: "));
+    }
+}
@@ -0,0 +1,157 @@
+//! Extension traits for reading and writing bencode directly on `Read` and `Write` streams.
+//!
+//! ```
+//! use beeenn::ReadBencodeExt;
+//!
+//! let mut socket = &b"d4:porti6881ee5:hello"[..];
+//! let message = socket.read_bevalue()?.unwrap();
+//! assert_eq!(6881, message.get_int("port")?);
+//! // Nothing after the value was consumed.
+//! assert_eq!(b"5:hello", socket);
+//! # Ok::<(), beeenn::Error>(())
+//! ```
+
+use std::io::{Read, Write};
+
+#[cfg(feature = "serde")]
+use serde::de::DeserializeOwned;
+#[cfg(feature = "serde")]
+use serde::Serialize;
+
+use crate::encode::KeyOrder;
+#[cfg(feature = "serde")]
+use crate::error::Error;
+use crate::error::Result;
+use crate::reader::BEReader;
+use crate::value::BEValue;
+
+/// Reads single bencode values from any `Read`, with the default `Limits`.
+///
+/// Input is read one byte at a time and never past the end of the value, so whatever follows it
+/// in the stream is left for the caller. That makes these methods safe on sockets that mix
+/// bencode with other framing, but slow on unbuffered files; wrap those in a `BufReader` when
+/// nothing else will read from them. For other limits, use `BEReader::with_limits`.
+pub trait ReadBencodeExt: Read {
+    /// Reads the next value. Returns `Ok(None)` if the stream ends before a value starts.
+    fn read_bevalue(&mut self) -> Result<Option<BEValue>> {
+        BEReader::new(self).next_value()
+    }
+
+    /// Reads the next value and deserializes it into a `T`, as `from_value` does.
+    ///
+    /// The value is read by `BEReader`, so it must be canonical. Returns `Error::Eof` if the
+    /// stream ends before a value starts.
+    #[cfg(feature = "serde")]
+    fn read_bencode<T: DeserializeOwned>(&mut self) -> Result<T> {
+        let value = self.read_bevalue()?.ok_or(Error::Eof)?;
+        crate::from_value(&value)
+    }
+}
+
+impl<R: Read + ?Sized> ReadBencodeExt for R {}
+
+/// Writes single bencode values to any `Write`.
+///
+/// Each value is encoded in full before anything is written, so an encoding error leaves the
+/// writer untouched. Both methods return the number of bytes written.
+///
+/// ```
+/// use beeenn::{BEValue, WriteBencodeExt};
+///
+/// let mut out = Vec::new();
+/// out.extend_from_slice(b"HDR");
+/// let list = BEValue::BEList(vec![BEValue::BEInteger(1), BEValue::BEInteger(2)]);
+/// assert_eq!(8, out.write_bevalue(&list)?);
+/// assert_eq!(b"HDRli1ei2ee", &out[..]);
+/// # Ok::<(), beeenn::Error>(())
+/// ```
+pub trait WriteBencodeExt: Write {
+    /// Serializes `value` as `to_bytes` does and writes it.
+    #[cfg(feature = "serde")]
+    fn write_bencode<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<usize> {
+        let mut bytes = Vec::new();
+        crate::append_to_bytes(&mut bytes, value)?;
+        self.write_all(&bytes)?;
+        Ok(bytes.len())
+    }
+
+    /// Encodes `value` with sorted keys, as `BEValue::encode(KeyOrder::Sort)` does, and writes it.
+    fn write_bevalue(&mut self, value: &BEValue) -> Result<usize> {
+        let bytes = value.encode(KeyOrder::Sort)?;
+        self.write_all(&bytes)?;
+        Ok(bytes.len())
+    }
+}
+
+impl<W: Write + ?Sized> WriteBencodeExt for W {}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn test_read_bevalue() {
+        let mut stream = &b"i1eli2ee"[..];
+        assert_eq!(Some(BEValue::BEInteger(1)), stream.read_bevalue().unwrap());
+        assert_eq!(b"li2ee", stream);
+        assert_eq!(
+            Some(BEValue::BEList(vec![BEValue::BEInteger(2)])),
+            stream.read_bevalue().unwrap()
+        );
+        assert_eq!(None, stream.read_bevalue().unwrap());
+
+        let mut stream = &b"li2e"[..];
+        assert!(matches!(
+            stream.read_bevalue(),
+            Err(Error::UnterminatedContainer { .. })
+        ));
+    }
+
+    #[test]
+    fn test_write_bevalue() {
+        let mut out = b"x".to_vec();
+        let value = BEValue::BEDict(vec![
+            (b"b".into(), BEValue::BEInteger(1)),
+            (b"a".into(), BEValue::BEInteger(2)),
+        ]);
+        assert_eq!(14, out.write_bevalue(&value).unwrap());
+        assert_eq!(b"xd1:ai2e1:bi1ee", &out[..]);
+
+        assert!(out
+            .write_bevalue(&BEValue::BEInvalid(b"?".to_vec()))
+            .is_err());
+        assert_eq!(b"xd1:ai2e1:bi1ee", &out[..]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_write_bencode() {
+        let mut out = Vec::new();
+        assert_eq!(8, out.write_bencode(&["a", "b"][..]).unwrap());
+        assert_eq!(4, out.write_bencode(&BEValue::BEInteger(-1)).unwrap());
+        assert_eq!(b"l1:a1:bei-1e", &out[..]);
+
+        // Errors from the writer come through with their kind.
+        let mut full = [0u8; 2];
+        let err = (&mut full[..]).write_bencode("abc").unwrap_err();
+        assert_eq!(Some(std::io::ErrorKind::WriteZero), err.io_kind());
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_read_bencode() {
+        #[derive(serde::Deserialize, Debug, PartialEq)]
+        struct Ping {
+            id: String,
+        }
+
+        let mut stream = std::io::Cursor::new(b"d2:id3:abce4:rest".to_vec());
+        let ping: Ping = stream.read_bencode().unwrap();
+        assert_eq!("abc", ping.id);
+        assert_eq!(11, stream.position());
+
+        let mut empty = &b""[..];
+        assert!(matches!(empty.read_bencode::<Ping>(), Err(Error::Eof)));
+    }
+}
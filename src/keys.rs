@@ -0,0 +1,36 @@
+//! Ordering of dict keys.
+//!
+//! Bencode dict keys are byte strings and must appear sorted by their raw bytes, compared
+//! lexicographically. Every part of the crate that sorts or checks keys goes through
+//! `cmp_keys` so that they all agree.
+
+use std::cmp::Ordering;
+
+/// Compares two dict keys in canonical bencode order.
+///
+/// This is a plain byte-wise comparison, so `"B" < "a"` and multi-byte UTF-8 sequences sort by
+/// their encoded bytes.
+pub fn cmp_keys(a: &[u8], b: &[u8]) -> Ordering {
+    a.cmp(b)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_cmp_keys() {
+        assert_eq!(Ordering::Less, cmp_keys(b"B", b"a"));
+        assert_eq!(Ordering::Less, cmp_keys(b"a", b"aa"));
+        assert_eq!(Ordering::Equal, cmp_keys(b"", b""));
+        assert_eq!(
+            Ordering::Less,
+            cmp_keys("zeta".as_bytes(), "\u{f1}ame".as_bytes())
+        );
+        assert_eq!(
+            Ordering::Greater,
+            cmp_keys("\u{3a9}mega".as_bytes(), "\u{f1}ame".as_bytes())
+        );
+        assert_eq!(Ordering::Greater, cmp_keys(&[0xff], "\u{3a9}".as_bytes()));
+    }
+}
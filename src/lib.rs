@@ -1,111 +1,622 @@
-use thiserror::Error as ThisError;
-
+#[cfg(feature = "serde")]
 mod de;
+#[cfg(any(feature = "serde", feature = "value"))]
+mod error;
+#[cfg(feature = "serde")]
+mod float;
+#[cfg(feature = "serde")]
+mod one_or_many;
+#[cfg(feature = "serde")]
+pub mod seed;
+#[cfg(feature = "serde")]
 mod ser;
+#[cfg(feature = "serde")]
+mod with;
 
-#[derive(Debug, ThisError, PartialEq)]
-pub enum SerbeError {
-    #[error("error from serde: {0}")]
-    Message(String),
+#[cfg(feature = "value")]
+mod be_bytes;
+#[cfg(feature = "value")]
+mod compact;
+#[cfg(feature = "value")]
+pub mod cow;
+#[cfg(feature = "value")]
+pub mod cursor;
+#[cfg(feature = "value")]
+pub mod encode;
+#[cfg(feature = "value")]
+pub mod extract;
+#[cfg(feature = "fastresume")]
+pub mod fastresume;
+#[cfg(feature = "value")]
+pub mod hexdump;
+#[cfg(feature = "value")]
+mod io_ext;
+pub mod keys;
+mod limits;
+pub mod metrics;
+pub mod prelude;
+#[cfg(feature = "value")]
+pub mod reader;
+#[cfg(feature = "value")]
+pub mod recover;
+#[cfg(feature = "value")]
+pub mod redact;
+#[cfg(feature = "value")]
+pub mod rewrite;
+#[cfg(feature = "value")]
+pub mod scan;
+#[cfg(feature = "value")]
+pub mod schema;
+#[cfg(feature = "value")]
+pub mod shared;
+#[cfg(feature = "value")]
+pub mod span;
+pub mod syntax;
+pub mod torrent;
+#[cfg(feature = "value")]
+pub mod value;
+#[cfg(all(feature = "serde", feature = "value"))]
+mod value_de;
+#[cfg(all(feature = "serde", feature = "value"))]
+mod value_ser;
+#[cfg(feature = "value")]
+pub mod walk;
 
-    #[error("reached end of input before finishing")]
-    Eof,
+#[cfg(feature = "value")]
+pub use be_bytes::BEBytes;
+#[cfg(feature = "value")]
+pub use cow::BEValueCow;
+#[cfg(feature = "value")]
+pub use cursor::BECursor;
+#[cfg(feature = "serde")]
+pub use de::{
+    from_bytes, from_bytes_with, from_datagram, from_reader_with, Deserializer, Utf8Policy,
+};
+#[cfg(any(feature = "serde", feature = "value"))]
+#[allow(deprecated)]
+pub use error::{BEError, Error, IoError, Kind, Result, SerbeError};
+#[cfg(feature = "serde")]
+pub use float::BeFloat;
+#[cfg(feature = "value")]
+pub use io_ext::{ReadBencodeExt, WriteBencodeExt};
+pub use limits::Limits;
+#[cfg(feature = "serde")]
+pub use one_or_many::OneOrMany;
+#[cfg(feature = "value")]
+pub use reader::{validate, BEReader, BESliceReader};
+#[cfg(feature = "serde")]
+pub use ser::{append_to_bytes, to_bytes, PoolStats, Serializer};
+#[cfg(feature = "value")]
+pub use value::BEValue;
+#[cfg(all(feature = "serde", feature = "value"))]
+pub use value_de::from_value;
+#[cfg(feature = "serde")]
+pub use with::{as_bool_int, hex_bytes, string_or_list, unix_timestamp};
 
-    #[error("expected a 'l' to start the list")]
-    ExpectedList,
+#[cfg(all(test, feature = "serde"))]
+mod test {
+    use super::*;
+    use serde::{Deserialize, Serialize};
 
-    #[error("expected a 'e' to end the list")]
-    ExpectedListEnd,
+    macro_rules! assert_round_trip {
+        ($v:expr, $t:ty) => {
+            let val: $t = $v;
+            assert_eq!(val, from_bytes::<$t>(&to_bytes(&val).unwrap()).unwrap());
+        };
+    }
 
-    #[error("expected a 'e' to end the number")]
-    ExpectedNumEnd,
+    #[test]
+    fn test_bool() {
+        let val: bool = from_bytes(b"i0e").unwrap();
+        assert!(!val);
 
-    #[error("expected a 'd' to start the map")]
-    ExpectedMap,
+        let val: bool = from_bytes(b"i1e").unwrap();
+        assert!(val);
 
-    #[error("expected a 'e' to end the map")]
-    ExpectedMapEnd,
+        let val: bool = from_bytes(b"i32e").unwrap();
+        assert!(val);
 
-    #[error("expected colon, ':', to separate length from bytes. Found {0}")]
-    MissingColon(u8),
+        assert_round_trip!(false, bool);
+        assert_round_trip!(true, bool);
+    }
 
-    #[error("every number must have at least one digit")]
-    NoDigitsInNumber,
+    #[test]
+    fn test_strict_bool() {
+        fn strict(bytes: &[u8]) -> Result<bool> {
+            let mut de = Deserializer::from_bytes(bytes).strict_bool(true);
+            let val = bool::deserialize(&mut de)?;
+            de.end()?;
+            Ok(val)
+        }
 
-    #[error("trailing input remains after deserializing")]
-    TrailingInput,
+        assert!(!strict(b"i0e").unwrap());
+        assert!(strict(b"i1e").unwrap());
+        assert_eq!(Error::InvalidBool(32), strict(b"i32e").unwrap_err());
+        assert_eq!(Error::InvalidBool(-1), strict(b"i-1e").unwrap_err());
+        assert_eq!(Error::TrailingInput, strict(b"i1ei0e").unwrap_err());
+    }
 
-    #[error("unrecognized prefix character, '{0}'")]
-    UnrecognizedPrefix(u8),
+    #[test]
+    fn test_utf8_policy() {
+        use std::collections::BTreeMap;
 
-    #[error("expected {1}, found: {0}")]
-    UnexpectedPrefix(char, char),
+        // Text when it's valid UTF-8, raw bytes otherwise.
+        #[derive(Debug, PartialEq)]
+        enum Name {
+            Text(String),
+            Raw(Vec<u8>),
+        }
+        impl<'de> Deserialize<'de> for Name {
+            fn deserialize<D: serde::Deserializer<'de>>(
+                d: D,
+            ) -> std::result::Result<Self, D::Error> {
+                struct NameVisitor;
+                impl<'de> serde::de::Visitor<'de> for NameVisitor {
+                    type Value = Name;
+                    fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                        f.write_str("a name")
+                    }
+                    fn visit_str<E>(self, v: &str) -> std::result::Result<Name, E> {
+                        Ok(Name::Text(v.to_string()))
+                    }
+                    fn visit_bytes<E>(self, v: &[u8]) -> std::result::Result<Name, E> {
+                        Ok(Name::Raw(v.to_vec()))
+                    }
+                }
+                d.deserialize_string(NameVisitor)
+            }
+        }
 
-    #[error("unexpected negative sign for unsigned value")]
-    UnexpectedSigned,
+        fn with<'a, T: Deserialize<'a>>(bytes: &'a [u8], policy: Utf8Policy) -> Result<T> {
+            let mut de = Deserializer::from_bytes(bytes).utf8_policy(policy);
+            T::deserialize(&mut de)
+        }
 
-    #[error("integers cannot start with '0' unless they are 0")]
-    UnexpectedZeroPrefix,
+        let bad = b"3:a\xffb";
+        assert!(matches!(
+            with::<String>(bad, Utf8Policy::Reject),
+            Err(Error::Utf8Error(_))
+        ));
+        assert_eq!(
+            "a\u{fffd}b",
+            with::<String>(bad, Utf8Policy::Lossy).unwrap()
+        );
+        assert!(matches!(
+            with::<&str>(bad, Utf8Policy::Lossy),
+            Err(Error::InvalidType { .. })
+        ));
+        assert_eq!(
+            Name::Raw(b"a\xffb".to_vec()),
+            with::<Name>(bad, Utf8Policy::Bytes).unwrap()
+        );
+        assert_eq!(
+            Name::Text("abc".to_string()),
+            with::<Name>(b"3:abc", Utf8Policy::Bytes).unwrap()
+        );
+        assert!(matches!(
+            with::<String>(bad, Utf8Policy::Bytes),
+            Err(Error::InvalidValue { .. })
+        ));
 
-    #[error("Utf8Error: {0}")]
-    Utf8Error(#[from] std::str::Utf8Error),
-}
+        // Dict keys follow the same policy.
+        let map: BTreeMap<String, i64> = with(b"d2:\xffai1ee", Utf8Policy::Lossy).unwrap();
+        assert_eq!(Some(&1), map.get("\u{fffd}a"));
+        assert!(matches!(
+            with::<BTreeMap<String, i64>>(b"d2:\xffai1ee", Utf8Policy::Reject),
+            Err(Error::Utf8Error(_))
+        ));
+    }
+
+    #[test]
+    fn test_limits() {
+        let limits = Limits {
+            max_depth: 2,
+            max_string_len: 3,
+            max_items: 3,
+            max_total_bytes: 12,
+        };
+        let ok: Vec<Vec<&str>> = from_bytes_with(b"ll1:aee", limits).unwrap();
+        assert_eq!(vec![vec!["a"]], ok);
+
+        assert_eq!(
+            Error::DepthLimitExceeded(2),
+            from_bytes_with::<Vec<Vec<Vec<u8>>>>(b"llleee", limits).unwrap_err()
+        );
+        assert_eq!(
+            Error::StringTooLong(4, 3),
+            from_bytes_with::<&str>(b"4:abcd", limits).unwrap_err()
+        );
+        assert_eq!(
+            Error::TooManyItems(3),
+            from_bytes_with::<Vec<u8>>(b"li1ei2ei3ee", limits).unwrap_err()
+        );
+        assert_eq!(
+            Error::InputTooLong(12),
+            from_bytes_with::<&str>(b"11:hello world", limits).unwrap_err()
+        );
+
+        // Keys and values both count as items, so the limit is hit at the second key.
+        #[derive(Deserialize, Debug)]
+        struct Pair {
+            #[allow(dead_code)]
+            a: u8,
+            #[allow(dead_code)]
+            b: u8,
+        }
+        assert_eq!(
+            Error::TooManyItems(3),
+            from_bytes_with::<Pair>(b"d1:ai1e1:b", limits).unwrap_err()
+        );
 
-impl std::convert::From<std::io::Error> for SerbeError {
-    fn from(err: std::io::Error) -> Self {
-        Self::Message(err.to_string())
+        let deep = "l".repeat(300) + &"e".repeat(300);
+        assert_eq!(
+            Error::DepthLimitExceeded(256),
+            from_bytes::<serde::de::IgnoredAny>(deep.as_bytes()).unwrap_err()
+        );
+        assert!(
+            from_bytes_with::<serde::de::IgnoredAny>(deep.as_bytes(), Limits::unlimited()).is_ok()
+        );
     }
-}
 
-impl serde::de::Error for SerbeError {
-    fn custom<T>(msg: T) -> Self
-    where
-        T: std::fmt::Display,
-    {
-        SerbeError::Message(msg.to_string())
+    #[test]
+    fn test_from_datagram() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Query<'a> {
+            t: &'a [u8],
+            y: &'a str,
+            q: &'a str,
+        }
+        let ping = b"d1:q4:ping1:t2:aa1:y1:qe";
+        assert_eq!(
+            Query {
+                t: b"aa",
+                y: "q",
+                q: "ping"
+            },
+            from_datagram(ping).unwrap()
+        );
+
+        let mut two = ping.to_vec();
+        two.extend_from_slice(b"i1e");
+        assert_eq!(
+            Error::TrailingDatagram {
+                parsed: 24,
+                received: 27
+            },
+            from_datagram::<Query>(&two).unwrap_err()
+        );
+        assert_eq!(Error::Eof, from_datagram::<Query>(b"").unwrap_err());
+
+        let deep = "l".repeat(33) + &"e".repeat(33);
+        assert_eq!(
+            Error::DepthLimitExceeded(32),
+            from_datagram::<serde::de::IgnoredAny>(deep.as_bytes()).unwrap_err()
+        );
+        assert_eq!(
+            Error::StringTooLong(4294967295, 65_535),
+            from_datagram::<&[u8]>(b"4294967295:x").unwrap_err()
+        );
+        let huge = format!("65536:{}", "x".repeat(65536));
+        assert_eq!(
+            Error::InputTooLong(65_535),
+            from_datagram::<&[u8]>(huge.as_bytes()).unwrap_err()
+        );
     }
-}
 
-impl serde::ser::Error for Error {
-    fn custom<T>(msg: T) -> Self
-    where
-        T: std::fmt::Display,
-    {
-        SerbeError::Message(msg.to_string())
+    #[test]
+    fn test_hostile_datagrams() {
+        // Every truncation and every single-byte corruption of a real message either decodes or
+        // fails cleanly, and only whole values are ever accepted.
+        let message = b"d1:ad2:id20:abcdefghij01234567896:target20:mnopqrstuvwxyz123456e1:q9:find_node1:t2:aa1:y1:qe";
+        for len in 0..message.len() {
+            let err = from_datagram::<serde::de::IgnoredAny>(&message[..len]).unwrap_err();
+            assert!(!matches!(err, Error::TrailingDatagram { .. }), "{}", len);
+        }
+        for i in 0..message.len() {
+            for &b in b"ilde:0-9\xff" {
+                let mut corrupt = message.to_vec();
+                corrupt[i] = b;
+                if let Err(Error::TrailingDatagram { parsed, received }) =
+                    from_datagram::<serde::de::IgnoredAny>(&corrupt)
+                {
+                    assert!(parsed < received);
+                    assert!(from_datagram::<serde::de::IgnoredAny>(&corrupt[..parsed]).is_ok());
+                }
+            }
+        }
     }
-}
 
-pub type Error = SerbeError;
-pub type Result<T> = std::result::Result<T, Error>;
+    #[test]
+    fn test_from_reader_with() {
+        let val: Vec<String> = from_reader_with(&b"l3:fooe"[..], Limits::default()).unwrap();
+        assert_eq!(vec!["foo".to_string()], val);
 
-pub use de::from_bytes;
-pub use ser::to_bytes;
+        let limits = Limits {
+            max_total_bytes: 4,
+            ..Limits::default()
+        };
+        assert_eq!(
+            Error::InputTooLong(4),
+            from_reader_with::<_, Vec<String>>(&b"l3:fooe"[..], limits).unwrap_err()
+        );
+    }
 
-#[cfg(test)]
-mod test {
-    use super::*;
-    use serde::{Deserialize, Serialize};
+    #[test]
+    fn test_from_reader_io_error() {
+        struct WouldBlock;
+        impl std::io::Read for WouldBlock {
+            fn read(&mut self, _: &mut [u8]) -> std::io::Result<usize> {
+                Err(std::io::ErrorKind::WouldBlock.into())
+            }
+        }
 
-    macro_rules! assert_round_trip {
-        ($v:expr, $t:ty) => {
-            let val: $t = $v;
-            assert_eq!(val, from_bytes::<$t>(&to_bytes(&val).unwrap()).unwrap());
+        let err = from_reader_with::<_, i64>(WouldBlock, Limits::default()).unwrap_err();
+        assert_eq!(Some(std::io::ErrorKind::WouldBlock), err.io_kind());
+        match err {
+            Error::Io(io) => assert_eq!(std::io::ErrorKind::WouldBlock, io.into_inner().kind()),
+            other => panic!("unexpected {:?}", other),
+        }
+        assert_eq!(None, Error::Eof.io_kind());
+    }
+
+    #[test]
+    fn test_append() {
+        #[derive(Serialize)]
+        struct Query<'a> {
+            q: &'a str,
+            t: u16,
+        }
+
+        let mut serializer = Serializer::new();
+        serializer.append(&Query { q: "ping", t: 1 }).unwrap();
+        serializer.append(&Query { q: "find", t: 2 }).unwrap();
+        assert_eq!(
+            &b"d1:q4:ping1:ti1eed1:q4:find1:ti2ee"[..],
+            serializer.bytes()
+        );
+
+        // A failed append leaves what was already written.
+        assert_eq!(
+            Error::NoneValue,
+            serializer.append(&None::<u8>).unwrap_err()
+        );
+        assert!(serializer.append(&vec![Some(1), None]).is_err());
+        serializer.append(&3).unwrap();
+        assert_eq!(
+            &b"d1:q4:ping1:ti1eed1:q4:find1:ti2eei3e"[..],
+            &serializer.into_bytes()[..]
+        );
+
+        let mut bytes = b"le".to_vec();
+        append_to_bytes(&mut bytes, "x").unwrap();
+        assert!(append_to_bytes(&mut bytes, &vec![None::<u8>]).is_err());
+        assert_eq!(b"le1:x", &bytes[..]);
+        let mut serializer = Serializer::from_bytes(bytes);
+        serializer.append(&vec![1u8]).unwrap();
+        assert_eq!(b"le1:xli1ee", serializer.bytes());
+    }
+
+    #[test]
+    fn test_missing_value_for_key() {
+        #[derive(Deserialize, Debug)]
+        struct Optional {
+            #[allow(dead_code)]
+            a: Option<u8>,
+        }
+        assert_eq!(
+            Error::MissingValueForKey("a".to_string()),
+            from_bytes::<Optional>(b"d1:ae").unwrap_err()
+        );
+        assert_eq!(
+            Error::MissingValueForKey("a".to_string()),
+            from_bytes::<Optional>(b"d1:a").unwrap_err()
+        );
+        assert_eq!(
+            Error::MissingValueForKey("key".to_string()),
+            from_bytes::<std::collections::HashMap<String, String>>(b"d3:key").unwrap_err()
+        );
+        // Unknown keys still need a value.
+        assert_eq!(
+            Error::MissingValueForKey("z".to_string()),
+            from_bytes::<Optional>(b"d1:ai1e1:ze").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_skip_value() {
+        let mut de = Deserializer::from_bytes(b"d1:ali1e2:xye1:bi2ee4:rest");
+        de.skip_value().unwrap();
+        assert_eq!("rest", <&str>::deserialize(&mut de).unwrap());
+        de.end().unwrap();
+
+        let mut de = Deserializer::from_bytes(b"li1ei-0ee");
+        assert_eq!(Error::NegativeZero, de.skip_value().unwrap_err());
+        let mut de = Deserializer::from_bytes(b"d1:ae");
+        assert_eq!(
+            Error::MissingValueForKey("a".to_string()),
+            de.skip_value().unwrap_err()
+        );
+        let mut de = Deserializer::from_bytes(b"llleee").limits(Limits {
+            max_depth: 2,
+            ..Limits::default()
+        });
+        assert_eq!(Error::DepthLimitExceeded(2), de.skip_value().unwrap_err());
+        let mut de = Deserializer::from_bytes(b"li1ei2ei3ee").limits(Limits {
+            max_items: 3,
+            ..Limits::default()
+        });
+        assert_eq!(Error::TooManyItems(3), de.skip_value().unwrap_err());
+    }
+
+    #[test]
+    fn test_ignore_unknown_fields() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Known {
+            b: u8,
+        }
+
+        // Every kind of value can be skipped, at any depth.
+        let bytes = b"d1:ad1:xli-1e0:d1:yleeee1:bi2e1:ci-3e1:d4:spame";
+        assert_eq!(Known { b: 2 }, from_bytes(bytes).unwrap());
+    }
+
+    #[test]
+    fn test_field_aliases() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Meta {
+            #[serde(rename = "created by", alias = "created_by")]
+            created_by: String,
+            #[serde(rename = "url-list", alias = "url list", default)]
+            url_list: Vec<String>,
+        }
+        fn strict(bytes: &[u8]) -> Result<Meta> {
+            let mut de = Deserializer::from_bytes(bytes).deny_unknown_fields(true);
+            Meta::deserialize(&mut de)
+        }
+        let expected = Meta {
+            created_by: "me".to_string(),
+            url_list: vec!["u".to_string()],
         };
+
+        assert_eq!(
+            expected,
+            from_bytes(b"d10:created by2:me8:url-listl1:uee").unwrap()
+        );
+        assert_eq!(
+            expected,
+            from_bytes(b"d10:created_by2:me8:url listl1:uee").unwrap()
+        );
+        // Aliases are known fields.
+        assert_eq!(
+            expected,
+            strict(b"d10:created_by2:me8:url listl1:uee").unwrap()
+        );
+
+        // Matching is byte-exact.
+        assert_eq!(
+            Error::MissingField {
+                field: "created by",
+                path: String::new(),
+            },
+            from_bytes::<Meta>(b"d10:Created By2:mee").unwrap_err()
+        );
+        // Two spellings of one field are a duplicate.
+        assert_eq!(
+            Error::DuplicateField {
+                field: "created by",
+                path: String::new(),
+            },
+            from_bytes::<Meta>(b"d10:created by1:a10:created_by1:be").unwrap_err()
+        );
     }
 
     #[test]
-    fn test_bool() {
-        let val: bool = from_bytes(b"i0e").unwrap();
-        assert_eq!(false, val);
+    fn test_deny_unknown_fields() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(deny_unknown_fields)]
+        struct Denied {
+            b: u8,
+        }
+        assert_eq!(Denied { b: 2 }, from_bytes(b"d1:bi2ee").unwrap());
+        assert_eq!(
+            Error::UnknownField {
+                key: "a".to_string(),
+                path: String::new(),
+            },
+            from_bytes::<Denied>(b"d1:ai1e1:bi2ee").unwrap_err()
+        );
 
-        let val: bool = from_bytes(b"i1e").unwrap();
-        assert_eq!(true, val);
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Inner {
+            b: u8,
+        }
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Outer {
+            inner: Vec<Inner>,
+            map: std::collections::BTreeMap<String, u8>,
+        }
+        fn strict(bytes: &[u8]) -> Result<Outer> {
+            let mut de = Deserializer::from_bytes(bytes).deny_unknown_fields(true);
+            let val = Outer::deserialize(&mut de)?;
+            de.end()?;
+            Ok(val)
+        }
 
-        let val: bool = from_bytes(b"i32e").unwrap();
-        assert_eq!(true, val);
+        // Keys of a map type are never unknown.
+        assert!(strict(b"d5:innerld1:bi1eee3:mapd3:anyi1eee").is_ok());
+        assert_eq!(
+            Error::UnknownField {
+                key: "c".to_string(),
+                path: "inner.0".to_string(),
+            },
+            strict(b"d5:innerld1:bi1e1:ci2eee3:mapdee").unwrap_err()
+        );
+        assert_eq!(
+            Error::UnknownField {
+                key: "extra".to_string(),
+                path: "".to_string(),
+            },
+            strict(b"d5:extrai1e5:innerle3:mapdee").unwrap_err()
+        );
 
-        assert_round_trip!(false, bool);
-        assert_round_trip!(true, bool);
+        // A bad key is reported as itself, not as an unknown field.
+        assert_eq!(
+            Error::NoDigitsInNumber,
+            strict(b"d5:innerlei1ei2ee").unwrap_err()
+        );
+        let mut de = Deserializer::from_bytes(b"d5:innerle3:mapdee")
+            .deny_unknown_fields(true)
+            .limits(Limits {
+                max_string_len: 4,
+                ..Limits::default()
+            });
+        assert_eq!(
+            Error::StringTooLong(5, 4),
+            Outer::deserialize(&mut de).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_serde_error_kinds() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(deny_unknown_fields)]
+        struct Inner {
+            b: u8,
+            s: String,
+        }
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Outer {
+            inner: Vec<Inner>,
+        }
+
+        assert_eq!(
+            Error::MissingField {
+                field: "s",
+                path: "inner.0".to_string(),
+            },
+            from_bytes::<Outer>(b"d5:innerld1:bi1eeee").unwrap_err()
+        );
+        assert_eq!(
+            Error::MissingField {
+                field: "inner",
+                path: String::new(),
+            },
+            from_bytes::<Outer>(b"de").unwrap_err()
+        );
+        assert_eq!(
+            Error::UnknownField {
+                key: "x".to_string(),
+                path: "inner.1".to_string(),
+            },
+            from_bytes::<Outer>(b"d5:innerld1:bi1e1:s0:ed1:xi1eeee").unwrap_err()
+        );
+        assert_eq!(
+            Error::DuplicateField {
+                field: "b",
+                path: "inner.0".to_string(),
+            },
+            from_bytes::<Outer>(b"d5:innerld1:bi1e1:bi2eeee").unwrap_err()
+        );
     }
 
     #[test]
@@ -121,8 +632,6 @@ mod test {
         assert_round_trip!(700, u16);
         assert_round_trip!(123457, u32);
         assert_round_trip!(12345678999, u64);
-
-        // TODO: check overflow
     }
 
     #[test]
@@ -146,6 +655,28 @@ mod test {
         assert_round_trip!(-12345678999, i64);
     }
 
+    #[test]
+    fn test_integer_overflow() {
+        assert_eq!(
+            u64::MAX,
+            from_bytes::<u64>(b"i18446744073709551615e").unwrap()
+        );
+        assert_eq!(
+            Error::IntegerOverflow,
+            from_bytes::<u64>(b"i18446744073709551616e").unwrap_err()
+        );
+        let long = format!("i-{}e", "9".repeat(101));
+        assert_eq!(
+            Error::IntegerOverflow,
+            from_bytes::<i64>(long.as_bytes()).unwrap_err()
+        );
+        let long = format!("{}:abc", "9".repeat(101));
+        assert_eq!(
+            Error::IntegerOverflow,
+            from_bytes::<&str>(long.as_bytes()).unwrap_err()
+        );
+    }
+
     #[test]
     fn test_missing_e() {
         assert_eq!(Error::Eof, from_bytes::<u32>(b"i56").unwrap_err(),);
@@ -186,16 +717,131 @@ mod test {
             Error::UnexpectedZeroPrefix,
             from_bytes::<i16>(b"i00e").unwrap_err()
         );
+        assert_eq!(
+            Error::UnexpectedZeroPrefix,
+            from_bytes::<u64>(b"i0000000001e").unwrap_err()
+        );
+        assert_eq!(100, from_bytes::<u16>(b"i100e").unwrap());
+    }
+
+    #[test]
+    fn test_string_length_digits() {
+        // String lengths go through the same digit parser as integers.
+        assert_eq!(
+            Error::UnexpectedZeroPrefix,
+            from_bytes::<&str>(b"05:hello").unwrap_err()
+        );
+        assert_eq!(
+            Error::UnexpectedZeroPrefix,
+            from_bytes::<&str>(b"00:").unwrap_err()
+        );
+        assert_eq!(
+            Error::NoDigitsInNumber,
+            from_bytes::<&str>(b":hello").unwrap_err()
+        );
+        assert_eq!(
+            Error::UnexpectedZeroPrefix,
+            from_bytes::<Vec<String>>(b"l1:a01:be").unwrap_err()
+        );
+        assert_eq!("", from_bytes::<&str>(b"0:").unwrap());
+        assert_eq!("0123456789", from_bytes::<&str>(b"10:0123456789").unwrap());
+    }
+
+    #[test]
+    fn test_negative_zero() {
+        // Rejected, as `BEReader` does, whatever the target type.
+        assert_eq!(Error::NegativeZero, from_bytes::<i32>(b"i-0e").unwrap_err());
+        assert_eq!(Error::NegativeZero, from_bytes::<u8>(b"i-0e").unwrap_err());
+        assert_eq!(
+            Error::UnexpectedZeroPrefix,
+            from_bytes::<i32>(b"i-00e").unwrap_err()
+        );
+        assert_eq!(0, from_bytes::<i32>(b"i0e").unwrap());
+    }
+
+    #[test]
+    fn test_negative_string_length() {
+        assert_eq!(
+            Error::NegativeStringLength,
+            from_bytes::<&str>(b"-3:abc").unwrap_err()
+        );
+        assert_eq!(
+            Error::NegativeStringLength,
+            from_bytes::<Vec<String>>(b"l-0:e").unwrap_err()
+        );
     }
 
     #[test]
     fn test_leading_negative_in_unsigned() {
         assert_eq!(
-            Error::Message("invalid value: integer `-5`, expected u16".to_string()),
+            Error::IntegerOutOfRange {
+                value: -5,
+                target: "u16",
+                path: "".to_string()
+            },
             from_bytes::<u16>(b"i-5e").unwrap_err()
         );
     }
 
+    #[test]
+    fn test_integer_out_of_range() {
+        assert_eq!(
+            Error::IntegerOutOfRange {
+                value: 65535,
+                target: "u8",
+                path: "".to_string()
+            },
+            from_bytes::<u8>(b"i65535e").unwrap_err()
+        );
+        assert_eq!(
+            Error::IntegerOutOfRange {
+                value: -129,
+                target: "i8",
+                path: "".to_string()
+            },
+            from_bytes::<i8>(b"i-129e").unwrap_err()
+        );
+        assert_eq!(
+            Error::IntegerOutOfRange {
+                value: 9223372036854775808,
+                target: "i64",
+                path: "".to_string()
+            },
+            from_bytes::<i64>(b"i9223372036854775808e").unwrap_err()
+        );
+        assert_eq!(
+            u64::MAX,
+            from_bytes::<u64>(b"i18446744073709551615e").unwrap()
+        );
+        assert_eq!(
+            i64::MIN,
+            from_bytes::<i64>(b"i-9223372036854775808e").unwrap()
+        );
+    }
+
+    #[test]
+    fn test_integer_out_of_range_path() {
+        #[derive(Deserialize, Debug)]
+        struct File {
+            #[allow(dead_code)]
+            length: u16,
+        }
+        #[derive(Deserialize, Debug)]
+        struct Info {
+            #[allow(dead_code)]
+            files: Vec<File>,
+        }
+
+        assert_eq!(
+            Error::IntegerOutOfRange {
+                value: 70000,
+                target: "u16",
+                path: "files.1.length".to_string()
+            },
+            from_bytes::<Info>(b"d5:filesld6:lengthi5eed6:lengthi70000eeee").unwrap_err()
+        );
+    }
+
     #[test]
     fn test_string() {
         let val: String = from_bytes(b"4:yarn").unwrap();
@@ -208,6 +854,31 @@ mod test {
         assert_round_trip!("hellion".to_string(), String);
     }
 
+    #[test]
+    fn test_truncated_string() {
+        assert_eq!(Error::Eof, from_bytes::<&str>(b"5:spam").unwrap_err());
+        assert_eq!(Error::Eof, from_bytes::<Vec<&str>>(b"l1:").unwrap_err());
+
+        // Lengths past the end of the input are caught before slicing, however large.
+        assert_eq!(
+            Error::Eof,
+            from_bytes_with::<&str>(b"4294967296:abc", Limits::unlimited()).unwrap_err()
+        );
+        assert_eq!(
+            Error::Eof,
+            from_bytes_with::<&str>(b"9223372036854775807:abc", Limits::unlimited()).unwrap_err()
+        );
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_length_overflow() {
+        assert_eq!(
+            Error::LengthOverflow(4294967296),
+            from_bytes_with::<&str>(b"4294967296:abc", Limits::unlimited()).unwrap_err()
+        );
+    }
+
     #[test]
     fn test_missing_colon() {
         assert_eq!(
@@ -244,6 +915,99 @@ mod test {
         assert_round_trip!(vec!["one", "two", "three"], Vec<&str>);
     }
 
+    #[test]
+    fn test_tuples() {
+        assert_eq!(&b"li1e3:fooe"[..], &to_bytes(&(1u8, "foo")).unwrap()[..]);
+        assert_round_trip!((1, "foo".to_string(), vec![2, 3]), (u8, String, Vec<u8>));
+
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Pair(i64, String);
+        assert_eq!(
+            &b"li-1e1:ae"[..],
+            &to_bytes(&Pair(-1, "a".to_string())).unwrap()[..]
+        );
+        assert_round_trip!(Pair(-1, "a".to_string()), Pair);
+
+        // Nodes in a DHT response, as [host, port] pairs.
+        let nodes: Vec<(&str, u16)> = from_bytes(b"ll1:ai1eel1:bi2eee").unwrap();
+        assert_eq!(vec![("a", 1), ("b", 2)], nodes);
+
+        assert!(matches!(
+            from_bytes::<(u8, u8)>(b"li1ee"),
+            Err(Error::InvalidLength { len: 1, .. })
+        ));
+        assert_eq!(
+            Error::ExpectedListEnd,
+            from_bytes::<(u8, u8)>(b"li1ei2ei3ee").unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_newtypes_and_chars() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Port(u16);
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Peer {
+            port: Port,
+            flag: char,
+            name: Option<Name>,
+        }
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Name(String);
+
+        let peer = Peer {
+            port: Port(6881),
+            flag: '\u{3a9}',
+            name: None,
+        };
+        assert_eq!(
+            "d4:flag2:\u{3a9}4:porti6881ee".as_bytes(),
+            to_bytes(&peer).unwrap().as_slice()
+        );
+        assert_round_trip!(peer, Peer);
+        assert_round_trip!(Name("x".to_string()), Name);
+
+        assert!(matches!(
+            from_bytes::<char>(b"2:ab"),
+            Err(Error::InvalidValue { .. })
+        ));
+        assert!(matches!(
+            from_bytes::<char>(b"0:"),
+            Err(Error::InvalidValue { .. })
+        ));
+    }
+
+    #[test]
+    fn test_unsupported_types() {
+        #[derive(Serialize, Deserialize, Debug)]
+        struct Unit;
+        #[derive(Serialize, Deserialize, Debug)]
+        enum Event {
+            Started,
+            Stopped(u8),
+        }
+
+        for result in [
+            to_bytes(&()),
+            to_bytes(&Unit),
+            to_bytes(&Event::Started),
+            to_bytes(&Event::Stopped(1)),
+        ] {
+            assert!(matches!(result, Err(Error::Message(_))), "{:?}", result);
+        }
+        assert_eq!(
+            Error::Message("bencode cannot represent the enum variant Event::Started".to_string()),
+            to_bytes(&Event::Started).unwrap_err()
+        );
+
+        assert!(matches!(from_bytes::<()>(b"0:"), Err(Error::Message(_))));
+        assert!(matches!(from_bytes::<Unit>(b"0:"), Err(Error::Message(_))));
+        assert!(matches!(
+            from_bytes::<Event>(b"7:Started"),
+            Err(Error::Message(_))
+        ));
+    }
+
     #[test]
     fn test_missing_l() {
         assert_eq!(
@@ -254,11 +1018,30 @@ mod test {
 
     #[test]
     fn test_missing_list_e() {
-        // Missing 'e' manifests as EOF because the list is never terminated.
         assert_eq!(
-            Error::Eof,
+            Error::UnterminatedContainer {
+                kind: "list",
+                offset: 0
+            },
             from_bytes::<Vec<u16>>(b"li0ei0ei0e").unwrap_err()
         );
+        // The innermost open container is reported.
+        assert_eq!(
+            Error::UnterminatedContainer {
+                kind: "list",
+                offset: 3
+            },
+            from_bytes::<Vec<Vec<u16>>>(b"lleli0e").unwrap_err()
+        );
+        assert_eq!(
+            Error::UnterminatedContainer {
+                kind: "dict",
+                offset: 1
+            },
+            from_bytes::<Vec<serde::de::IgnoredAny>>(b"ld1:ai0e").unwrap_err()
+        );
+        // Running out inside an element is still EOF.
+        assert_eq!(Error::Eof, from_bytes::<Vec<u16>>(b"li0ei0").unwrap_err());
     }
 
     #[test]
@@ -285,8 +1068,176 @@ mod test {
             },
             TestStruct
         );
+    }
 
-        // TODO: test ignored fields
+    #[test]
+    fn test_non_ascii_key_order() {
+        #[derive(Serialize, Deserialize, PartialEq, Debug)]
+        struct Keys {
+            #[serde(rename = "\u{3a9}mega")]
+            omega: u8,
+            #[serde(rename = "\u{f1}ame")]
+            name: u8,
+            zeta: u8,
+            #[serde(rename = "Zeta")]
+            upper_zeta: u8,
+        }
+
+        let val = Keys {
+            omega: 1,
+            name: 2,
+            zeta: 3,
+            upper_zeta: 4,
+        };
+        // Sorted by raw bytes: 'Z' < 'z' < U+00F1 (c3 b1) < U+03A9 (ce a9).
+        assert_eq!(
+            "d4:Zetai4e4:zetai3e5:\u{f1}amei2e6:\u{3a9}megai1ee".as_bytes(),
+            to_bytes(&val).unwrap().as_slice()
+        );
+        assert_round_trip!(val, Keys);
+    }
+
+    #[test]
+    fn test_serialize_map() {
+        use std::collections::{BTreeMap, HashMap};
+
+        let mut map = HashMap::new();
+        map.insert("zeta", 1);
+        map.insert("alpha", 2);
+        map.insert("\u{f1}", 3);
+        map.insert("Zeta", 4);
+        assert_eq!(
+            "d4:Zetai4e5:alphai2e4:zetai1e2:\u{f1}i3ee".as_bytes(),
+            to_bytes(&map).unwrap().as_slice()
+        );
+        assert_eq!(map, from_bytes(&to_bytes(&map).unwrap()).unwrap());
+
+        // None values are left out, as for struct fields.
+        let mut map = BTreeMap::new();
+        map.insert("a", Some(1));
+        map.insert("b", None);
+        assert_eq!(b"d1:ai1ee", to_bytes(&map).unwrap().as_slice());
+
+        let mut map = BTreeMap::new();
+        map.insert(vec![1], 1);
+        assert_eq!(Error::KeyMustBeString, to_bytes(&map).unwrap_err());
+    }
+
+    #[test]
+    fn test_integer_keys() {
+        use std::collections::{BTreeMap, HashMap};
+
+        // Keys are written as decimal strings, so they sort as strings.
+        let mut map = HashMap::new();
+        map.insert(9u32, "nine");
+        map.insert(10u32, "ten");
+        let bytes = to_bytes(&map).unwrap();
+        assert_eq!(&b"d2:103:ten1:94:ninee"[..], &bytes[..]);
+        assert_eq!(map, from_bytes::<HashMap<u32, &str>>(&bytes).unwrap());
+
+        let map: BTreeMap<i64, u8> = from_bytes(b"d2:-1i1e1:0i0ee").unwrap();
+        assert_eq!(vec![(-1, 1), (0, 0)], map.into_iter().collect::<Vec<_>>());
+        assert_eq!(
+            &b"d2:-1i1ee"[..],
+            &to_bytes(&[(-1i8, 1)].iter().copied().collect::<BTreeMap<_, _>>()).unwrap()[..]
+        );
+
+        for key in ["x", "", "01", "-0", "+1", "1.5", "99999999999999999999999"] {
+            let bytes = format!("d{}:{}i1ee", key.len(), key);
+            assert_eq!(
+                Error::KeyNotInteger(key.to_string()),
+                from_bytes::<HashMap<i64, u8>>(bytes.as_bytes()).unwrap_err(),
+                "{}",
+                key
+            );
+        }
+        assert_eq!(
+            Error::IntegerOutOfRange {
+                value: 300,
+                target: "u8",
+                path: "a".to_string()
+            },
+            from_bytes::<HashMap<&str, HashMap<u8, u8>>>(b"d1:ad3:300i1eee").unwrap_err()
+        );
+
+        // String keys are unaffected.
+        let map: HashMap<String, u8> = from_bytes(b"d2:10i1ee").unwrap();
+        assert_eq!(Some(&1), map.get("10"));
+    }
+
+    #[test]
+    fn test_preserve_order() {
+        #[derive(Serialize)]
+        struct Inner {
+            z: u8,
+            a: u8,
+        }
+
+        #[derive(Serialize)]
+        struct Outer {
+            name: &'static str,
+            inner: Inner,
+            length: u8,
+        }
+
+        let val = Outer {
+            name: "n",
+            inner: Inner { z: 1, a: 2 },
+            length: 3,
+        };
+        let mut serializer = Serializer::new().preserve_order(true);
+        serializer.append(&val).unwrap();
+        assert_eq!(
+            &b"d4:name1:n5:innerd1:zi1e1:ai2ee6:lengthi3ee"[..],
+            serializer.bytes()
+        );
+        assert_eq!(
+            &b"d5:innerd1:ai2e1:zi1ee6:lengthi3e4:name1:ne"[..],
+            &to_bytes(&val).unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        #[derive(Serialize)]
+        struct Renamed {
+            #[serde(rename = "url-list")]
+            urls: Vec<&'static str>,
+            #[serde(rename = "url-list")]
+            mirrors: Vec<&'static str>,
+        }
+        let val = Renamed {
+            urls: vec!["a"],
+            mirrors: vec!["b"],
+        };
+        assert_eq!(
+            Error::DuplicateKey("url-list".to_string()),
+            to_bytes(&val).unwrap_err()
+        );
+        let mut serializer = Serializer::new().preserve_order(true);
+        assert_eq!(
+            Error::DuplicateKey("url-list".to_string()),
+            serializer.append(&val).unwrap_err()
+        );
+
+        #[derive(Serialize)]
+        struct Extra {
+            name: &'static str,
+        }
+        #[derive(Serialize)]
+        struct Flattened {
+            name: &'static str,
+            #[serde(flatten)]
+            extra: Extra,
+        }
+        let val = Flattened {
+            name: "a",
+            extra: Extra { name: "b" },
+        };
+        assert_eq!(
+            Error::DuplicateKey("name".to_string()),
+            to_bytes(&val).unwrap_err()
+        );
     }
 
     #[test]
@@ -326,12 +1277,57 @@ mod test {
             val
         );
 
-        let bytes = to_bytes(&TestWithOption { i: None, s: None }).unwrap();
-        println!(
-            "BYTES: {} == {:?}",
-            std::str::from_utf8(&bytes).unwrap(),
-            bytes
+        assert_eq!(
+            b"de".to_vec(),
+            to_bytes(&TestWithOption { i: None, s: None }).unwrap()
         );
         assert_round_trip!(TestWithOption { i: None, s: None }, TestWithOption);
+        assert_round_trip!(
+            TestWithOption {
+                i: Some(3),
+                s: Some("three")
+            },
+            TestWithOption
+        );
+    }
+
+    #[test]
+    fn test_serialize_option() {
+        assert_eq!(b"i5e".to_vec(), to_bytes(&Some(5u8)).unwrap());
+        assert_eq!(Error::NoneValue, to_bytes(&None::<u8>).unwrap_err());
+
+        assert_eq!(
+            b"li1ei2ee".to_vec(),
+            to_bytes(&vec![Some(1u8), Some(2)]).unwrap()
+        );
+        assert_eq!(
+            Error::NoneInContainer {
+                container: "list",
+                index: 1
+            },
+            to_bytes(&vec![Some(1u8), None, Some(2)]).unwrap_err()
+        );
+
+        // The index is the one in the innermost list.
+        assert_eq!(
+            Error::NoneInContainer {
+                container: "list",
+                index: 0
+            },
+            to_bytes(&vec![vec![Some(1u8)], vec![None]]).unwrap_err()
+        );
+
+        // A None inside a list inside a field is still an error.
+        #[derive(Serialize)]
+        struct WithList {
+            list: Vec<Option<u8>>,
+        }
+        assert_eq!(
+            Error::NoneInContainer {
+                container: "list",
+                index: 0
+            },
+            to_bytes(&WithList { list: vec![None] }).unwrap_err()
+        );
     }
 }
@@ -0,0 +1,69 @@
+//! Safety limits shared by every decoding entry point.
+//!
+//! The same `Limits` value can be passed to `from_bytes_with`, `from_reader_with`,
+//! `BEReader::with_limits` and `validate`, so the serde and DOM layers accept exactly the same
+//! inputs.
+
+/// Bounds on the size and shape of a single decoded value.
+///
+/// The defaults are generous enough for any real torrent while keeping a small, malicious input
+/// from exhausting the stack or memory. Use `Limits::unlimited()` to turn every check off.
+///
+/// ```
+/// use beeenn::Limits;
+///
+/// let limits = Limits {
+///     max_depth: 8,
+///     ..Limits::default()
+/// };
+/// assert_eq!(8, limits.max_depth);
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Limits {
+    /// How deeply lists and dicts may be nested. A top-level list is at depth 1.
+    pub max_depth: usize,
+
+    /// The longest string, in bytes, including dict keys.
+    pub max_string_len: usize,
+
+    /// The number of values, at any depth, that make up one top-level value, counting the
+    /// top-level value itself. Each dict entry counts once for its key and once for its value.
+    pub max_items: usize,
+
+    /// The number of bytes of input that one top-level value may span.
+    pub max_total_bytes: usize,
+}
+
+impl Limits {
+    /// Limits that never reject anything.
+    pub fn unlimited() -> Self {
+        Limits {
+            max_depth: usize::MAX,
+            max_string_len: usize::MAX,
+            max_items: usize::MAX,
+            max_total_bytes: usize::MAX,
+        }
+    }
+
+    /// Tight limits for a single small message, such as a DHT packet. Nothing larger than the
+    /// biggest UDP payload is accepted. `from_datagram` uses these.
+    pub fn datagram() -> Self {
+        Limits {
+            max_depth: 32,
+            max_string_len: 65_535,
+            max_items: 16_384,
+            max_total_bytes: 65_535,
+        }
+    }
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_depth: 256,
+            max_string_len: 64 << 20,
+            max_items: 10_000_000,
+            max_total_bytes: 256 << 20,
+        }
+    }
+}
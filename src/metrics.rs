@@ -0,0 +1,100 @@
+//! Hooks for counting what the decoders do.
+//!
+//! A program installs one `Metrics` implementation with `set_metrics`, as it would a logger, and
+//! every decoding entry point reports to it. Servers can use this to export counters of payload
+//! sizes and malformed input without wrapping each call.
+//!
+//! Reports come from `BEReader::next_value`, `BESliceReader::next_value`, `from_bytes`,
+//! `from_bytes_with`, `from_datagram` and `from_reader_with`. A `Deserializer` driven by hand
+//! doesn't report.
+//!
+//! ```
+//! use beeenn::metrics::{set_metrics, Metrics};
+//! # #[cfg(any(feature = "serde", feature = "value"))]
+//! use beeenn::{Error, Kind};
+//! use std::sync::atomic::{AtomicUsize, Ordering};
+//!
+//! #[derive(Default)]
+//! struct Counters {
+//!     bytes: AtomicUsize,
+//!     eof: AtomicUsize,
+//!     other_errors: AtomicUsize,
+//! }
+//!
+//! impl Metrics for Counters {
+//!     fn decoded(&self, len: usize, _values: usize) {
+//!         self.bytes.fetch_add(len, Ordering::Relaxed);
+//!     }
+//!
+//!     # #[cfg(any(feature = "serde", feature = "value"))]
+//!     fn error(&self, error: &Error) {
+//!         match error.kind() {
+//!             Kind::Eof => self.eof.fetch_add(1, Ordering::Relaxed),
+//!             _ => self.other_errors.fetch_add(1, Ordering::Relaxed),
+//!         };
+//!     }
+//! }
+//!
+//! static COUNTERS: Counters = Counters {
+//!     bytes: AtomicUsize::new(0),
+//!     eof: AtomicUsize::new(0),
+//!     other_errors: AtomicUsize::new(0),
+//! };
+//!
+//! set_metrics(&COUNTERS).ok();
+//! # #[cfg(feature = "serde")] {
+//! let _ = beeenn::from_bytes::<u8>(b"i1e");
+//! let _ = beeenn::from_bytes::<u8>(b"i1");
+//! assert_eq!(3, COUNTERS.bytes.load(Ordering::Relaxed));
+//! assert_eq!(1, COUNTERS.eof.load(Ordering::Relaxed));
+//! # }
+//! ```
+
+#[cfg(any(feature = "serde", feature = "value"))]
+use crate::error::Error;
+
+use std::sync::OnceLock;
+
+/// Receives reports from the decoders. Every method does nothing by default.
+///
+/// Methods are called on the decoding thread, so they should be cheap: bump an atomic counter
+/// rather than take a lock.
+pub trait Metrics: Sync {
+    /// A top-level value was decoded from `len` bytes. `values` counts every value in it, at any
+    /// depth, as `Limits::max_items` does.
+    fn decoded(&self, len: usize, values: usize) {
+        let _ = (len, values);
+    }
+
+    /// Decoding failed with `error`. `error.kind()` groups errors for counting.
+    #[cfg(any(feature = "serde", feature = "value"))]
+    fn error(&self, error: &Error) {
+        let _ = error;
+    }
+}
+
+static METRICS: OnceLock<&'static dyn Metrics> = OnceLock::new();
+
+/// Installs `metrics` for the rest of the program.
+///
+/// Only the first call succeeds. Later calls return the `metrics` they were given.
+pub fn set_metrics(metrics: &'static dyn Metrics) -> Result<(), &'static dyn Metrics> {
+    METRICS.set(metrics)
+}
+
+// Reports to the installed `Metrics`, if any. Nothing decodes without a feature enabled.
+#[cfg(any(feature = "serde", feature = "value"))]
+pub(crate) fn report<F: FnOnce(&dyn Metrics)>(f: F) {
+    if let Some(metrics) = METRICS.get() {
+        f(*metrics);
+    }
+}
+
+// Reports the outcome of decoding one top-level value.
+#[cfg(any(feature = "serde", feature = "value"))]
+pub(crate) fn report_result<T>(result: &Result<T, Error>, len: usize, values: usize) {
+    report(|metrics| match result {
+        Ok(_) => metrics.decoded(len, values),
+        Err(err) => metrics.error(err),
+    });
+}
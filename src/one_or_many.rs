@@ -0,0 +1,157 @@
+//! Fields that hold either one value or a list of them.
+
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::value::{
+    BorrowedBytesDeserializer, BorrowedStrDeserializer, BytesDeserializer, MapAccessDeserializer,
+    StrDeserializer,
+};
+use serde::de::{self, Deserialize, Deserializer, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+
+/// A list that may also be written as a single bare value.
+///
+/// Some keys (`url-list`, `httpseeds`, and `announce-list` from buggy clients) hold a single
+/// value in some files and a list in others. `OneOrMany<T>` reads either form into a `Vec<T>`
+/// and always writes a list.
+///
+/// `T` must not itself be a list, or a single list value would be read as many values.
+///
+/// ```
+/// use beeenn::OneOrMany;
+///
+/// let one: OneOrMany<String> = beeenn::from_bytes(b"3:url").unwrap();
+/// let many: OneOrMany<String> = beeenn::from_bytes(b"l1:a1:be").unwrap();
+/// assert_eq!(vec!["url"], one.0);
+/// assert_eq!(vec!["a", "b"], many.0);
+/// assert_eq!(b"l3:urle", &beeenn::to_bytes(&one).unwrap()[..]);
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OneOrMany<T>(pub Vec<T>);
+
+impl<T> OneOrMany<T> {
+    pub fn into_inner(self) -> Vec<T> {
+        self.0
+    }
+}
+
+impl<T> From<Vec<T>> for OneOrMany<T> {
+    fn from(list: Vec<T>) -> Self {
+        OneOrMany(list)
+    }
+}
+
+impl<T: Serialize> Serialize for OneOrMany<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.0.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for OneOrMany<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer
+            .deserialize_any(OneOrManyVisitor(PhantomData))
+            .map(OneOrMany)
+    }
+}
+
+// Collects a list, or hands any other value to `T` through a deserializer of its own.
+struct OneOrManyVisitor<T>(PhantomData<T>);
+
+impl<'de, T: Deserialize<'de>> Visitor<'de> for OneOrManyVisitor<T> {
+    type Value = Vec<T>;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a value or a list of values")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Vec<T>, A::Error> {
+        let mut list = Vec::new();
+        while let Some(value) = seq.next_element()? {
+            list.push(value);
+        }
+        Ok(list)
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Vec<T>, A::Error> {
+        T::deserialize(MapAccessDeserializer::new(map)).map(|value| vec![value])
+    }
+
+    fn visit_i64<E: de::Error>(self, v: i64) -> Result<Vec<T>, E> {
+        T::deserialize(v.into_deserializer()).map(|value| vec![value])
+    }
+
+    fn visit_u64<E: de::Error>(self, v: u64) -> Result<Vec<T>, E> {
+        T::deserialize(v.into_deserializer()).map(|value| vec![value])
+    }
+
+    fn visit_str<E: de::Error>(self, v: &str) -> Result<Vec<T>, E> {
+        T::deserialize(StrDeserializer::new(v)).map(|value| vec![value])
+    }
+
+    fn visit_borrowed_str<E: de::Error>(self, v: &'de str) -> Result<Vec<T>, E> {
+        T::deserialize(BorrowedStrDeserializer::new(v)).map(|value| vec![value])
+    }
+
+    fn visit_bytes<E: de::Error>(self, v: &[u8]) -> Result<Vec<T>, E> {
+        T::deserialize(BytesDeserializer::new(v)).map(|value| vec![value])
+    }
+
+    fn visit_borrowed_bytes<E: de::Error>(self, v: &'de [u8]) -> Result<Vec<T>, E> {
+        T::deserialize(BorrowedBytesDeserializer::new(v)).map(|value| vec![value])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{from_bytes, to_bytes};
+
+    #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+    struct Tracker {
+        url: String,
+        tier: u8,
+    }
+
+    #[test]
+    fn test_forms() {
+        assert_eq!(vec![7], from_bytes::<OneOrMany<u16>>(b"i7e").unwrap().0);
+        assert_eq!(vec![-7], from_bytes::<OneOrMany<i64>>(b"i-7e").unwrap().0);
+        assert_eq!(
+            vec![1, 2],
+            from_bytes::<OneOrMany<u16>>(b"li1ei2ee").unwrap().0
+        );
+        assert!(from_bytes::<OneOrMany<u16>>(b"le").unwrap().0.is_empty());
+        assert_eq!(
+            vec![&b"\xff"[..]],
+            from_bytes::<OneOrMany<&[u8]>>(b"1:\xff").unwrap().0
+        );
+
+        let tracker = Tracker {
+            url: "u".to_string(),
+            tier: 1,
+        };
+        let one: OneOrMany<Tracker> = from_bytes(b"d4:tieri1e3:url1:ue").unwrap();
+        assert_eq!(vec![tracker], one.0);
+        assert_eq!(&b"ld4:tieri1e3:url1:uee"[..], &to_bytes(&one).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_field() {
+        #[derive(Debug, PartialEq, serde::Serialize, serde::Deserialize)]
+        struct MetaInfo {
+            #[serde(rename = "url-list", default)]
+            url_list: OneOrMany<String>,
+        }
+
+        let val: MetaInfo = from_bytes(b"d8:url-list1:ae").unwrap();
+        assert_eq!(vec!["a"], val.url_list.into_inner());
+        let val: MetaInfo = from_bytes(b"de").unwrap();
+        assert!(val.url_list.0.is_empty());
+
+        // The element type still checks the value.
+        assert!(from_bytes::<MetaInfo>(b"d8:url-listi1ee").is_err());
+        assert!(from_bytes::<OneOrMany<u8>>(b"i300e").is_err());
+    }
+}
@@ -0,0 +1,13 @@
+//! Convenience re-exports of the most commonly used types and functions.
+//!
+//! ```
+//! use beeenn::prelude::*;
+//! ```
+
+#[cfg(any(feature = "serde", feature = "value"))]
+pub use crate::Error;
+pub use crate::Limits;
+#[cfg(feature = "serde")]
+pub use crate::{from_bytes, to_bytes};
+#[cfg(feature = "value")]
+pub use crate::{BEReader, BESliceReader, BEValue, ReadBencodeExt, WriteBencodeExt};
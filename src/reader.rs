@@ -0,0 +1,1203 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::convert::TryFrom;
+use std::io::{Bytes, Read};
+use std::iter::Peekable;
+use std::ops::Range;
+
+use crate::be_bytes::BEBytes;
+use crate::cow::BEValueCow;
+use crate::error::Error;
+use crate::keys::cmp_keys;
+use crate::limits::Limits;
+use crate::metrics;
+use crate::span::{Spanned, Spans};
+use crate::syntax::{
+    Digits, DICT_PREFIX, INTEGER_PREFIX, LIST_PREFIX, NEGATIVE_SIGN, STRING_SEPARATOR, SUFFIX,
+};
+use crate::value::BEValue;
+
+type Result<T> = std::result::Result<T, Error>;
+
+/// What to do when a dict repeats a key.
+///
+/// Keys must be in sorted order, so a repeated key always directly follows the first one. A key
+/// that comes back after a different key is reported as `KeysOutOfOrder` whatever the policy.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateKeys {
+    /// Fail with `Error::DuplicateKey`.
+    #[default]
+    Reject,
+    /// Keep the value of the first occurrence and drop the rest.
+    KeepFirst,
+    /// Keep the value of the last occurrence.
+    KeepLast,
+}
+
+/// A builder for the `Limits` and other rules applied while reading a single `BEValue`.
+///
+/// Starts from `Limits::default()` and rejects duplicate keys. Tighten the limits further when
+/// reading untrusted input.
+///
+/// ```
+/// use beeenn::reader::BEReaderOptions;
+/// use beeenn::Error;
+///
+/// let options = BEReaderOptions::new().max_depth(2);
+/// let mut reader = options.slice_reader(b"llleee");
+/// assert!(matches!(reader.next_value(), Err(Error::DepthLimitExceeded(2))));
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct BEReaderOptions {
+    limits: Limits,
+    duplicate_keys: DuplicateKeys,
+}
+
+impl BEReaderOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// See `Limits::max_depth`.
+    pub fn max_depth(mut self, max_depth: usize) -> Self {
+        self.limits.max_depth = max_depth;
+        self
+    }
+
+    /// See `Limits::max_string_len`.
+    pub fn max_string_len(mut self, max_string_len: usize) -> Self {
+        self.limits.max_string_len = max_string_len;
+        self
+    }
+
+    /// See `Limits::max_items`.
+    pub fn max_items(mut self, max_items: usize) -> Self {
+        self.limits.max_items = max_items;
+        self
+    }
+
+    /// See `Limits::max_total_bytes`.
+    pub fn max_total_bytes(mut self, max_total_bytes: usize) -> Self {
+        self.limits.max_total_bytes = max_total_bytes;
+        self
+    }
+
+    /// Sets what happens when a dict repeats a key.
+    pub fn duplicate_keys(mut self, duplicate_keys: DuplicateKeys) -> Self {
+        self.duplicate_keys = duplicate_keys;
+        self
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Creates a `BEReader` that applies these options.
+    pub fn reader<R: Read>(&self, read: R) -> BEReader<R> {
+        BEReader::with_options(read, *self)
+    }
+
+    /// Creates a `BESliceReader` that applies these options.
+    pub fn slice_reader<'a>(&self, bytes: &'a [u8]) -> BESliceReader<'a> {
+        BESliceReader::with_options(bytes, *self)
+    }
+
+    fn from_limits(limits: Limits) -> Self {
+        BEReaderOptions {
+            limits,
+            ..Self::default()
+        }
+    }
+}
+
+/// Checks that `bytes` holds exactly one well-formed bencode value within `limits`.
+///
+/// Applies the same rules as `BEReader`, including canonical key order.
+pub fn validate(bytes: &[u8], limits: Limits) -> Result<()> {
+    let mut reader = BESliceReader::with_limits(bytes, limits);
+    reader.next_value()?.ok_or(Error::Eof)?;
+    if !reader.remaining().is_empty() {
+        return Err(Error::TrailingInput);
+    }
+    Ok(())
+}
+
+/// Reads a stream of `BEValue`s from any `Read`.
+///
+/// The input is consumed one byte at a time, so unbuffered sources such as files and sockets
+/// should be wrapped in a `BufReader`. For input that is already in memory, `BESliceReader` is
+/// faster.
+///
+/// ```
+/// use beeenn::BEReader;
+/// use std::io::BufReader;
+///
+/// // Any `Read` works, such as a `File` or a `TcpStream`.
+/// let stream = BufReader::new(&b"d1:q4:ping1:y1:qed1:q4:ping1:y1:qe"[..]);
+/// let mut reader = BEReader::new(stream);
+/// let mut queries = 0;
+/// while let Some(message) = reader.next_value().unwrap() {
+///     assert_eq!("ping", message["q"].string());
+///     queries += 1;
+/// }
+/// assert_eq!(2, queries);
+/// ```
+pub struct BEReader<R: Read> {
+    parser: Parser<ReadSource<R>>,
+}
+
+impl<R: Read> BEReader<R> {
+    /// Creates a reader with the default `Limits`.
+    pub fn new(read: R) -> Self {
+        Self::with_limits(read, Limits::default())
+    }
+
+    pub fn with_limits(read: R, limits: Limits) -> Self {
+        Self::with_options(read, BEReaderOptions::from_limits(limits))
+    }
+
+    #[allow(clippy::unbuffered_bytes)]
+    fn with_options(read: R, options: BEReaderOptions) -> Self {
+        BEReader {
+            parser: Parser::new(
+                ReadSource {
+                    chars: read.bytes().peekable(),
+                    offset: 0,
+                },
+                options,
+            ),
+        }
+    }
+
+    /// Reads the next value from the stream.
+    ///
+    /// Returns `Ok(None)` if the stream is exhausted before a value starts.
+    pub fn next_value(&mut self) -> Result<Option<BEValue>> {
+        self.parser.next_value()
+    }
+}
+
+/// Reads a sequence of `BEValue`s from an in-memory buffer.
+///
+/// Produces exactly the same values and errors as `BEReader`, but strings are sliced out of the
+/// input and copied in one go instead of being read byte by byte.
+///
+/// An infohash is the SHA-1 of the `info` dict's bytes exactly as they appear in the file, so
+/// find them with `next_spanned` rather than by encoding the parsed value again:
+///
+/// ```
+/// use beeenn::BESliceReader;
+///
+/// let torrent = b"d8:announce5:udp:a4:infod6:lengthi7e4:name3:fooee";
+/// let spanned = BESliceReader::new(torrent).next_spanned().unwrap().unwrap();
+/// let info = &torrent[spanned.span_of("info").unwrap()];
+/// assert_eq!(&b"d6:lengthi7e4:name3:fooe"[..], info);
+/// // let infohash = Sha1::digest(info);
+/// ```
+pub struct BESliceReader<'a> {
+    parser: Parser<SliceSource<'a>>,
+}
+
+impl<'a> BESliceReader<'a> {
+    /// Creates a reader with the default `Limits`.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self::with_limits(bytes, Limits::default())
+    }
+
+    pub fn with_limits(bytes: &'a [u8], limits: Limits) -> Self {
+        Self::with_options(bytes, BEReaderOptions::from_limits(limits))
+    }
+
+    fn with_options(bytes: &'a [u8], options: BEReaderOptions) -> Self {
+        BESliceReader {
+            parser: Parser::new(SliceSource { bytes, pos: 0 }, options),
+        }
+    }
+
+    /// Reads the next value from the buffer.
+    ///
+    /// Returns `Ok(None)` if the buffer is exhausted before a value starts.
+    pub fn next_value(&mut self) -> Result<Option<BEValue>> {
+        self.parser.next_value()
+    }
+
+    /// Like `next_value`, but strings and keys borrow from the buffer instead of being copied.
+    pub fn next_value_cow(&mut self) -> Result<Option<BEValueCow<'a>>> {
+        self.parser.next_value()
+    }
+
+    /// Reads the next value, along with the spans of every node in it.
+    ///
+    /// Spans are offsets into the buffer the reader was created with. They are recorded as the
+    /// value is read, so they stay right when a `DuplicateKeys` policy drops entries.
+    pub fn next_spanned(&mut self) -> Result<Option<Spanned>> {
+        self.parser.next_value()
+    }
+
+    /// Returns the number of bytes consumed so far.
+    pub fn offset(&self) -> usize {
+        self.parser.source.pos
+    }
+
+    /// Returns the part of the buffer that has not been consumed yet.
+    pub fn remaining(&self) -> &'a [u8] {
+        let source = &self.parser.source;
+        &source.bytes[source.pos..]
+    }
+}
+
+impl<'a> From<&'a [u8]> for BESliceReader<'a> {
+    fn from(bytes: &'a [u8]) -> Self {
+        BESliceReader::new(bytes)
+    }
+}
+
+impl<'a> From<&'a str> for BESliceReader<'a> {
+    fn from(s: &'a str) -> Self {
+        BESliceReader::new(s.as_bytes())
+    }
+}
+
+// Where the parser gets its bytes from. Strings can borrow from the source for `'s`.
+trait Source<'s> {
+    fn peek_byte(&mut self) -> Result<Option<u8>>;
+    fn next_byte(&mut self) -> Result<u8>;
+    // The number of bytes consumed so far.
+    fn offset(&self) -> usize;
+    // Consumes exactly `len` bytes, or fails with `Eof`.
+    fn take(&mut self, len: usize) -> Result<Cow<'s, [u8]>>;
+}
+
+// What the parser builds its values as. Each value is given the bytes of input it was read from.
+trait Build<'s>: Sized {
+    type Key: AsRef<[u8]>;
+
+    fn integer(i: i64, span: Range<usize>) -> Self;
+    fn string(bytes: Cow<'s, [u8]>, span: Range<usize>) -> Self;
+    fn list(list: Vec<Self>, span: Range<usize>) -> Self;
+    fn dict(dict: Vec<(Self::Key, Self)>, span: Range<usize>) -> Self;
+    // Returns a string as a dict key, or any other value as a `BEValue` for the error.
+    fn into_key(self) -> std::result::Result<Self::Key, BEValue>;
+}
+
+impl<'s> Build<'s> for BEValue {
+    type Key = BEBytes;
+
+    fn integer(i: i64, _: Range<usize>) -> Self {
+        BEValue::BEInteger(i)
+    }
+
+    fn string(bytes: Cow<'s, [u8]>, _: Range<usize>) -> Self {
+        BEValue::BEString(bytes.into())
+    }
+
+    fn list(list: Vec<Self>, _: Range<usize>) -> Self {
+        BEValue::BEList(list)
+    }
+
+    fn dict(dict: Vec<(BEBytes, Self)>, _: Range<usize>) -> Self {
+        BEValue::BEDict(dict)
+    }
+
+    fn into_key(self) -> std::result::Result<BEBytes, BEValue> {
+        match self {
+            BEValue::BEString(key) => Ok(key),
+            other => Err(other),
+        }
+    }
+}
+
+impl<'s> Build<'s> for BEValueCow<'s> {
+    type Key = Cow<'s, [u8]>;
+
+    fn integer(i: i64, _: Range<usize>) -> Self {
+        BEValueCow::Integer(i)
+    }
+
+    fn string(bytes: Cow<'s, [u8]>, _: Range<usize>) -> Self {
+        BEValueCow::String(bytes)
+    }
+
+    fn list(list: Vec<Self>, _: Range<usize>) -> Self {
+        BEValueCow::List(list)
+    }
+
+    fn dict(dict: Vec<(Cow<'s, [u8]>, Self)>, _: Range<usize>) -> Self {
+        BEValueCow::Dict(dict)
+    }
+
+    fn into_key(self) -> std::result::Result<Cow<'s, [u8]>, BEValue> {
+        match self {
+            BEValueCow::String(key) => Ok(key),
+            other => Err(other.into_owned()),
+        }
+    }
+}
+
+// The most a string read from a `Read` allocates before any of its bytes arrive. The declared
+// length is only a claim, so a short input can't make us allocate much more than it sends.
+const MAX_PREALLOC: usize = 4096;
+
+// A dict key, and where it was read from.
+struct SpannedKey(BEBytes, Range<usize>);
+
+impl AsRef<[u8]> for SpannedKey {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl<'s> Build<'s> for Spanned {
+    type Key = SpannedKey;
+
+    fn integer(i: i64, span: Range<usize>) -> Self {
+        Spanned::leaf(BEValue::BEInteger(i), span)
+    }
+
+    fn string(bytes: Cow<'s, [u8]>, span: Range<usize>) -> Self {
+        Spanned::leaf(BEValue::BEString(bytes.into_owned().into()), span)
+    }
+
+    fn list(list: Vec<Self>, span: Range<usize>) -> Self {
+        let (values, children) = list.into_iter().map(|s| (s.value, s.spans)).unzip();
+        Spanned {
+            value: BEValue::BEList(values),
+            spans: Spans {
+                span,
+                key: None,
+                children,
+            },
+        }
+    }
+
+    fn dict(dict: Vec<(SpannedKey, Self)>, span: Range<usize>) -> Self {
+        let (entries, children) = dict
+            .into_iter()
+            .map(|(SpannedKey(key, key_span), s)| {
+                let spans = Spans {
+                    key: Some(key_span),
+                    ..s.spans
+                };
+                ((key, s.value), spans)
+            })
+            .unzip();
+        Spanned {
+            value: BEValue::BEDict(entries),
+            spans: Spans {
+                span,
+                key: None,
+                children,
+            },
+        }
+    }
+
+    fn into_key(self) -> std::result::Result<SpannedKey, BEValue> {
+        match self.value {
+            BEValue::BEString(key) => Ok(SpannedKey(key, self.spans.span)),
+            other => Err(other),
+        }
+    }
+}
+
+struct ReadSource<R: Read> {
+    chars: Peekable<Bytes<R>>,
+    offset: usize,
+}
+
+impl<R: Read> Source<'static> for ReadSource<R> {
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        match self.chars.peek() {
+            None => Ok(None),
+            Some(Ok(b)) => Ok(Some(*b)),
+            // Consume the error so that we can return it by value.
+            Some(Err(_)) => Err(self.chars.next().unwrap().unwrap_err().into()),
+        }
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        match self.chars.next() {
+            None => Err(Error::Eof),
+            Some(result) => {
+                let b = result?;
+                self.offset += 1;
+                Ok(b)
+            }
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.offset
+    }
+
+    fn take(&mut self, len: usize) -> Result<Cow<'static, [u8]>> {
+        let mut bytes = Vec::with_capacity(len.min(MAX_PREALLOC));
+        for _ in 0..len {
+            bytes.push(self.next_byte()?);
+        }
+        Ok(Cow::Owned(bytes))
+    }
+}
+
+struct SliceSource<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Source<'a> for SliceSource<'a> {
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        Ok(self.bytes.get(self.pos).copied())
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        let b = self.peek_byte()?.ok_or(Error::Eof)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn offset(&self) -> usize {
+        self.pos
+    }
+
+    fn take(&mut self, len: usize) -> Result<Cow<'a, [u8]>> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or(Error::Eof)?;
+        let bytes = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(Cow::Borrowed(bytes))
+    }
+}
+
+// The bencode grammar, shared by every reader.
+struct Parser<S> {
+    source: S,
+    limits: Limits,
+    duplicate_keys: DuplicateKeys,
+    // Containers currently open, values started, and the offset where the current top-level
+    // value started.
+    depth: usize,
+    items: usize,
+    start: usize,
+}
+
+impl<S> Parser<S> {
+    fn new(source: S, options: BEReaderOptions) -> Self {
+        Parser {
+            source,
+            limits: options.limits,
+            duplicate_keys: options.duplicate_keys,
+            depth: 0,
+            items: 0,
+            start: 0,
+        }
+    }
+}
+
+impl<'s, S: Source<'s>> Parser<S> {
+    fn next_value<V: Build<'s>>(&mut self) -> Result<Option<V>> {
+        if self.peek_byte()?.is_none() {
+            return Ok(None);
+        }
+        self.depth = 0;
+        self.items = 0;
+        self.start = self.source.offset();
+        let result = self.read_value();
+        metrics::report_result(&result, self.source.offset() - self.start, self.items);
+        result.map(Some)
+    }
+
+    fn peek_byte(&mut self) -> Result<Option<u8>> {
+        self.source.peek_byte()
+    }
+
+    fn next_byte(&mut self) -> Result<u8> {
+        self.check_total_bytes(1)?;
+        self.source.next_byte()
+    }
+
+    // Fails if consuming `len` more bytes would take the current value over `max_total_bytes`.
+    fn check_total_bytes(&self, len: usize) -> Result<()> {
+        let max = self.limits.max_total_bytes;
+        let used = self.source.offset() - self.start;
+        if len > max - used {
+            return Err(Error::InputTooLong(max));
+        }
+        Ok(())
+    }
+
+    fn expect_byte(&mut self, expected: u8) -> Result<()> {
+        let b = self.next_byte()?;
+        if b != expected {
+            return Err(Error::MissingSuffixError(b, expected));
+        }
+        Ok(())
+    }
+
+    fn read_value<V: Build<'s>>(&mut self) -> Result<V> {
+        self.items += 1;
+        if self.items > self.limits.max_items {
+            return Err(Error::TooManyItems(self.limits.max_items));
+        }
+
+        match self.peek_byte()?.ok_or(Error::Eof)? {
+            DICT_PREFIX => self.read_dict(),
+            INTEGER_PREFIX => self.read_integer(),
+            LIST_PREFIX => self.read_list(),
+            b'0'..=b'9' => self.read_string(),
+            ch => Err(Error::UnexpectedCharError(ch as char)),
+        }
+    }
+
+    // Reads an optionally-signed integer. Does not consume the prefix or suffix.
+    fn read_raw_integer(&mut self) -> Result<i64> {
+        let negative = if self.peek_byte()? == Some(NEGATIVE_SIGN) {
+            self.next_byte()?;
+            true
+        } else {
+            false
+        };
+
+        let mut digits = Digits::default();
+        while let Some(b) = self.peek_byte()? {
+            if !b.is_ascii_digit() {
+                break;
+            }
+            digits.push(b)?;
+            self.next_byte()?;
+        }
+
+        let magnitude = digits.finish()?;
+        if negative && magnitude == 0 {
+            return Err(Error::NegativeZero);
+        }
+        let val = if negative {
+            0i64.checked_sub_unsigned(magnitude)
+        } else {
+            i64::try_from(magnitude).ok()
+        };
+        val.ok_or(Error::IntegerOverflow)
+    }
+
+    fn read_integer<V: Build<'s>>(&mut self) -> Result<V> {
+        let start = self.source.offset();
+        self.expect_byte(INTEGER_PREFIX)?;
+        let val = self.read_raw_integer()?;
+        self.expect_byte(SUFFIX)?;
+        Ok(V::integer(val, start..self.source.offset()))
+    }
+
+    fn read_string<V: Build<'s>>(&mut self) -> Result<V> {
+        let start = self.source.offset();
+        let len = self.read_raw_integer()?;
+        if len < 0 {
+            return Err(Error::NegativeStringLength);
+        }
+
+        let sep = self.next_byte()?;
+        if sep != STRING_SEPARATOR {
+            return Err(Error::MissingColon(sep));
+        }
+
+        let len = usize::try_from(len).map_err(|_| Error::LengthOverflow(len as u64))?;
+        if len > self.limits.max_string_len {
+            return Err(Error::StringTooLong(len, self.limits.max_string_len));
+        }
+
+        self.check_total_bytes(len)?;
+        let bytes = self.source.take(len)?;
+        Ok(V::string(bytes, start..self.source.offset()))
+    }
+
+    // Called after reading the prefix of a list or dict. Must be paired with `leave_container`.
+    fn enter_container(&mut self) -> Result<()> {
+        self.depth += 1;
+        if self.depth > self.limits.max_depth {
+            return Err(Error::DepthLimitExceeded(self.limits.max_depth));
+        }
+        Ok(())
+    }
+
+    fn leave_container(&mut self) {
+        self.depth -= 1;
+    }
+
+    // Peeks inside the container that started at `start`, where running out of input means it was
+    // never terminated.
+    fn peek_in_container(&mut self, kind: &'static str, start: usize) -> Result<u8> {
+        self.peek_byte()?.ok_or(Error::UnterminatedContainer {
+            kind,
+            offset: start,
+        })
+    }
+
+    fn read_list<V: Build<'s>>(&mut self) -> Result<V> {
+        let start = self.source.offset();
+        self.expect_byte(LIST_PREFIX)?;
+        self.enter_container()?;
+
+        let mut list = Vec::new();
+        while self.peek_in_container("list", start)? != SUFFIX {
+            list.push(self.read_value()?);
+        }
+
+        self.expect_byte(SUFFIX)?;
+        self.leave_container();
+        Ok(V::list(list, start..self.source.offset()))
+    }
+
+    fn read_dict<V: Build<'s>>(&mut self) -> Result<V> {
+        let start = self.source.offset();
+        self.expect_byte(DICT_PREFIX)?;
+        self.enter_container()?;
+
+        let mut dict: Vec<(V::Key, V)> = Vec::new();
+        while self.peek_in_container("dict", start)? != SUFFIX {
+            let key = self
+                .read_value::<V>()?
+                .into_key()
+                .map_err(Error::KeyNotString)?;
+
+            if matches!(self.peek_byte()?, None | Some(SUFFIX)) {
+                return Err(Error::MissingValueForKey(
+                    String::from_utf8_lossy(key.as_ref()).into_owned(),
+                ));
+            }
+
+            // Keys must appear in sorted order, so the only key a new one can equal is the last.
+            let repeated = match dict
+                .last()
+                .map(|(last_key, _)| cmp_keys(key.as_ref(), last_key.as_ref()))
+            {
+                Some(Ordering::Less) => {
+                    return Err(Error::KeysOutOfOrder(
+                        String::from_utf8_lossy(key.as_ref()).into_owned(),
+                    ))
+                }
+                Some(Ordering::Equal) if self.duplicate_keys == DuplicateKeys::Reject => {
+                    return Err(Error::DuplicateKey(
+                        String::from_utf8_lossy(key.as_ref()).into_owned(),
+                    ))
+                }
+                Some(Ordering::Equal) => true,
+                _ => false,
+            };
+
+            // The value is read even when it will be dropped, so the input stays in sync.
+            let value = self.read_value()?;
+            match dict.last_mut() {
+                Some(last) if repeated && self.duplicate_keys == DuplicateKeys::KeepLast => {
+                    *last = (key, value)
+                }
+                _ if repeated => {}
+                _ => dict.push((key, value)),
+            }
+        }
+
+        self.expect_byte(SUFFIX)?;
+        self.leave_container();
+        Ok(V::dict(dict, start..self.source.offset()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    macro_rules! assert_error0 {
+        ($e:expr, $p:path) => {
+            match $e {
+                Err($p) => {}
+                _ => {
+                    assert!(false)
+                }
+            }
+        };
+    }
+
+    macro_rules! assert_error1 {
+        ($e:expr, $p:path, $v:expr) => {
+            match $e {
+                Err($p(v)) => {
+                    assert_eq!(v, $v);
+                }
+                _ => {
+                    assert!(false);
+                }
+            }
+        };
+    }
+
+    macro_rules! assert_error2 {
+        ($e:expr, $p:path, $v1:expr, $v2:expr) => {
+            match $e {
+                Err($p(v1, v2)) => {
+                    assert_eq!(v1, $v1);
+                    assert_eq!(v2, $v2);
+                }
+                _ => {
+                    assert!(false)
+                }
+            }
+        };
+    }
+
+    fn reader(s: &'static str) -> BEReader<impl Read> {
+        BEReader::new(s.as_bytes())
+    }
+
+    fn value_for_string(s: &str) -> BEValue {
+        BEReader::new(s.as_bytes()).next_value().unwrap().unwrap()
+    }
+
+    fn make_string() -> BEValue {
+        value_for_string("4:quux")
+    }
+
+    fn make_integer() -> BEValue {
+        value_for_string("i42e")
+    }
+
+    fn make_empty_list() -> BEValue {
+        value_for_string("le")
+    }
+
+    fn make_empty_dict() -> BEValue {
+        value_for_string("de")
+    }
+
+    #[test]
+    fn test_empty() {
+        let mut ber = BEReader::new("".as_bytes());
+        let value = ber.next_value().unwrap();
+
+        assert!(value.is_none());
+    }
+
+    #[test]
+    fn test_read_integer() {
+        let mut ber = reader("i45e");
+        let value = ber.next_value().unwrap();
+        assert_eq!(value.unwrap().integer(), 45);
+
+        let mut ber = reader("i-45e");
+        let value = ber.next_value().unwrap();
+        assert_eq!(value.unwrap().integer(), -45);
+
+        let mut ber = reader("i0e");
+        let value = ber.next_value().unwrap();
+        assert_eq!(value.unwrap().integer(), 0);
+    }
+
+    #[test]
+    fn test_integer_missing_e() {
+        // Missing suffix.
+        let mut ber = reader("i32");
+        let value = ber.next_value();
+        assert_error0!(value, Error::Eof);
+
+        // Missing suffix with more chars.
+        let mut ber = reader("i32i33e");
+        let value = ber.next_value();
+        assert_error2!(value, Error::MissingSuffixError, b'i', b'e');
+    }
+
+    #[test]
+    fn test_leading_zero() {
+        // Leading zero not allowed.
+        let mut ber = reader("i032e");
+        let value = ber.next_value();
+        assert_error0!(value, Error::UnexpectedZeroPrefix);
+    }
+
+    #[test]
+    fn test_negative_zero() {
+        let mut ber = reader("i-0e");
+        let value = ber.next_value();
+        assert_error0!(value, Error::NegativeZero);
+    }
+
+    #[test]
+    fn test_integer_overflow() {
+        let mut ber = reader("i9223372036854775808e");
+        let value = ber.next_value();
+        assert_error0!(value, Error::IntegerOverflow);
+
+        let mut ber = reader("i-9223372036854775809e");
+        assert_error0!(ber.next_value(), Error::IntegerOverflow);
+
+        // However many digits there are.
+        let long = format!("i{}e", "9".repeat(101));
+        assert_error0!(
+            BESliceReader::from(long.as_str()).next_value(),
+            Error::IntegerOverflow
+        );
+        let long = format!("{}:abc", "9".repeat(101));
+        assert_error0!(
+            BESliceReader::from(long.as_str()).next_value(),
+            Error::IntegerOverflow
+        );
+
+        let mut ber = reader("i-9223372036854775808ei9223372036854775807e");
+        assert_eq!(i64::MIN, ber.next_value().unwrap().unwrap().integer());
+        assert_eq!(i64::MAX, ber.next_value().unwrap().unwrap().integer());
+    }
+
+    #[cfg(target_pointer_width = "32")]
+    #[test]
+    fn test_length_overflow() {
+        let mut ber = BESliceReader::with_limits(b"4294967296:abc", Limits::unlimited());
+        assert_error1!(ber.next_value(), Error::LengthOverflow, 4294967296);
+    }
+
+    #[test]
+    fn test_read_string() {
+        // Empty string
+        let mut ber = BEReader::new("0:".as_bytes());
+        let value = ber.next_value().unwrap();
+        assert_eq!(value.unwrap().string(), "");
+
+        // One digit length
+        let mut ber = BEReader::new("7:unicorn".as_bytes());
+        let value = ber.next_value().unwrap();
+        assert_eq!(value.unwrap().string(), "unicorn");
+
+        // Two digit length
+        let mut ber = BEReader::new("12:unicornfarts".as_bytes());
+        let value = ber.next_value().unwrap();
+        assert_eq!(value.unwrap().string(), "unicornfarts");
+
+        // String longer than length
+        let mut ber = BEReader::new("11:unicornfarts".as_bytes());
+        let value = ber.next_value().unwrap();
+        assert_eq!(value.unwrap().string(), "unicornfart");
+
+        // String containing a number
+        let mut ber = reader("4:1234");
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(value.string(), "1234");
+    }
+
+    #[test]
+    fn test_missing_colon() {
+        let mut ber = reader("3foo");
+        let value = ber.next_value();
+        assert_error1!(value, Error::MissingColon, b'f');
+    }
+
+    #[test]
+    fn test_negative_string_length() {
+        let mut ber = reader("-10:impossible_");
+
+        // The beencode format makes this impossible, so we have to test it with the
+        // private helper function.
+        let value = ber.parser.read_string::<BEValue>();
+
+        assert_error0!(value, Error::NegativeStringLength);
+    }
+
+    #[test]
+    fn test_read_list() {
+        // empty list
+        let mut ber = reader("le");
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(value.len(), 0);
+
+        let mut ber = reader("l3:fooe");
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(value.len(), 1);
+
+        let mut ber = reader("li32ei45ee");
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(value.len(), 2);
+
+        let mut ber = reader("li-88e4:quuxi23ee");
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(value.len(), 3);
+        assert_eq!(value[0].integer(), -88);
+        assert_eq!(value[1].string(), "quux");
+        assert_eq!(value[2].integer(), 23);
+    }
+
+    #[test]
+    fn test_read_dict() {
+        // empty test_read_dict
+        let mut ber = reader("de");
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(value.len(), 0);
+
+        let mut ber = reader("d3:one4:worde");
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(value.len(), 1);
+
+        let mut ber = reader("d2:toi32e3:two5:wordse");
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(value.len(), 2);
+        assert_eq!(value["two"].string(), "words");
+        assert_eq!(value["to"].integer(), 32);
+    }
+
+    #[test]
+    fn test_keys_out_of_order() {
+        let mut ber = reader("d3:zzz5:words3:aaai7ee");
+        let value = ber.next_value();
+
+        assert_error1!(value, Error::KeysOutOfOrder, "aaa");
+    }
+
+    #[test]
+    fn test_duplicate_keys() {
+        let input = "d1:ai1e1:ai2e1:ai3e1:bi4ee";
+        let mut ber = reader(input);
+        assert_error1!(ber.next_value(), Error::DuplicateKey, "a");
+
+        let options = BEReaderOptions::new().duplicate_keys(DuplicateKeys::KeepFirst);
+        let value = options
+            .reader(input.as_bytes())
+            .next_value()
+            .unwrap()
+            .unwrap();
+        assert_eq!(2, value.len());
+        assert_eq!(1, value["a"].integer());
+        assert_eq!(4, value["b"].integer());
+
+        let options = options.duplicate_keys(DuplicateKeys::KeepLast);
+        let mut ber = options.slice_reader(input.as_bytes());
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(2, value.len());
+        assert_eq!(3, value["a"].integer());
+        assert_eq!(input.len(), ber.offset());
+
+        // A key that returns after another key is still out of order.
+        let mut ber = options.slice_reader(b"d1:ai1e1:bi2e1:ai3ee");
+        assert_error1!(ber.next_value(), Error::KeysOutOfOrder, "a");
+    }
+
+    #[test]
+    fn test_missing_value() {
+        let mut ber = reader("d3:two5:words7:missinge");
+        let value = ber.next_value();
+
+        assert_error1!(value, Error::MissingValueForKey, "missing");
+    }
+
+    #[test]
+    fn test_unterminated() {
+        let mut ber = reader("i1eld1:ai1e");
+        ber.next_value().unwrap();
+        assert!(matches!(
+            ber.next_value(),
+            Err(Error::UnterminatedContainer {
+                kind: "dict",
+                offset: 4
+            })
+        ));
+
+        let mut ber = BESliceReader::from("ll");
+        assert!(matches!(
+            ber.next_value(),
+            Err(Error::UnterminatedContainer {
+                kind: "list",
+                offset: 1
+            })
+        ));
+
+        // EOF inside an element, or right after a key, is not.
+        assert_error0!(reader("li1").next_value(), Error::Eof);
+        assert_error1!(reader("d1:a").next_value(), Error::MissingValueForKey, "a");
+    }
+
+    #[test]
+    fn test_non_string_key() {
+        let mut ber = reader("di666e5:words7:secondei42ee");
+        let value = ber.next_value();
+
+        assert_error1!(value, Error::KeyNotString, BEValue::BEInteger(666));
+    }
+
+    #[test]
+    fn test_list_of_lists() {
+        let mut ber = reader("ll5:mooreel3:bar4:quuxee");
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(2, value.len());
+
+        assert_eq!(value[0][0].string(), "moore");
+        assert_eq!(value[1][0].string(), "bar");
+        assert_eq!(value[1][1].string(), "quux");
+    }
+
+    #[test]
+    fn test_is_string() {
+        assert!(make_string().is_string());
+        assert!(!make_integer().is_string());
+        assert!(!make_empty_list().is_string());
+        assert!(!make_empty_dict().is_string());
+    }
+
+    #[test]
+    fn test_is_integer() {
+        assert!(!make_string().is_integer());
+        assert!(make_integer().is_integer());
+        assert!(!make_empty_list().is_integer());
+        assert!(!make_empty_dict().is_integer());
+    }
+
+    #[test]
+    fn test_is_list() {
+        assert!(!make_string().is_list());
+        assert!(!make_integer().is_list());
+        assert!(make_empty_list().is_list());
+        assert!(!make_empty_dict().is_list());
+    }
+
+    #[test]
+    fn test_is_dict() {
+        assert!(!make_string().is_dict());
+        assert!(!make_integer().is_dict());
+        assert!(!make_empty_list().is_dict());
+        assert!(make_empty_dict().is_dict());
+    }
+
+    #[test]
+    fn test_illegal_prefix() {
+        let mut ber = reader("y");
+        let result = ber.next_value();
+        assert_error1!(result, Error::UnexpectedCharError, 'y');
+    }
+
+    #[test]
+    fn test_slice_reader() {
+        let input = "d4:infod6:lengthi7e4:name3:fooe4:listli-1e0:ee";
+        let mut ber = BESliceReader::from(input);
+        let value = ber.next_value().unwrap().unwrap();
+        assert_eq!(value_for_string(input), value);
+        assert_eq!(value["info"]["name"].string(), "foo");
+        assert!(ber.next_value().unwrap().is_none());
+
+        let mut ber = BESliceReader::new(b"i1e3:two");
+        assert_eq!(ber.next_value().unwrap().unwrap().integer(), 1);
+        assert_eq!(b"3:two", ber.remaining());
+    }
+
+    #[test]
+    fn test_slice_reader_errors() {
+        // Both readers report the same errors.
+        let mut ber = BESliceReader::from("5:abc");
+        assert_error0!(ber.next_value(), Error::Eof);
+
+        let mut ber = BESliceReader::from("d3:zzz5:words3:aaai7ee");
+        assert_error1!(ber.next_value(), Error::KeysOutOfOrder, "aaa");
+
+        let mut ber = BESliceReader::from("i032e");
+        assert_error0!(ber.next_value(), Error::UnexpectedZeroPrefix);
+    }
+
+    #[test]
+    fn test_max_depth() {
+        let options = BEReaderOptions::new().max_depth(2);
+        let value = options.slice_reader(b"ld1:ai1eee").next_value().unwrap();
+        assert_eq!(1, value.unwrap()[0]["a"].integer());
+
+        let mut ber = options.reader("llleee".as_bytes());
+        assert_error1!(ber.next_value(), Error::DepthLimitExceeded, 2);
+
+        // Depth is tracked per container, not per value read.
+        let mut ber = options.slice_reader(b"llelelelee");
+        assert!(ber.next_value().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_max_string_len() {
+        let options = BEReaderOptions::new().max_string_len(4);
+        assert!(options.slice_reader(b"4:quux").next_value().is_ok());
+
+        let mut ber = options.slice_reader(b"5:quuxx");
+        assert_error2!(ber.next_value(), Error::StringTooLong, 5, 4);
+
+        // A length over the limit is rejected before the string is read.
+        let mut ber = options.reader("99999999999:".as_bytes());
+        assert_error2!(ber.next_value(), Error::StringTooLong, 99999999999, 4);
+
+        // Without a limit, a huge length runs out of input instead of allocating it up front.
+        let mut ber = BEReader::with_limits("999999999999999999:x".as_bytes(), Limits::unlimited());
+        assert_error0!(ber.next_value(), Error::Eof);
+
+        let mut ber = options.slice_reader(b"d7:toolongi1ee");
+        assert_error2!(ber.next_value(), Error::StringTooLong, 7, 4);
+    }
+
+    #[test]
+    fn test_max_items() {
+        let options = BEReaderOptions::new().max_items(4);
+        assert!(options.slice_reader(b"li1ei2ei3ee").next_value().is_ok());
+
+        let mut ber = options.slice_reader(b"li1ei2ei3ei4ee");
+        assert_error1!(ber.next_value(), Error::TooManyItems, 4);
+
+        // Keys count as items.
+        let mut ber = options.slice_reader(b"d1:ai1e1:bi2ee");
+        assert_error1!(ber.next_value(), Error::TooManyItems, 4);
+
+        // The count starts over for each top-level value.
+        let mut ber = options.reader("li1ei2eeli1ei2ee".as_bytes());
+        assert!(ber.next_value().is_ok());
+        assert!(ber.next_value().is_ok());
+    }
+
+    #[test]
+    fn test_max_total_bytes() {
+        let options = BEReaderOptions::new().max_total_bytes(8);
+        assert!(options.slice_reader(b"l3:fooe").next_value().is_ok());
+
+        let mut ber = options.reader("l3:fooi1ee".as_bytes());
+        assert_error1!(ber.next_value(), Error::InputTooLong, 8);
+
+        // Checked before a long string is read.
+        let mut ber = options.slice_reader(b"20:");
+        assert_error1!(ber.next_value(), Error::InputTooLong, 8);
+
+        // Each top-level value gets the full allowance.
+        let mut ber = options.reader("l3:fooel3:fooe".as_bytes());
+        assert!(ber.next_value().is_ok());
+        assert!(ber.next_value().is_ok());
+    }
+
+    #[test]
+    fn test_default_limits() {
+        let deep = "l".repeat(300) + &"e".repeat(300);
+        let mut ber = BEReader::new(deep.as_bytes());
+        assert_error1!(ber.next_value(), Error::DepthLimitExceeded, 256);
+
+        let mut ber = BEReader::with_limits(deep.as_bytes(), Limits::unlimited());
+        assert!(ber.next_value().unwrap().is_some());
+    }
+
+    #[test]
+    fn test_validate() {
+        let limits = Limits::default();
+        assert!(validate(b"d3:fooli1eee", limits).is_ok());
+        assert_error0!(validate(b"", limits), Error::Eof);
+        assert_error0!(validate(b"i1ei2e", limits), Error::TrailingInput);
+        assert_error1!(
+            validate(b"d1:bi1e1:ai2ee", limits),
+            Error::KeysOutOfOrder,
+            "a"
+        );
+
+        let limits = Limits {
+            max_depth: 1,
+            ..Limits::default()
+        };
+        assert_error1!(validate(b"llee", limits), Error::DepthLimitExceeded, 1);
+    }
+
+    #[test]
+    fn test_multiple_values() {
+        let mut ber = reader("i1e3:twoi3e");
+        assert_eq!(ber.next_value().unwrap().unwrap().integer(), 1);
+        assert_eq!(ber.next_value().unwrap().unwrap().string(), "two");
+        assert_eq!(ber.next_value().unwrap().unwrap().integer(), 3);
+        assert!(ber.next_value().unwrap().is_none());
+    }
+}
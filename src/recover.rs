@@ -0,0 +1,361 @@
+//! Best-effort parsing of damaged bencode.
+//!
+//! `BEReader` stops at the first error. `recover` keeps going: every value that can't be parsed
+//! is replaced with a `BEValue::BEInvalid` holding its raw bytes, and the error is recorded along
+//! with where it happened. This is meant for inspecting corrupted torrents, not for normal
+//! decoding.
+//!
+//! ```
+//! use beeenn::recover::recover;
+//! use beeenn::BEValue;
+//!
+//! let recovery = recover(b"l3:fooi03e3:bare");
+//! assert_eq!(1, recovery.errors.len());
+//! assert_eq!(6, recovery.errors[0].offset);
+//!
+//! let list = recovery.value.unwrap();
+//! assert_eq!("foo", list[0].string());
+//! assert_eq!(BEValue::BEInvalid(b"i03e".to_vec()), list[1]);
+//! assert_eq!("bar", list[2].string());
+//! ```
+
+use std::cmp::Ordering;
+
+use crate::be_bytes::BEBytes;
+use crate::error::Error;
+use crate::keys::cmp_keys;
+use crate::limits::Limits;
+use crate::reader::BESliceReader;
+use crate::value::BEValue;
+
+/// An error found by `recover`, with the offset of the value it applies to.
+#[derive(Debug)]
+pub struct RecoveredError {
+    pub offset: usize,
+    pub error: Error,
+}
+
+/// The result of `recover`.
+#[derive(Debug)]
+pub struct Recovery {
+    /// Whatever could be decoded, or `None` if the input was empty.
+    pub value: Option<BEValue>,
+    /// Every error found, in input order. Empty if the input was valid.
+    pub errors: Vec<RecoveredError>,
+}
+
+/// Parses as much of one bencode value as possible.
+///
+/// Errors are contained to the smallest value that holds them:
+/// - A malformed integer or string becomes `BEInvalid`, and parsing resumes after it.
+/// - A list or dict that ends early is closed at the end of the input.
+/// - A dict entry with a non-string key is dropped. A key with no value gets an empty
+///   `BEInvalid`. Keys out of order are reported but kept.
+/// - A container nested deeper than `Limits::default().max_depth` becomes `BEInvalid` holding the
+///   rest of the input.
+pub fn recover(bytes: &[u8]) -> Recovery {
+    let mut recoverer = Recoverer {
+        bytes,
+        pos: 0,
+        depth: 0,
+        max_depth: Limits::default().max_depth,
+        errors: Vec::new(),
+    };
+
+    if bytes.is_empty() {
+        return Recovery {
+            value: None,
+            errors: recoverer.errors,
+        };
+    }
+
+    let value = recoverer.value();
+    if recoverer.pos < bytes.len() {
+        recoverer.error(recoverer.pos, Error::TrailingInput);
+    }
+    Recovery {
+        value: Some(value),
+        errors: recoverer.errors,
+    }
+}
+
+struct Recoverer<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+    max_depth: usize,
+    errors: Vec<RecoveredError>,
+}
+
+impl Recoverer<'_> {
+    fn error(&mut self, offset: usize, error: Error) {
+        self.errors.push(RecoveredError { offset, error });
+    }
+
+    fn peek_byte(&self) -> Option<u8> {
+        self.bytes.get(self.pos).copied()
+    }
+
+    fn value(&mut self) -> BEValue {
+        match self.peek_byte() {
+            None => {
+                self.error(self.pos, Error::Eof);
+                BEValue::BEInvalid(Vec::new())
+            }
+            Some(b'l') | Some(b'd') if self.depth >= self.max_depth => {
+                let start = self.pos;
+                self.error(start, Error::DepthLimitExceeded(self.max_depth));
+                self.pos = self.bytes.len();
+                BEValue::BEInvalid(self.bytes[start..].to_vec())
+            }
+            Some(b'l') => self.list(),
+            Some(b'd') => self.dict(),
+            Some(_) => self.leaf(),
+        }
+    }
+
+    // Integers and strings are parsed by `BESliceReader`, so the rules match exactly.
+    fn leaf(&mut self) -> BEValue {
+        let start = self.pos;
+        let mut reader = BESliceReader::with_limits(&self.bytes[start..], Limits::unlimited());
+        match reader.next_value() {
+            Ok(Some(value)) => {
+                self.pos = self.bytes.len() - reader.remaining().len();
+                value
+            }
+            Ok(None) => unreachable!("leaf() called at end of input"),
+            Err(err) => {
+                self.error(start, err);
+                self.pos = self.skip_leaf(start);
+                BEValue::BEInvalid(self.bytes[start..self.pos].to_vec())
+            }
+        }
+    }
+
+    // Guesses where a malformed leaf starting at `start` ends. Integers run to the next 'e';
+    // strings run for their length if it can be read; anything else is skipped one byte at a
+    // time.
+    fn skip_leaf(&self, start: usize) -> usize {
+        let rest = &self.bytes[start..];
+        let end = match rest[0] {
+            b'i' => rest
+                .iter()
+                .position(|b| *b == b'e')
+                .map_or(rest.len(), |i| i + 1),
+            b'0'..=b'9' => {
+                let digits = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+                if rest.get(digits) == Some(&b':') {
+                    let len = rest[..digits].iter().fold(0usize, |len, b| {
+                        len.saturating_mul(10).saturating_add((b - b'0') as usize)
+                    });
+                    (digits + 1).saturating_add(len).min(rest.len())
+                } else {
+                    digits
+                }
+            }
+            _ => 1,
+        };
+        start + end
+    }
+
+    fn list(&mut self) -> BEValue {
+        let start = self.pos;
+        self.pos += 1;
+        self.depth += 1;
+
+        let mut list = Vec::new();
+        while let Some(b) = self.peek_byte() {
+            if b == b'e' {
+                self.pos += 1;
+                self.depth -= 1;
+                return BEValue::BEList(list);
+            }
+            list.push(self.value());
+        }
+
+        self.error(
+            self.pos,
+            Error::UnterminatedContainer {
+                kind: "list",
+                offset: start,
+            },
+        );
+        self.depth -= 1;
+        BEValue::BEList(list)
+    }
+
+    fn dict(&mut self) -> BEValue {
+        let start = self.pos;
+        self.pos += 1;
+        self.depth += 1;
+
+        let mut dict: Vec<(BEBytes, BEValue)> = Vec::new();
+        while let Some(b) = self.peek_byte() {
+            if b == b'e' {
+                self.pos += 1;
+                self.depth -= 1;
+                return BEValue::BEDict(dict);
+            }
+
+            let key_start = self.pos;
+            let key = match self.value() {
+                BEValue::BEString(key) => Some(key),
+                // The error was already recorded.
+                BEValue::BEInvalid(_) => None,
+                other => {
+                    self.error(key_start, Error::KeyNotString(other));
+                    None
+                }
+            };
+
+            if matches!(self.peek_byte(), None | Some(b'e')) {
+                if let Some(key) = key {
+                    let name = String::from_utf8_lossy(&key).into_owned();
+                    self.error(self.pos, Error::MissingValueForKey(name));
+                    dict.push((key, BEValue::BEInvalid(Vec::new())));
+                }
+                continue;
+            }
+
+            let value = self.value();
+            if let Some(key) = key {
+                if let Some((last_key, _)) = dict.last() {
+                    if cmp_keys(&key, last_key) != Ordering::Greater {
+                        let name = String::from_utf8_lossy(&key).into_owned();
+                        self.error(key_start, Error::KeysOutOfOrder(name));
+                    }
+                }
+                dict.push((key, value));
+            }
+        }
+
+        self.error(
+            self.pos,
+            Error::UnterminatedContainer {
+                kind: "dict",
+                offset: start,
+            },
+        );
+        self.depth -= 1;
+        BEValue::BEDict(dict)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn string(s: &str) -> BEValue {
+        BEValue::from(s)
+    }
+
+    #[test]
+    fn test_valid_input() {
+        let recovery = recover(b"d1:ali1ei2ee1:b3:fooe");
+        assert!(recovery.errors.is_empty());
+        let expected = BESliceReader::new(b"d1:ali1ei2ee1:b3:fooe")
+            .next_value()
+            .unwrap();
+        assert_eq!(expected, recovery.value);
+
+        let recovery = recover(b"");
+        assert!(recovery.value.is_none());
+        assert!(recovery.errors.is_empty());
+    }
+
+    #[test]
+    fn test_bad_leaves() {
+        let recovery = recover(b"li-0e5:hello?i1x4:abce");
+        assert_eq!(
+            BEValue::BEList(vec![
+                BEValue::BEInvalid(b"i-0e".to_vec()),
+                string("hello"),
+                BEValue::BEInvalid(b"?".to_vec()),
+                BEValue::BEInvalid(b"i1x4:abce".to_vec()),
+            ]),
+            recovery.value.unwrap()
+        );
+        let offsets: Vec<usize> = recovery.errors.iter().map(|e| e.offset).collect();
+        assert_eq!(vec![1, 12, 13, 22], offsets);
+        assert!(matches!(recovery.errors[0].error, Error::NegativeZero));
+        assert!(matches!(
+            recovery.errors[1].error,
+            Error::UnexpectedCharError('?')
+        ));
+        // The unterminated list.
+        assert!(matches!(
+            recovery.errors[3].error,
+            Error::UnterminatedContainer {
+                kind: "list",
+                offset: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_truncated() {
+        let recovery = recover(b"d4:infod6:lengthi7e4:name10:abc");
+        let value = recovery.value.unwrap();
+        assert_eq!(7, value["info"]["length"].integer());
+        assert_eq!(
+            BEValue::BEInvalid(b"10:abc".to_vec()),
+            value["info"]["name"]
+        );
+        // The string is cut short, then both dicts are unterminated.
+        assert_eq!(3, recovery.errors.len());
+        assert!(matches!(recovery.errors[0].error, Error::Eof));
+        assert!(matches!(
+            recovery.errors[1].error,
+            Error::UnterminatedContainer {
+                kind: "dict",
+                offset: 7
+            }
+        ));
+        assert!(matches!(
+            recovery.errors[2].error,
+            Error::UnterminatedContainer {
+                kind: "dict",
+                offset: 0
+            }
+        ));
+    }
+
+    #[test]
+    fn test_bad_dicts() {
+        let recovery = recover(b"d1:bi1e1:ai2ei3ei4e1:c1:ze");
+        assert_eq!(
+            BEValue::BEDict(vec![
+                (b"b".into(), BEValue::BEInteger(1)),
+                (b"a".into(), BEValue::BEInteger(2)),
+                (b"c".into(), string("z")),
+            ]),
+            recovery.value.unwrap()
+        );
+        assert_eq!(2, recovery.errors.len());
+        assert!(matches!(&recovery.errors[0].error, Error::KeysOutOfOrder(k) if k == "a"));
+        assert_eq!(7, recovery.errors[0].offset);
+        assert!(matches!(
+            recovery.errors[1].error,
+            Error::KeyNotString(BEValue::BEInteger(3))
+        ));
+
+        let recovery = recover(b"d1:ai1e1:be");
+        assert_eq!(BEValue::BEInvalid(Vec::new()), recovery.value.unwrap()["b"]);
+        assert!(matches!(&recovery.errors[0].error, Error::MissingValueForKey(k) if k == "b"));
+    }
+
+    #[test]
+    fn test_trailing_and_depth() {
+        let recovery = recover(b"i1ejunk");
+        assert_eq!(BEValue::BEInteger(1), recovery.value.unwrap());
+        assert_eq!(3, recovery.errors[0].offset);
+        assert!(matches!(recovery.errors[0].error, Error::TrailingInput));
+
+        let deep = "l".repeat(300) + &"e".repeat(300);
+        let recovery = recover(deep.as_bytes());
+        assert!(matches!(
+            recovery.errors[0].error,
+            Error::DepthLimitExceeded(256)
+        ));
+    }
+}
@@ -0,0 +1,191 @@
+//! Scrubbing identifying fields out of a `BEValue` before sharing it.
+//!
+//! Fields are named by their dotted path from the root, as for `BEValue::get_path`, so
+//! `"comment"` only matches the top-level key and `"info.source"` only the one inside `info`.
+//!
+//! ```
+//! use beeenn::redact::{redact, Redactor};
+//! use beeenn::BEReader;
+//!
+//! let input = &b"d8:announce3:url7:comment2:hi4:infod6:lengthi7eee"[..];
+//! let mut value = BEReader::new(input).next_value().unwrap().unwrap();
+//! redact(&mut value, &["announce", "comment"]);
+//! assert!(value.get("announce").is_none());
+//! assert_eq!(7, value["info"]["length"].integer());
+//!
+//! let mut value = BEReader::new(input).next_value().unwrap().unwrap();
+//! Redactor::new(&[]).info_only(true).redact(&mut value);
+//! assert_eq!(1, value.len());
+//! ```
+
+use crate::be_bytes::BEBytes;
+use crate::value::BEValue;
+use crate::walk::{VisitorMut, Walk};
+
+/// Fields in a torrent's metainfo that say who made it, when, or where it is tracked.
+pub const PRIVATE_FIELDS: &[&str] = &[
+    "announce",
+    "announce-list",
+    "comment",
+    "created by",
+    "creation date",
+    "info.source",
+    "url-list",
+];
+
+/// What happens to a redacted field.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Redaction {
+    /// Remove the key and its value.
+    #[default]
+    Remove,
+    /// Keep the key, but replace its value with an empty value of the same type (`0` for
+    /// integers).
+    Blank,
+}
+
+/// Removes `fields` from `value`. Fields that aren't present are ignored.
+pub fn redact(value: &mut BEValue, fields: &[&str]) {
+    Redactor::new(fields).redact(value);
+}
+
+/// Configures how `redact` scrubs a value.
+#[derive(Clone, Copy, Debug)]
+pub struct Redactor<'a> {
+    fields: &'a [&'a str],
+    redaction: Redaction,
+    info_only: bool,
+}
+
+impl<'a> Redactor<'a> {
+    pub fn new(fields: &'a [&'a str]) -> Self {
+        Redactor {
+            fields,
+            redaction: Redaction::Remove,
+            info_only: false,
+        }
+    }
+
+    /// Sets what happens to each redacted field.
+    pub fn redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// When set, every top-level key except `info` is redacted too.
+    pub fn info_only(mut self, info_only: bool) -> Self {
+        self.info_only = info_only;
+        self
+    }
+
+    /// Applies the redaction to `value` in place.
+    pub fn redact(&self, value: &mut BEValue) {
+        value.walk_mut(&mut Visit(self));
+    }
+
+    fn is_redacted(&self, path: &str, key: &[u8]) -> bool {
+        let key = String::from_utf8_lossy(key);
+        if path.is_empty() {
+            (self.info_only && key != "info") || self.fields.contains(&&*key)
+        } else {
+            let full = format!("{}.{}", path, key);
+            self.fields.contains(&full.as_str())
+        }
+    }
+}
+
+fn blank(value: &BEValue) -> BEValue {
+    match value {
+        BEValue::BEInteger(_) => BEValue::BEInteger(0),
+        BEValue::BEString(_) => BEValue::BEString(BEBytes::default()),
+        BEValue::BEList(_) => BEValue::BEList(Vec::new()),
+        BEValue::BEDict(_) => BEValue::BEDict(Vec::new()),
+        BEValue::BEInvalid(_) => BEValue::BEInvalid(Vec::new()),
+    }
+}
+
+struct Visit<'r, 'a>(&'r Redactor<'a>);
+
+impl VisitorMut for Visit<'_, '_> {
+    fn enter(&mut self, path: &str, value: &mut BEValue) -> Walk {
+        if let BEValue::BEDict(dict) = value {
+            match self.0.redaction {
+                Redaction::Remove => dict.retain(|(key, _)| !self.0.is_redacted(path, key)),
+                Redaction::Blank => {
+                    for (key, element) in dict.iter_mut() {
+                        if self.0.is_redacted(path, key) {
+                            *element = blank(element);
+                        }
+                    }
+                }
+            }
+        }
+        Walk::Continue
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reader::BESliceReader;
+
+    const TORRENT: &[u8] = b"d8:announce3:url7:comment2:hi10:created by2:me13:creation datei1e\
+        4:infod6:lengthi7e4:name3:foo6:source3:tnte\
+        8:url-listl3:webee";
+
+    fn torrent() -> BEValue {
+        BESliceReader::new(TORRENT).next_value().unwrap().unwrap()
+    }
+
+    fn keys(value: &BEValue) -> Vec<String> {
+        value.entries_str().map(|(k, _)| k.into_owned()).collect()
+    }
+
+    #[test]
+    fn test_private_fields() {
+        let mut value = torrent();
+        redact(&mut value, PRIVATE_FIELDS);
+        assert_eq!(vec!["info"], keys(&value));
+        assert_eq!(vec!["length", "name"], keys(&value["info"]));
+    }
+
+    #[test]
+    fn test_paths_are_exact() {
+        let mut value = torrent();
+        redact(&mut value, &["name", "info.length", "info.missing"]);
+        assert_eq!(vec!["name", "source"], keys(&value["info"]));
+        assert_eq!(6, keys(&value).len());
+    }
+
+    #[test]
+    fn test_blank() {
+        let mut value = torrent();
+        Redactor::new(PRIVATE_FIELDS)
+            .redaction(Redaction::Blank)
+            .redact(&mut value);
+        assert_eq!(6, keys(&value).len());
+        assert!(value["announce"].is_empty());
+        assert_eq!(0, value["creation date"].integer());
+        assert!(value["url-list"].is_list());
+        assert!(value["url-list"].is_empty());
+        assert!(value["info"]["source"].is_empty());
+        assert_eq!("foo", value["info"]["name"].string());
+    }
+
+    #[test]
+    fn test_info_only() {
+        let mut value = torrent();
+        Redactor::new(&["info.source"])
+            .info_only(true)
+            .redact(&mut value);
+        assert_eq!(vec!["info"], keys(&value));
+        assert_eq!(vec!["length", "name"], keys(&value["info"]));
+
+        // Values that aren't dicts are left alone.
+        let mut value = BEValue::BEInteger(1);
+        Redactor::new(PRIVATE_FIELDS)
+            .info_only(true)
+            .redact(&mut value);
+        assert_eq!(BEValue::BEInteger(1), value);
+    }
+}
@@ -0,0 +1,305 @@
+//! Editing encoded bencode without building a `BEValue`.
+//!
+//! Everything that isn't changed is copied byte for byte, so non-canonical input stays exactly
+//! as it was and hashes over untouched parts (like `info`) still match.
+//!
+//! ```
+//! use beeenn::rewrite::filter_copy;
+//!
+//! let input = b"d8:announce3:url4:infod6:lengthi7e6:source3:fooee";
+//! let mut output = Vec::new();
+//! filter_copy(input, &mut output, |path| path != "info.source").unwrap();
+//! assert_eq!(&b"d8:announce3:url4:infod6:lengthi7eee"[..], &output[..]);
+//! ```
+
+use std::io::Write;
+use std::ops::Range;
+
+use crate::error::Error;
+use crate::limits::Limits;
+use crate::scan::skip;
+use crate::walk::push_component;
+
+/// Copies the bencode value in `input` to `output`, leaving out every list element and dict
+/// entry whose dotted path (as for `BEValue::get_path`) `keep` rejects.
+///
+/// `keep` is not called for anything inside a subtree it has already rejected. Removed subtrees
+/// are still checked for valid syntax, so invalid input is never silently dropped. The input must
+/// hold exactly one value.
+pub fn filter_copy<W, F>(input: &[u8], output: &mut W, keep: F) -> Result<(), Error>
+where
+    W: Write,
+    F: FnMut(&str) -> bool,
+{
+    let mut filter = Filter {
+        input,
+        pos: 0,
+        depth: 0,
+        max_depth: Limits::default().max_depth,
+        output,
+        keep,
+    };
+    filter.copy_value(&mut String::new())?;
+    if filter.pos < input.len() {
+        return Err(Error::TrailingInput);
+    }
+    Ok(())
+}
+
+/// Returns a copy of `input` with the value at the dotted `path` (as for `BEValue::get_path`)
+/// replaced by `new_value`. Every other byte is left as it was.
+///
+/// `input` and `new_value` must each hold exactly one valid value. Returns
+/// `Error::MissingKey` if nothing exists at `path`. With duplicate keys, the first is replaced.
+///
+/// ```
+/// use beeenn::rewrite::replace_value;
+///
+/// let input = b"d8:announce3:url4:infod6:lengthi7eee";
+/// let output = replace_value(input, "announce", b"9:other-url").unwrap();
+/// assert_eq!(&b"d8:announce9:other-url4:infod6:lengthi7eee"[..], &output[..]);
+/// ```
+pub fn replace_value(input: &[u8], path: &str, new_value: &[u8]) -> Result<Vec<u8>, Error> {
+    for bytes in [input, new_value] {
+        if skip(bytes)? != bytes.len() {
+            return Err(Error::TrailingInput);
+        }
+    }
+    let span = locate(input, path)?;
+
+    let mut output = Vec::with_capacity(input.len() - span.len() + new_value.len());
+    output.extend_from_slice(&input[..span.start]);
+    output.extend_from_slice(new_value);
+    output.extend_from_slice(&input[span.end..]);
+    Ok(output)
+}
+
+// Finds the bytes of the value at `path` in `input`, which must already have been checked.
+fn locate(input: &[u8], path: &str) -> Result<Range<usize>, Error> {
+    let missing = || Error::MissingKey(path.to_string());
+    let mut start = 0;
+    for component in path.split('.') {
+        let mut pos = start + 1;
+        match input[start] {
+            b'l' => {
+                let index: usize = component.parse().map_err(|_| missing())?;
+                for _ in 0..index {
+                    if input[pos] == b'e' {
+                        return Err(missing());
+                    }
+                    pos += skip(&input[pos..])?;
+                }
+            }
+            b'd' => loop {
+                if input[pos] == b'e' {
+                    return Err(missing());
+                }
+                let key_end = pos + skip(&input[pos..])?;
+                let colon = pos + input[pos..].iter().position(|b| *b == b':').unwrap_or(0);
+                pos = key_end;
+                if &input[colon + 1..key_end] == component.as_bytes() {
+                    break;
+                }
+                pos += skip(&input[pos..])?;
+            },
+            _ => return Err(missing()),
+        }
+        if input[pos] == b'e' {
+            return Err(missing());
+        }
+        start = pos;
+    }
+    Ok(start..start + skip(&input[start..])?)
+}
+
+struct Filter<'a, W, F> {
+    input: &'a [u8],
+    pos: usize,
+    depth: usize,
+    max_depth: usize,
+    output: &'a mut W,
+    keep: F,
+}
+
+impl<W: Write, F: FnMut(&str) -> bool> Filter<'_, W, F> {
+    fn peek_byte(&self) -> Result<u8, Error> {
+        self.input.get(self.pos).copied().ok_or(Error::Eof)
+    }
+
+    // Moves past the leaf, list or dict at `pos`, returning its range.
+    fn skip_value(&mut self) -> Result<Range<usize>, Error> {
+        let start = self.pos;
+        self.pos += skip(&self.input[start..])?;
+        Ok(start..self.pos)
+    }
+
+    fn copy_value(&mut self, path: &mut String) -> Result<(), Error> {
+        let container = self.peek_byte()?;
+        if container != b'l' && container != b'd' {
+            let range = self.skip_value()?;
+            self.output.write_all(&self.input[range])?;
+            return Ok(());
+        }
+
+        if self.depth >= self.max_depth {
+            return Err(Error::DepthLimitExceeded(self.max_depth));
+        }
+        self.depth += 1;
+        self.output.write_all(&[container])?;
+        self.pos += 1;
+
+        let mut index = 0;
+        while self.peek_byte()? != b'e' {
+            let (key, component) = if container == b'l' {
+                index += 1;
+                (0..0, (index - 1).to_string())
+            } else {
+                self.key()?
+            };
+            let len = push_component(path, &component);
+            if (self.keep)(path) {
+                self.output.write_all(&self.input[key])?;
+                self.copy_value(path)?;
+            } else {
+                self.skip_value()?;
+            }
+            path.truncate(len);
+        }
+
+        self.output.write_all(b"e")?;
+        self.pos += 1;
+        self.depth -= 1;
+        Ok(())
+    }
+
+    // Reads a dict key, leaving `pos` at its value. Returns the encoded key's range and the key.
+    fn key(&mut self) -> Result<(Range<usize>, String), Error> {
+        let b = self.peek_byte()?;
+        if !b.is_ascii_digit() {
+            return Err(Error::UnexpectedCharError(b as char));
+        }
+        let range = self.skip_value()?;
+        let encoded = &self.input[range.clone()];
+        let colon = encoded.iter().position(|b| *b == b':').unwrap_or(0);
+        let key = String::from_utf8_lossy(&encoded[colon + 1..]).into_owned();
+        if matches!(self.input.get(self.pos), None | Some(b'e')) {
+            return Err(Error::MissingValueForKey(key));
+        }
+        Ok((range, key))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn filter(input: &[u8], keep: impl FnMut(&str) -> bool) -> Result<Vec<u8>, Error> {
+        let mut output = Vec::new();
+        filter_copy(input, &mut output, keep).map(|_| output)
+    }
+
+    #[test]
+    fn test_filter_copy() {
+        let input = b"d1:ali1ei2ei3ee1:bd1:ci4e1:d0:ee";
+        assert_eq!(&input[..], &filter(input, |_| true).unwrap()[..]);
+        assert_eq!(
+            &b"d1:ali1ei3ee1:bd1:d0:ee"[..],
+            &filter(input, |path| path != "a.1" && path != "b.c").unwrap()[..]
+        );
+        assert_eq!(&b"de"[..], &filter(input, |_| false).unwrap()[..]);
+        assert_eq!(&b"i5e"[..], &filter(b"i5e", |_| false).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_pruned_subtrees() {
+        let mut seen = Vec::new();
+        let output = filter(b"d1:bl1:xe1:cd1:di1eee", |path| {
+            seen.push(path.to_string());
+            path != "b"
+        })
+        .unwrap();
+        assert_eq!(&b"d1:cd1:di1eee"[..], &output[..]);
+        assert_eq!(vec!["b", "c", "c.d"], seen);
+    }
+
+    #[test]
+    fn test_byte_for_byte() {
+        // Unsorted keys and non-UTF-8 bytes are copied as they are.
+        let input = b"d1:bi1e1:a1:\xffe";
+        assert_eq!(&input[..], &filter(input, |_| true).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_replace_value() {
+        let input = b"d1:ali1ei2ee1:bd1:ci3ee0:i4ee";
+        assert_eq!(
+            &b"d1:ali1e3:twoe1:bd1:ci3ee0:i4ee"[..],
+            &replace_value(input, "a.1", b"3:two").unwrap()[..]
+        );
+        assert_eq!(
+            &b"d1:ali1ei2ee1:bd1:clee0:i4ee"[..],
+            &replace_value(input, "b.c", b"le").unwrap()[..]
+        );
+        assert_eq!(
+            &b"d1:ali1ei2ee1:bd1:ci3ee0:dee"[..],
+            &replace_value(input, "", b"de").unwrap()[..]
+        );
+
+        // Only the target changes, even in non-canonical input.
+        let input = b"d1:bi1e1:ai2e1:ai3ee";
+        assert_eq!(
+            &b"d1:bi1e1:ai9e1:ai3ee"[..],
+            &replace_value(input, "a", b"i9e").unwrap()[..]
+        );
+    }
+
+    #[test]
+    fn test_replace_value_errors() {
+        let input = b"d1:ali1ee1:bi2ee";
+        for path in ["c", "a.1", "a.x", "b.c", "a.0.0"] {
+            assert!(
+                matches!(replace_value(input, path, b"i0e"), Err(Error::MissingKey(p)) if p == path),
+                "{}",
+                path
+            );
+        }
+        assert!(matches!(
+            replace_value(input, "b", b"i0ei1e"),
+            Err(Error::TrailingInput)
+        ));
+        assert!(matches!(replace_value(input, "b", b"i0"), Err(Error::Eof)));
+        assert!(matches!(
+            replace_value(b"d1:ai1e", "a", b"i0e"),
+            Err(Error::Eof)
+        ));
+    }
+
+    #[test]
+    fn test_errors() {
+        assert!(matches!(filter(b"", |_| true), Err(Error::Eof)));
+        assert!(matches!(filter(b"l", |_| true), Err(Error::Eof)));
+        assert!(matches!(
+            filter(b"i1ei2e", |_| true),
+            Err(Error::TrailingInput)
+        ));
+        assert!(matches!(
+            filter(b"di1ei2ee", |_| true),
+            Err(Error::UnexpectedCharError('i'))
+        ));
+        assert!(matches!(
+            filter(b"d1:ae", |_| true),
+            Err(Error::MissingValueForKey(_))
+        ));
+        // Removed values are still checked.
+        assert!(matches!(
+            filter(b"li01ee", |_| false),
+            Err(Error::UnexpectedZeroPrefix)
+        ));
+
+        let deep = "l".repeat(300) + &"e".repeat(300);
+        assert!(matches!(
+            filter(deep.as_bytes(), |_| true),
+            Err(Error::DepthLimitExceeded(256))
+        ));
+    }
+}
@@ -0,0 +1,332 @@
+//! Scanning bencode without building a `BEValue`.
+//!
+//! The scanner checks the same syntax as `BEReader` (except dict key order) but keeps its own
+//! stack, so it never recurses and allocates only one small frame per open container.
+
+use std::convert::TryFrom;
+use std::ops::Range;
+
+use crate::error::Error;
+use crate::syntax::Digits;
+
+/// How much more input a buffer needs before its first value is complete.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Needed {
+    /// The first value is complete and occupies this many bytes.
+    Complete(usize),
+    /// At least this many more bytes are needed. More may turn out to be needed once they
+    /// arrive, for example if a string length or integer has more digits.
+    AtLeast(usize),
+    /// The buffer ends inside the bytes of a string whose length prefix starts at `offset`.
+    /// `minimum` more bytes are needed: the rest of the string plus enough to close every open
+    /// list and dict.
+    InString {
+        offset: usize,
+        declared_len: usize,
+        minimum: usize,
+    },
+    /// The bytes at this offset can never start or continue a valid value.
+    Invalid(usize),
+}
+
+/// Explains what a possibly truncated buffer is still missing.
+///
+/// Useful for framing code that must decide whether to wait for more data.
+///
+/// ```
+/// use beeenn::scan::{diagnose_incomplete, Needed};
+///
+/// assert_eq!(Needed::Complete(3), diagnose_incomplete(b"i1etrailing"));
+/// assert_eq!(Needed::AtLeast(2), diagnose_incomplete(b"li4"));
+/// assert_eq!(
+///     Needed::InString { offset: 1, declared_len: 5, minimum: 4 },
+///     diagnose_incomplete(b"l5:ab")
+/// );
+/// assert_eq!(Needed::Invalid(1), diagnose_incomplete(b"lx"));
+/// ```
+pub fn diagnose_incomplete(bytes: &[u8]) -> Needed {
+    scan(bytes).unwrap_or_else(|(offset, _)| Needed::Invalid(offset))
+}
+
+/// Returns how many bytes the first value in `bytes` occupies, or `None` if the buffer ends
+/// before the value does.
+///
+/// Nothing is built, so this is cheap enough to call each time more data arrives when splitting
+/// messages out of a stream.
+///
+/// ```
+/// use beeenn::scan::value_len;
+///
+/// let mut stream = &b"d1:ai1eeli2ee4:sp"[..];
+/// let mut messages = Vec::new();
+/// while let Some(len) = value_len(stream).unwrap() {
+///     messages.push(&stream[..len]);
+///     stream = &stream[len..];
+/// }
+/// assert_eq!(vec![&b"d1:ai1ee"[..], b"li2ee"], messages);
+/// assert_eq!(b"4:sp", stream);
+/// ```
+pub fn value_len(bytes: &[u8]) -> Result<Option<usize>, Error> {
+    match scan(bytes) {
+        Ok(Needed::Complete(len)) => Ok(Some(len)),
+        Ok(_) => Ok(None),
+        Err((_, err)) => Err(err),
+    }
+}
+
+/// Returns how many bytes the first value in `bytes` occupies, treating a truncated value as an
+/// error.
+///
+/// ```
+/// use beeenn::scan::skip;
+///
+/// let input = b"d4:infod6:lengthi7eee4:next";
+/// let len = skip(input).unwrap();
+/// assert_eq!(b"4:next", &input[len..]);
+/// assert!(skip(b"li1e").is_err());
+/// ```
+pub fn skip(bytes: &[u8]) -> Result<usize, Error> {
+    value_len(bytes)?.ok_or(Error::Eof)
+}
+
+enum Frame {
+    List,
+    // `key` is the key waiting for its value, if any.
+    Dict { key: Option<Range<usize>> },
+}
+
+// The fewest bytes that could finish the value: one 'e' per open container, plus the shortest
+// possible value ("0:", "le" or "de") if a dict key is still waiting for one.
+fn closing_len(stack: &[Frame], needs_value: bool) -> usize {
+    stack.len() + if needs_value { 2 } else { 0 }
+}
+
+// Reads the digits of a non-negative number starting at `start`, rejecting leading zeros and
+// values above `max`. Returns the value and the offset just past the digits, which may be the
+// end of the buffer.
+fn read_digits(bytes: &[u8], start: usize, max: u64) -> Result<(u64, usize), Error> {
+    let mut digits = Digits::default();
+    let mut pos = start;
+    while let Some(b) = bytes.get(pos).filter(|b| b.is_ascii_digit()) {
+        digits.push(*b)?;
+        if digits.value() > max {
+            return Err(Error::IntegerOverflow);
+        }
+        pos += 1;
+    }
+    Ok((digits.value(), pos))
+}
+
+// Scans the first value in `bytes`. Errors carry the offset of the token that was bad.
+fn scan(bytes: &[u8]) -> Result<Needed, (usize, Error)> {
+    let mut stack: Vec<Frame> = Vec::new();
+    let mut pos = 0;
+
+    loop {
+        let in_key = matches!(stack.last(), Some(Frame::Dict { key: None }));
+
+        let b = match bytes.get(pos) {
+            Some(b) => *b,
+            None if stack.is_empty() => return Ok(Needed::AtLeast(2)),
+            None => {
+                let needs_value = matches!(stack.last(), Some(Frame::Dict { key: Some(_) }));
+                return Ok(Needed::AtLeast(closing_len(&stack, needs_value)));
+            }
+        };
+
+        // The end of a container.
+        if b == b'e' && !stack.is_empty() {
+            if let Some(Frame::Dict { key: Some(key) }) = stack.last() {
+                let name = String::from_utf8_lossy(&bytes[key.clone()]).into_owned();
+                return Err((pos, Error::MissingValueForKey(name)));
+            }
+            stack.pop();
+            pos += 1;
+        } else if in_key && !b.is_ascii_digit() {
+            return Err((pos, Error::UnexpectedCharError(b as char)));
+        } else {
+            match b {
+                b'l' => {
+                    stack.push(Frame::List);
+                    pos += 1;
+                    continue;
+                }
+                b'd' => {
+                    stack.push(Frame::Dict { key: None });
+                    pos += 1;
+                    continue;
+                }
+                b'i' => {
+                    let start = pos;
+                    let digits = if bytes.get(pos + 1) == Some(&b'-') {
+                        pos + 2
+                    } else {
+                        pos + 1
+                    };
+                    // i64::MIN has one more unit of magnitude than i64::MAX.
+                    let max = i64::MAX as u64 + (digits - pos - 1) as u64;
+                    let (val, end) = read_digits(bytes, digits, max).map_err(|err| (start, err))?;
+                    if end > digits && val == 0 && digits == pos + 2 {
+                        return Err((start, Error::NegativeZero));
+                    }
+                    let suffix = match bytes.get(end) {
+                        Some(suffix) => *suffix,
+                        None => {
+                            let token = if end == digits { 2 } else { 1 };
+                            return Ok(Needed::AtLeast(token + closing_len(&stack, false)));
+                        }
+                    };
+                    if end == digits {
+                        return Err((start, Error::NoDigitsInNumber));
+                    }
+                    if suffix != b'e' {
+                        return Err((start, Error::MissingSuffixError(suffix, b'e')));
+                    }
+                    pos = end + 1;
+                }
+                b'0'..=b'9' => {
+                    let start = pos;
+                    let (len, end) =
+                        read_digits(bytes, pos, i64::MAX as u64).map_err(|err| (start, err))?;
+                    let len = usize::try_from(len).map_err(|_| (start, Error::IntegerOverflow))?;
+                    let sep = match bytes.get(end) {
+                        Some(sep) => *sep,
+                        None => {
+                            let rest = len.saturating_add(1);
+                            return Ok(Needed::AtLeast(
+                                rest.saturating_add(closing_len(&stack, in_key)),
+                            ));
+                        }
+                    };
+                    if sep != b':' {
+                        return Err((start, Error::MissingColon(sep)));
+                    }
+                    let body_end = (end + 1).saturating_add(len);
+                    if body_end > bytes.len() {
+                        let rest = body_end - bytes.len();
+                        return Ok(Needed::InString {
+                            offset: start,
+                            declared_len: len,
+                            minimum: rest.saturating_add(closing_len(&stack, in_key)),
+                        });
+                    }
+                    if in_key {
+                        stack.pop();
+                        stack.push(Frame::Dict {
+                            key: Some(end + 1..body_end),
+                        });
+                        pos = body_end;
+                        continue;
+                    }
+                    pos = body_end;
+                }
+                _ => return Err((pos, Error::UnexpectedCharError(b as char))),
+            }
+        }
+
+        // A value just finished.
+        match stack.last_mut() {
+            None => return Ok(Needed::Complete(pos)),
+            Some(Frame::Dict { key }) => *key = None,
+            Some(Frame::List) => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_complete() {
+        for input in [&b"i0e"[..], b"4:spam", b"le", b"de", b"d1:ali1e0:ee"] {
+            assert_eq!(Needed::Complete(input.len()), diagnose_incomplete(input));
+        }
+        assert_eq!(Needed::Complete(5), diagnose_incomplete(b"l1:aeli1ee"));
+    }
+
+    #[test]
+    fn test_incomplete() {
+        assert_eq!(Needed::AtLeast(2), diagnose_incomplete(b""));
+        assert_eq!(Needed::AtLeast(2), diagnose_incomplete(b"i"));
+        assert_eq!(Needed::AtLeast(2), diagnose_incomplete(b"i-"));
+        assert_eq!(Needed::AtLeast(1), diagnose_incomplete(b"i12"));
+        assert_eq!(Needed::AtLeast(12), diagnose_incomplete(b"11"));
+        assert_eq!(Needed::AtLeast(2), diagnose_incomplete(b"ll"));
+        // The key needs a value, and the dict needs an 'e'.
+        assert_eq!(Needed::AtLeast(3), diagnose_incomplete(b"d3:foo"));
+        assert_eq!(Needed::AtLeast(5), diagnose_incomplete(b"d1"));
+        assert_eq!(
+            Needed::InString {
+                offset: 1,
+                declared_len: 10,
+                minimum: 10,
+            },
+            diagnose_incomplete(b"d10:abc")
+        );
+        assert_eq!(
+            Needed::InString {
+                offset: 7,
+                declared_len: 4,
+                minimum: 4,
+            },
+            diagnose_incomplete(b"d3:fool4:sp")
+        );
+    }
+
+    #[test]
+    fn test_value_len() {
+        assert_eq!(Some(4), value_len(b"i42ei43e").unwrap());
+        assert_eq!(Some(12), value_len(b"d3:fooli1eee").unwrap());
+        assert_eq!(None, value_len(b"d3:fooli1ee").unwrap());
+        assert_eq!(None, value_len(b"").unwrap());
+        assert!(matches!(
+            value_len(b"i01e"),
+            Err(Error::UnexpectedZeroPrefix)
+        ));
+        assert!(matches!(
+            value_len(b"l?"),
+            Err(Error::UnexpectedCharError('?'))
+        ));
+
+        // Agrees with the reader about where the value ends.
+        let input = b"d8:announce3:url4:infod6:lengthi7e4:name3:fooee";
+        let mut reader = crate::BESliceReader::new(input);
+        reader.next_value().unwrap();
+        assert_eq!(Some(reader.offset()), value_len(input).unwrap());
+    }
+
+    #[test]
+    fn test_skip() {
+        assert_eq!(3, skip(b"i1e").unwrap());
+        assert_eq!(4, skip(b"lleeee").unwrap());
+        assert!(matches!(skip(b""), Err(Error::Eof)));
+        assert!(matches!(skip(b"d1:a"), Err(Error::Eof)));
+        assert!(matches!(skip(b"i-0e"), Err(Error::NegativeZero)));
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert_eq!(Needed::Invalid(0), diagnose_incomplete(b"x"));
+        assert_eq!(Needed::Invalid(1), diagnose_incomplete(b"li03e"));
+        assert_eq!(Needed::Invalid(0), diagnose_incomplete(b"i-0"));
+        assert_eq!(Needed::Invalid(0), diagnose_incomplete(b"i-e"));
+        assert_eq!(Needed::Invalid(0), diagnose_incomplete(b"05:hello"));
+        assert_eq!(Needed::Invalid(0), diagnose_incomplete(b"3x"));
+        // Keys must be strings, and every key needs a value.
+        assert_eq!(Needed::Invalid(1), diagnose_incomplete(b"di1ei2ee"));
+        assert_eq!(Needed::Invalid(4), diagnose_incomplete(b"d1:ae"));
+        assert_eq!(
+            Needed::Invalid(0),
+            diagnose_incomplete(b"i9223372036854775808e")
+        );
+        assert_eq!(
+            Needed::Invalid(0),
+            diagnose_incomplete(b"i-9223372036854775809e")
+        );
+        assert_eq!(
+            Needed::Complete(22),
+            diagnose_incomplete(b"i-9223372036854775808e")
+        );
+    }
+}
@@ -0,0 +1,344 @@
+//! Checking the shape of a `BEValue` without defining serde types.
+//!
+//! A `Schema` describes the keys, types and integer ranges a message should have. `validate`
+//! reports everything that doesn't match, each with its dotted path (as for `BEValue::get_path`),
+//! so a service can check KRPC queries or tracker responses of many shapes with one
+//! declaration each.
+//!
+//! ```
+//! use beeenn::schema::{Schema, Violation};
+//! use beeenn::BESliceReader;
+//!
+//! let torrent = Schema::dict()
+//!     .key("announce", Schema::text())
+//!     .key(
+//!         "info",
+//!         Schema::dict()
+//!             .key("name", Schema::string())
+//!             .key("piece length", Schema::integer().min(1))
+//!             .optional("private", Schema::integer().min(0).max(1)),
+//!     );
+//!
+//! let input = b"d8:announce3:url4:infod4:name3:foo12:piece lengthi0e7:privatei2eee";
+//! let value = BESliceReader::new(input).next_value().unwrap().unwrap();
+//! assert_eq!(
+//!     vec![
+//!         Violation::OutOfRange("info.piece length".to_string(), 0),
+//!         Violation::OutOfRange("info.private".to_string(), 2),
+//!     ],
+//!     torrent.validate(&value)
+//! );
+//! ```
+
+use crate::value::BEValue;
+use crate::walk::push_component;
+
+/// A way in which a value doesn't match its `Schema`.
+///
+/// Each violation carries the dotted path of the value it is about.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Violation {
+    /// A required key is missing from a dict.
+    MissingKey(String),
+    /// A key the dict doesn't allow, under `deny_unknown_keys`.
+    UnknownKey(String),
+    /// The value isn't of the expected type: "integer", "string", "list" or "dict".
+    WrongType(String, &'static str),
+    /// An integer outside the allowed range. Holds the integer.
+    OutOfRange(String, i64),
+    /// A string of the wrong length. Holds its length in bytes.
+    WrongLength(String, usize),
+    /// A string that must be text isn't valid UTF-8.
+    NotUtf8(String),
+}
+
+impl Violation {
+    /// The dotted path of the value the violation is about.
+    pub fn path(&self) -> &str {
+        match self {
+            Violation::MissingKey(path)
+            | Violation::UnknownKey(path)
+            | Violation::WrongType(path, _)
+            | Violation::OutOfRange(path, _)
+            | Violation::WrongLength(path, _)
+            | Violation::NotUtf8(path) => path,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Kind {
+    Any,
+    Integer {
+        min: i64,
+        max: i64,
+    },
+    String {
+        text: bool,
+        len: Option<usize>,
+    },
+    List(Box<Schema>),
+    Dict {
+        // Each key's schema, and whether it's required.
+        keys: Vec<(Vec<u8>, Schema, bool)>,
+        deny_unknown_keys: bool,
+    },
+}
+
+/// The expected shape of a `BEValue`. See the module documentation.
+///
+/// The refining methods (`min`, `len`, `key` and so on) panic if called on a schema of another
+/// type, since that's a mistake in the schema rather than in the value.
+#[derive(Clone, Debug)]
+pub struct Schema(Kind);
+
+impl Schema {
+    /// Matches any value.
+    pub fn any() -> Self {
+        Schema(Kind::Any)
+    }
+
+    /// Matches any integer. Narrow it with `min` and `max`.
+    pub fn integer() -> Self {
+        Schema(Kind::Integer {
+            min: i64::MIN,
+            max: i64::MAX,
+        })
+    }
+
+    /// Matches any string, text or not.
+    pub fn string() -> Self {
+        Schema(Kind::String {
+            text: false,
+            len: None,
+        })
+    }
+
+    /// Matches a string that is valid UTF-8.
+    pub fn text() -> Self {
+        Schema(Kind::String {
+            text: true,
+            len: None,
+        })
+    }
+
+    /// Matches a list whose elements all match `element`.
+    pub fn list(element: Schema) -> Self {
+        Schema(Kind::List(Box::new(element)))
+    }
+
+    /// Matches a dict. Add its keys with `key` and `optional`. Other keys are allowed unless
+    /// `deny_unknown_keys` is set.
+    pub fn dict() -> Self {
+        Schema(Kind::Dict {
+            keys: Vec::new(),
+            deny_unknown_keys: false,
+        })
+    }
+
+    /// The smallest integer allowed.
+    pub fn min(mut self, value: i64) -> Self {
+        match &mut self.0 {
+            Kind::Integer { min, .. } => *min = value,
+            other => panic!("min() called on a non-integer schema: {:?}", other),
+        }
+        self
+    }
+
+    /// The largest integer allowed.
+    pub fn max(mut self, value: i64) -> Self {
+        match &mut self.0 {
+            Kind::Integer { max, .. } => *max = value,
+            other => panic!("max() called on a non-integer schema: {:?}", other),
+        }
+        self
+    }
+
+    /// The exact length of a string in bytes, such as 20 for a node ID or info hash.
+    pub fn len(mut self, value: usize) -> Self {
+        match &mut self.0 {
+            Kind::String { len, .. } => *len = Some(value),
+            other => panic!("len() called on a non-string schema: {:?}", other),
+        }
+        self
+    }
+
+    /// Requires `key`, whose value must match `schema`.
+    pub fn key<K: AsRef<[u8]>>(self, key: K, schema: Schema) -> Self {
+        self.add_key(key.as_ref(), schema, true)
+    }
+
+    /// Allows `key`, whose value must match `schema` when it's there.
+    pub fn optional<K: AsRef<[u8]>>(self, key: K, schema: Schema) -> Self {
+        self.add_key(key.as_ref(), schema, false)
+    }
+
+    /// Reports keys not added with `key` or `optional` as `Violation::UnknownKey`.
+    pub fn deny_unknown_keys(mut self) -> Self {
+        match &mut self.0 {
+            Kind::Dict {
+                deny_unknown_keys, ..
+            } => *deny_unknown_keys = true,
+            other => panic!(
+                "deny_unknown_keys() called on a non-dict schema: {:?}",
+                other
+            ),
+        }
+        self
+    }
+
+    fn add_key(mut self, key: &[u8], schema: Schema, required: bool) -> Self {
+        match &mut self.0 {
+            Kind::Dict { keys, .. } => keys.push((key.to_vec(), schema, required)),
+            other => panic!("key() called on a non-dict schema: {:?}", other),
+        }
+        self
+    }
+
+    /// Checks `value` against the schema, returning every violation found. An empty result
+    /// means the value matches.
+    ///
+    /// Nothing under a value of the wrong type is checked.
+    pub fn validate(&self, value: &BEValue) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        self.check(value, &mut String::new(), &mut violations);
+        violations
+    }
+
+    fn check(&self, value: &BEValue, path: &mut String, violations: &mut Vec<Violation>) {
+        match (&self.0, value) {
+            (Kind::Any, _) => {}
+            (Kind::Integer { min, max }, BEValue::BEInteger(i)) => {
+                if i < min || i > max {
+                    violations.push(Violation::OutOfRange(path.clone(), *i));
+                }
+            }
+            (Kind::String { text, len }, BEValue::BEString(bytes)) => {
+                if len.is_some_and(|len| len != bytes.len()) {
+                    violations.push(Violation::WrongLength(path.clone(), bytes.len()));
+                }
+                if *text && std::str::from_utf8(bytes).is_err() {
+                    violations.push(Violation::NotUtf8(path.clone()));
+                }
+            }
+            (Kind::List(element), BEValue::BEList(list)) => {
+                for (i, value) in list.iter().enumerate() {
+                    let len = push_component(path, &i.to_string());
+                    element.check(value, path, violations);
+                    path.truncate(len);
+                }
+            }
+            (
+                Kind::Dict {
+                    keys,
+                    deny_unknown_keys,
+                },
+                BEValue::BEDict(dict),
+            ) => {
+                for (key, schema, required) in keys {
+                    let len = push_component(path, &String::from_utf8_lossy(key));
+                    match value.get(key) {
+                        Some(value) => schema.check(value, path, violations),
+                        None if *required => violations.push(Violation::MissingKey(path.clone())),
+                        None => {}
+                    }
+                    path.truncate(len);
+                }
+                if *deny_unknown_keys {
+                    for (key, _) in dict {
+                        if !keys.iter().any(|(k, _, _)| key == k) {
+                            let len = push_component(path, &String::from_utf8_lossy(key));
+                            violations.push(Violation::UnknownKey(path.clone()));
+                            path.truncate(len);
+                        }
+                    }
+                }
+            }
+            (kind, _) => {
+                let expected = match kind {
+                    Kind::Integer { .. } => "integer",
+                    Kind::String { .. } => "string",
+                    Kind::List(_) => "list",
+                    _ => "dict",
+                };
+                violations.push(Violation::WrongType(path.clone(), expected));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reader::BESliceReader;
+
+    fn parse(bytes: &[u8]) -> BEValue {
+        BESliceReader::new(bytes).next_value().unwrap().unwrap()
+    }
+
+    fn ping() -> Schema {
+        Schema::dict()
+            .key("t", Schema::string())
+            .key("y", Schema::text())
+            .key("q", Schema::text())
+            .key("a", Schema::dict().key("id", Schema::string().len(20)))
+            .deny_unknown_keys()
+    }
+
+    #[test]
+    fn test_valid() {
+        let value = parse(b"d1:ad2:id20:abcdefghij0123456789e1:q4:ping1:t2:aa1:y1:qe");
+        assert_eq!(Vec::<Violation>::new(), ping().validate(&value));
+        assert!(Schema::any().validate(&value).is_empty());
+    }
+
+    #[test]
+    fn test_violations() {
+        let value = parse(b"d1:ad2:id3:abce1:q1:\xff1:t2:aa1:vi1e1:yi1ee");
+        assert_eq!(
+            vec![
+                Violation::WrongType("y".to_string(), "string"),
+                Violation::NotUtf8("q".to_string()),
+                Violation::WrongLength("a.id".to_string(), 3),
+                Violation::UnknownKey("v".to_string()),
+            ],
+            ping().validate(&value)
+        );
+
+        let value = parse(b"d1:ade1:q4:ping1:y1:qe");
+        assert_eq!(
+            vec![
+                Violation::MissingKey("t".to_string()),
+                Violation::MissingKey("a.id".to_string()),
+            ],
+            ping().validate(&value)
+        );
+        assert_eq!(
+            vec![Violation::WrongType("".to_string(), "dict")],
+            ping().validate(&parse(b"le"))
+        );
+    }
+
+    #[test]
+    fn test_lists_and_ranges() {
+        let schema = Schema::list(Schema::list(Schema::integer().min(0).max(9)));
+        let value = parse(b"lli1ei10eeli-1e3:fooee");
+        let violations = schema.validate(&value);
+        assert_eq!(
+            vec![
+                Violation::OutOfRange("0.1".to_string(), 10),
+                Violation::OutOfRange("1.0".to_string(), -1),
+                Violation::WrongType("1.1".to_string(), "integer"),
+            ],
+            violations
+        );
+        assert_eq!("1.1", violations[2].path());
+        assert!(schema.validate(&parse(b"le")).is_empty());
+    }
+
+    #[test]
+    #[should_panic(expected = "min() called on a non-integer schema")]
+    fn test_misuse() {
+        Schema::string().min(1);
+    }
+}
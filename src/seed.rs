@@ -0,0 +1,268 @@
+//! `DeserializeSeed` adapters for decoding into buffers the caller already owns.
+//!
+//! Deriving `Deserialize` allocates fresh strings and vectors for every message. A server that
+//! decodes the same shape of message over and over can instead keep its buffers and refill them:
+//!
+//! - `BytesInto` copies a byte string into an existing `Vec<u8>`.
+//! - `SeqOf` decodes each element of a list with a seed from a `SeedFactory`.
+//! - `MapFields` hands each dict entry to a `Fields` implementation, skipping the ones it
+//!   doesn't want.
+//!
+//! Once the buffers have grown to fit, decoding allocates nothing.
+//!
+//! ```
+//! use beeenn::seed::{BytesInto, Fields, MapFields, SeedFactory, SeqOf};
+//! use beeenn::Deserializer;
+//! use serde::de::{self, DeserializeSeed, MapAccess};
+//!
+//! // Peer ids decoded into a pool of reusable buffers.
+//! #[derive(Default)]
+//! struct Peers {
+//!     ids: Vec<Vec<u8>>,
+//!     len: usize,
+//! }
+//!
+//! impl<'de> SeedFactory<'de> for Peers {
+//!     type Seed<'s> = BytesInto<'s>;
+//!
+//!     fn seed(&mut self, index: usize) -> BytesInto<'_> {
+//!         if index == self.ids.len() {
+//!             self.ids.push(Vec::new());
+//!         }
+//!         BytesInto(&mut self.ids[index])
+//!     }
+//! }
+//!
+//! #[derive(Default)]
+//! struct Announce {
+//!     info_hash: Vec<u8>,
+//!     port: u16,
+//!     peers: Peers,
+//! }
+//!
+//! impl<'de> Fields<'de> for Announce {
+//!     fn field<A: MapAccess<'de>>(&mut self, key: &[u8], map: &mut A) -> Result<bool, A::Error> {
+//!         match key {
+//!             b"info_hash" => map.next_value_seed(BytesInto(&mut self.info_hash))?,
+//!             b"port" => self.port = map.next_value()?,
+//!             b"peers" => self.peers.len = map.next_value_seed(SeqOf(&mut self.peers))?,
+//!             _ => return Ok(false),
+//!         }
+//!         Ok(true)
+//!     }
+//! }
+//!
+//! let mut announce = Announce::default();
+//! for message in [&b"d9:info_hash2:ab5:peersl1:x1:ye4:porti80ee"[..], b"d5:peersl1:ze4:porti81ee"] {
+//!     let mut de = Deserializer::from_bytes(message);
+//!     MapFields(&mut announce).deserialize(&mut de).unwrap();
+//!     de.end().unwrap();
+//! }
+//! assert_eq!(81, announce.port);
+//! assert_eq!(b"ab", &announce.info_hash[..]);
+//! assert_eq!(1, announce.peers.len);
+//! assert_eq!(b"z", &announce.peers.ids[0][..]);
+//! ```
+
+use std::fmt;
+
+use serde::de::{self, DeserializeSeed, Deserializer, IgnoredAny, MapAccess, SeqAccess, Visitor};
+
+/// A seed that replaces the contents of a `Vec<u8>` with a byte string, reusing its capacity.
+pub struct BytesInto<'a>(pub &'a mut Vec<u8>);
+
+impl<'de> DeserializeSeed<'de> for BytesInto<'_> {
+    type Value = ();
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+        deserializer.deserialize_bytes(self)
+    }
+}
+
+impl<'de> Visitor<'de> for BytesInto<'_> {
+    type Value = ();
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a byte string")
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<(), E> {
+        self.0.clear();
+        self.0.extend_from_slice(bytes);
+        Ok(())
+    }
+
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<(), E> {
+        self.visit_bytes(s.as_bytes())
+    }
+}
+
+/// Makes the seed for each element decoded by `SeqOf`.
+///
+/// Each seed may borrow from the factory, so it can point into a buffer the factory owns.
+/// Closures that return seeds owning nothing they borrow implement this too.
+pub trait SeedFactory<'de> {
+    type Seed<'s>: DeserializeSeed<'de, Value = ()>
+    where
+        Self: 's;
+
+    /// Returns the seed for the element at `index`.
+    ///
+    /// `SeqOf` asks for a seed before it knows whether another element follows, so this is also
+    /// called once with the index just past the last element, and that seed goes unused.
+    fn seed(&mut self, index: usize) -> Self::Seed<'_>;
+}
+
+impl<'de, F, S> SeedFactory<'de> for F
+where
+    F: FnMut(usize) -> S,
+    S: DeserializeSeed<'de, Value = ()>,
+{
+    type Seed<'s>
+        = S
+    where
+        F: 's;
+
+    fn seed(&mut self, index: usize) -> S {
+        self(index)
+    }
+}
+
+/// A seed that decodes a list with one seed per element. Its value is the number of elements.
+pub struct SeqOf<'a, F>(pub &'a mut F);
+
+impl<'de, F: SeedFactory<'de>> DeserializeSeed<'de> for SeqOf<'_, F> {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_seq(self)
+    }
+}
+
+impl<'de, F: SeedFactory<'de>> Visitor<'de> for SeqOf<'_, F> {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a list")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<usize, A::Error> {
+        let mut len = 0;
+        while seq.next_element_seed(self.0.seed(len))?.is_some() {
+            len += 1;
+        }
+        Ok(len)
+    }
+}
+
+/// Decodes the entries of a dict for `MapFields`.
+pub trait Fields<'de> {
+    /// Decodes the value for `key` with `map.next_value` or `map.next_value_seed`, returning
+    /// `true`. Returns `false` without touching `map` to skip the value.
+    fn field<A: MapAccess<'de>>(&mut self, key: &[u8], map: &mut A) -> Result<bool, A::Error>;
+}
+
+/// A seed that decodes a dict entry by entry into a `Fields`. Its value is the number of entries
+/// that weren't skipped.
+pub struct MapFields<'a, T>(pub &'a mut T);
+
+impl<'de, T: Fields<'de>> DeserializeSeed<'de> for MapFields<'_, T> {
+    type Value = usize;
+
+    fn deserialize<D: Deserializer<'de>>(self, deserializer: D) -> Result<usize, D::Error> {
+        deserializer.deserialize_map(self)
+    }
+}
+
+impl<'de, T: Fields<'de>> Visitor<'de> for MapFields<'_, T> {
+    type Value = usize;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a dict")
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<usize, A::Error> {
+        let mut used = 0;
+        while let Some(key) = map.next_key::<&'de [u8]>()? {
+            if self.0.field(key, &mut map)? {
+                used += 1;
+            } else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+        Ok(used)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Deserializer, Error};
+    use serde::Deserialize;
+
+    #[test]
+    fn test_bytes_into() {
+        let mut buf = Vec::with_capacity(16);
+        let ptr = buf.as_ptr();
+        for (input, expected) in [(&b"5:hello"[..], &b"hello"[..]), (b"2:hi", b"hi")] {
+            let mut de = Deserializer::from_bytes(input);
+            BytesInto(&mut buf).deserialize(&mut de).unwrap();
+            assert_eq!(expected, &buf[..]);
+        }
+        assert_eq!(ptr, buf.as_ptr());
+
+        let mut de = Deserializer::from_bytes(b"i1e");
+        assert!(BytesInto(&mut buf).deserialize(&mut de).is_err());
+    }
+
+    #[test]
+    fn test_seq_of() {
+        // A closure seed factory whose seeds own nothing.
+        let mut factory = |_| Skip;
+        let mut de = Deserializer::from_bytes(b"li1e3:abcleli2eee");
+        assert_eq!(4, SeqOf(&mut factory).deserialize(&mut de).unwrap());
+
+        let mut de = Deserializer::from_bytes(b"d1:ai1ee");
+        assert!(SeqOf(&mut factory).deserialize(&mut de).is_err());
+    }
+
+    #[test]
+    fn test_map_fields() {
+        struct Totals(i64);
+        impl<'de> Fields<'de> for Totals {
+            fn field<A: MapAccess<'de>>(
+                &mut self,
+                key: &[u8],
+                map: &mut A,
+            ) -> Result<bool, A::Error> {
+                if !key.starts_with(b"n") {
+                    return Ok(false);
+                }
+                self.0 += map.next_value::<i64>()?;
+                Ok(true)
+            }
+        }
+
+        let mut totals = Totals(0);
+        let mut de = Deserializer::from_bytes(b"d2:n1i3e1:xli9ee2:n2i4ee");
+        assert_eq!(2, MapFields(&mut totals).deserialize(&mut de).unwrap());
+        assert_eq!(7, totals.0);
+
+        let mut de = Deserializer::from_bytes(b"d2:n13:abce");
+        assert_eq!(
+            Error::UnexpectedPrefix('3', 'i'),
+            MapFields(&mut totals).deserialize(&mut de).unwrap_err()
+        );
+    }
+
+    // Skips whatever it is given.
+    struct Skip;
+
+    impl<'de> DeserializeSeed<'de> for Skip {
+        type Value = ();
+
+        fn deserialize<D: serde::Deserializer<'de>>(self, deserializer: D) -> Result<(), D::Error> {
+            IgnoredAny::deserialize(deserializer).map(drop)
+        }
+    }
+}
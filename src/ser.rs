@@ -3,23 +3,326 @@ use serde::{ser, Serialize};
 use std::io::Write;
 
 use super::{Error, Result};
+use crate::keys::cmp_keys;
 
+/// Writes values as bencode into a buffer it owns.
+///
+/// `to_bytes` is enough for a single value. Use a `Serializer` to write several values back to
+/// back into one buffer.
 pub struct Serializer {
     bytes: Vec<u8>,
+
+    // True when serializing a struct field, where a top-level None is written as nothing so
+    // that the field is left out of the dict.
+    in_field: bool,
+
+    // The index of the current element in each enclosing list, innermost last.
+    list_indices: Vec<usize>,
+
+    // Write dict keys in the order they are given instead of sorting them.
+    preserve_order: bool,
+
+    // Cleared buffers left over from written dict entries, reused for the next nested values so
+    // that a torrent with thousands of files doesn't allocate a new buffer for every field.
+    scratch: Pool,
+}
+
+/// How a `Serializer` has used its pool of scratch buffers, from `Serializer::pool_stats`.
+///
+/// Struct fields and map entries are each serialized into a buffer of their own before they
+/// are sorted and written, and those buffers are reused. `created` stays small, however many
+/// values are written, when reuse is working.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct PoolStats {
+    /// Buffers taken from the pool.
+    pub reused: usize,
+    /// Buffers created because the pool was empty.
+    pub created: usize,
+    /// Buffers in the pool now.
+    pub pooled: usize,
+}
+
+#[derive(Default)]
+struct Pool {
+    buffers: Vec<Vec<u8>>,
+    reused: usize,
+    created: usize,
+}
+
+impl Pool {
+    fn take(&mut self) -> Vec<u8> {
+        match self.buffers.pop() {
+            Some(buffer) => {
+                self.reused += 1;
+                buffer
+            }
+            None => {
+                self.created += 1;
+                Vec::new()
+            }
+        }
+    }
 }
 
+// The error for a type bencode has no way to write.
+pub(crate) fn unsupported(what: &str) -> Error {
+    Error::Message(format!("bencode cannot represent {}", what))
+}
+
+// The error for an `f32` or `f64`, which bencode has no encoding for.
+pub(crate) fn float_error() -> Error {
+    unsupported("floats; wrap the field in beeenn::BeFloat to store it as a decimal string")
+}
+
+// The most spare buffers a `Serializer` keeps. Only a few are in use at once: one for each
+// entry of the dicts currently open.
+const MAX_SCRATCH_BUFFERS: usize = 32;
+
+/// Serializes `value` into a new buffer.
+///
+/// Dict keys are written in sorted order, as canonical bencode requires, and `None` fields are
+/// left out.
+///
+/// ```
+/// use serde::Serialize;
+///
+/// #[derive(Serialize)]
+/// struct Torrent<'a> {
+///     announce: &'a str,
+///     comment: Option<&'a str>,
+///     #[serde(rename = "creation date")]
+///     creation_date: u64,
+/// }
+///
+/// let torrent = Torrent {
+///     announce: "udp:a",
+///     comment: None,
+///     creation_date: 1_700_000_000,
+/// };
+/// assert_eq!(
+///     &b"d8:announce5:udp:a13:creation datei1700000000ee"[..],
+///     &beeenn::to_bytes(&torrent).unwrap()[..]
+/// );
+/// ```
 pub fn to_bytes<T>(value: &T) -> Result<Vec<u8>>
 where
     T: Serialize,
 {
-    let mut serializer = Serializer {
-        bytes: Default::default(),
-    };
+    let mut serializer = Serializer::new();
     value.serialize(&mut serializer)?;
     Ok(serializer.bytes)
 }
 
+/// Appends the encoding of `value` to `bytes`.
+///
+/// If serialization fails, `bytes` is left as it was.
+///
+/// ```
+/// let mut batch = Vec::new();
+/// beeenn::append_to_bytes(&mut batch, &vec!["ping", "pong"]).unwrap();
+/// beeenn::append_to_bytes(&mut batch, &7).unwrap();
+/// assert_eq!(b"l4:ping4:pongei7e", &batch[..]);
+/// ```
+pub fn append_to_bytes<T>(bytes: &mut Vec<u8>, value: &T) -> Result<()>
+where
+    T: ?Sized + Serialize,
+{
+    let mut serializer = Serializer::from_bytes(std::mem::take(bytes));
+    let result = serializer.append(value);
+    *bytes = serializer.bytes;
+    result
+}
+
 impl Serializer {
+    /// Creates a serializer with an empty buffer.
+    pub fn new() -> Self {
+        Self::with_in_field(false)
+    }
+
+    /// Creates a serializer that appends to `bytes`.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Serializer {
+            bytes,
+            ..Self::new()
+        }
+    }
+
+    fn with_in_field(in_field: bool) -> Self {
+        Serializer {
+            bytes: Default::default(),
+            in_field,
+            list_indices: Vec::new(),
+            preserve_order: false,
+            scratch: Pool::default(),
+        }
+    }
+
+    /// When `preserve` is set, struct fields are written in declaration order and map entries
+    /// in iteration order, instead of sorted by key.
+    ///
+    /// The output is **not canonical bencode**: other decoders, including `BEReader`, may reject
+    /// it, and it can't be used to compute an infohash. It exists to reproduce the output of
+    /// other encoders byte for byte when debugging or diffing.
+    pub fn preserve_order(mut self, preserve: bool) -> Self {
+        self.preserve_order = preserve;
+        self
+    }
+
+    // Serializes `value` on its own, with the same options, into a buffer from `scratch`. The
+    // nested serializer borrows the scratch buffers and gives them back when it's done.
+    fn serialize_nested<T>(&mut self, value: &T, in_field: bool) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut serializer = Serializer {
+            bytes: self.scratch.take(),
+            preserve_order: self.preserve_order,
+            scratch: std::mem::take(&mut self.scratch),
+            ..Self::with_in_field(in_field)
+        };
+        let result = value.serialize(&mut serializer);
+        self.scratch = serializer.scratch;
+        result?;
+        Ok(serializer.bytes)
+    }
+
+    // Copies `bytes` into a buffer from `scratch`.
+    fn scratch_copy(&mut self, bytes: &[u8]) -> Vec<u8> {
+        let mut buffer = self.scratch.take();
+        buffer.extend_from_slice(bytes);
+        buffer
+    }
+
+    // Keeps `buffer` for a later nested value.
+    fn recycle(&mut self, mut buffer: Vec<u8>) {
+        if self.scratch.buffers.len() < MAX_SCRATCH_BUFFERS && buffer.capacity() > 0 {
+            buffer.clear();
+            self.scratch.buffers.push(buffer);
+        }
+    }
+
+    // Serializes a struct field or map value. Returns an empty Vec if it should be left out.
+    fn field_to_bytes<T>(&mut self, value: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        self.serialize_nested(value, true)
+    }
+
+    // Serializes a map key, which must be a string or an integer, returning its raw bytes.
+    // Integers become their decimal string.
+    fn key_to_bytes<T>(&mut self, key: &T) -> Result<Vec<u8>>
+    where
+        T: ?Sized + Serialize,
+    {
+        let mut bytes = self.serialize_nested(key, false)?;
+        match bytes.iter().position(|b| !b.is_ascii_digit()) {
+            Some(colon) if colon > 0 && bytes[colon] == b':' => {
+                bytes.drain(..=colon);
+                Ok(bytes)
+            }
+            Some(0) if bytes[0] == b'i' => {
+                bytes.pop();
+                bytes.remove(0);
+                Ok(bytes)
+            }
+            _ => Err(Error::KeyMustBeString),
+        }
+    }
+
+    // Adds a dict entry as soon as it is serialized, so that a repeated key (from a `rename`
+    // collision or `flatten`, say) fails right away, naming the key. Canonical bencode lists keys
+    // in order of their raw bytes, so entries are kept sorted unless order is preserved.
+    fn insert_entry(
+        &self,
+        entries: &mut Vec<(Vec<u8>, Vec<u8>)>,
+        key: Vec<u8>,
+        value: Vec<u8>,
+    ) -> Result<()> {
+        let pos = if self.preserve_order {
+            match entries.iter().position(|(k, _)| *k == key) {
+                Some(pos) => Ok(pos),
+                None => Err(entries.len()),
+            }
+        } else {
+            entries.binary_search_by(|(k, _)| cmp_keys(k, &key))
+        };
+        match pos {
+            Ok(_) => Err(Error::DuplicateKey(
+                String::from_utf8_lossy(&key).into_owned(),
+            )),
+            Err(pos) => {
+                entries.insert(pos, (key, value));
+                Ok(())
+            }
+        }
+    }
+
+    // Writes a dict from entries built by `insert_entry`, skipping empty values.
+    fn write_dict(&mut self, entries: Vec<(Vec<u8>, Vec<u8>)>) -> Result<()> {
+        write!(self.bytes, "{}", b'd' as char)?;
+        for (key, value) in entries {
+            // We don't write empty fields.
+            if !value.is_empty() {
+                self.serialize_bytes(&key)?;
+                self.bytes.extend_from_slice(&value);
+            }
+            self.recycle(key);
+            self.recycle(value);
+        }
+        write!(self.bytes, "{}", b'e' as char)?;
+        Ok(())
+    }
+
+    /// Writes `value` after everything already in the buffer.
+    ///
+    /// If serialization fails, the buffer is left as it was.
+    pub fn append<T>(&mut self, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let len = self.bytes.len();
+        self.list_indices.clear();
+        let result = value.serialize(&mut *self);
+        if result.is_err() {
+            self.bytes.truncate(len);
+        }
+        result
+    }
+
+    /// Returns everything written so far.
+    pub fn bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Counts of the scratch buffers used so far, for benchmarks and tuning.
+    ///
+    /// ```
+    /// use beeenn::Serializer;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut serializer = Serializer::new();
+    /// for i in 0..100 {
+    ///     let mut file = BTreeMap::new();
+    ///     file.insert("length", i);
+    ///     serializer.append(&file).unwrap();
+    /// }
+    /// let stats = serializer.pool_stats();
+    /// assert_eq!(200, stats.created + stats.reused);
+    /// assert_eq!(2, stats.created);
+    /// ```
+    pub fn pool_stats(&self) -> PoolStats {
+        PoolStats {
+            reused: self.scratch.reused,
+            created: self.scratch.created,
+            pooled: self.scratch.buffers.len(),
+        }
+    }
+
     // Does not write 'i' or 'e'.
     fn write_raw_int(&mut self, val: u64) -> Result<()> {
         if val == 0 {
@@ -40,6 +343,12 @@ impl Serializer {
     }
 }
 
+impl Default for Serializer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl<'a> ser::Serializer for &'a mut Serializer {
     type Ok = ();
     type Error = Error;
@@ -47,10 +356,10 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     type SerializeSeq = Self;
     type SerializeTuple = Self;
     type SerializeTupleStruct = Self;
-    type SerializeTupleVariant = Self;
-    type SerializeMap = Self;
+    type SerializeTupleVariant = ser::Impossible<(), Error>;
+    type SerializeMap = SerializeMap<'a>;
     type SerializeStruct = SerializeStruct<'a>;
-    type SerializeStructVariant = Self;
+    type SerializeStructVariant = ser::Impossible<(), Error>;
 
     fn serialize_bool(self, v: bool) -> Result<Self::Ok> {
         self.serialize_u64(if v { 1 } else { 0 })
@@ -69,12 +378,12 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_i64(self, v: i64) -> Result<Self::Ok> {
-        let abs_val = v.abs();
+        let abs_val = v.unsigned_abs();
         write!(self.bytes, "{}", b'i' as char)?;
         if v < 0 {
             write!(self.bytes, "{}", b'-' as char)?;
         }
-        self.write_raw_int(abs_val as u64)?;
+        self.write_raw_int(abs_val)?;
         write!(self.bytes, "{}", b'e' as char)?;
         Ok(())
     }
@@ -99,112 +408,133 @@ impl<'a> ser::Serializer for &'a mut Serializer {
     }
 
     fn serialize_f32(self, _v: f32) -> Result<Self::Ok> {
-        todo!()
+        Err(float_error())
     }
 
     fn serialize_f64(self, _v: f64) -> Result<Self::Ok> {
-        todo!()
+        Err(float_error())
     }
 
-    fn serialize_char(self, _v: char) -> Result<Self::Ok> {
-        todo!()
+    // Written as a one-character string.
+    fn serialize_char(self, v: char) -> Result<Self::Ok> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
     }
 
     fn serialize_str(self, v: &str) -> Result<Self::Ok> {
         self.write_raw_int(v.len() as u64)?;
         write!(self.bytes, ":")?;
-        for byte in v.bytes() {
-            write!(self.bytes, "{}", byte as char)?;
-        }
+        self.bytes.extend_from_slice(v.as_bytes());
         Ok(())
     }
 
-    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok> {
-        todo!()
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok> {
+        self.write_raw_int(v.len() as u64)?;
+        write!(self.bytes, ":")?;
+        self.bytes.extend_from_slice(v);
+        Ok(())
     }
 
     fn serialize_none(self) -> Result<Self::Ok> {
-        // No-op. beencoded just leaves out missing values.
-        Ok(())
+        // beencoded has no null, so None can only be represented by leaving out a struct field.
+        match self.list_indices.last() {
+            Some(&index) => Err(Error::NoneInContainer {
+                container: "list",
+                index,
+            }),
+            None if self.in_field => Ok(()),
+            None => Err(Error::NoneValue),
+        }
     }
 
-    fn serialize_some<T: ?Sized>(self, _value: &T) -> Result<Self::Ok>
+    fn serialize_some<T>(self, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        value.serialize(self)
     }
 
     fn serialize_unit(self) -> Result<Self::Ok> {
-        todo!()
+        Err(unsupported("()"))
     }
 
-    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok> {
-        todo!()
+    fn serialize_unit_struct(self, name: &'static str) -> Result<Self::Ok> {
+        Err(unsupported(&format!("the unit struct {}", name)))
     }
 
     fn serialize_unit_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
     ) -> Result<Self::Ok> {
-        todo!()
+        Err(unsupported(&format!(
+            "the enum variant {}::{}",
+            name, variant
+        )))
     }
 
-    fn serialize_newtype_struct<T: ?Sized>(
-        self,
-        _name: &'static str,
-        _value: &T,
-    ) -> Result<Self::Ok>
+    // Newtypes are written as the value they wrap.
+    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        value.serialize(self)
     }
 
-    fn serialize_newtype_variant<T: ?Sized>(
+    fn serialize_newtype_variant<T>(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _value: &T,
     ) -> Result<Self::Ok>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        Err(unsupported(&format!(
+            "the enum variant {}::{}",
+            name, variant
+        )))
     }
 
     fn serialize_seq(self, _len: Option<usize>) -> Result<Self::SerializeSeq> {
         write!(self.bytes, "{}", b'l' as char)?;
+        self.list_indices.push(0);
         Ok(self)
     }
 
-    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple> {
-        todo!()
+    // Tuples and tuple structs are written as lists.
+    fn serialize_tuple(self, len: usize) -> Result<Self::SerializeTuple> {
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_struct(
         self,
         _name: &'static str,
-        _len: usize,
+        len: usize,
     ) -> Result<Self::SerializeTupleStruct> {
-        todo!()
+        self.serialize_seq(Some(len))
     }
 
     fn serialize_tuple_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
-        todo!()
+        Err(unsupported(&format!(
+            "the enum variant {}::{}",
+            name, variant
+        )))
     }
 
     fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap> {
-        todo!()
+        Ok(SerializeMap {
+            serializer: self,
+            entries: Vec::new(),
+            key: None,
+        })
     }
 
     fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self::SerializeStruct> {
@@ -213,110 +543,115 @@ impl<'a> ser::Serializer for &'a mut Serializer {
 
     fn serialize_struct_variant(
         self,
-        _name: &'static str,
+        name: &'static str,
         _variant_index: u32,
-        _variant: &'static str,
+        variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
-        todo!()
+        Err(unsupported(&format!(
+            "the enum variant {}::{}",
+            name, variant
+        )))
     }
 }
 
-impl<'a> ser::SerializeSeq for &'a mut Serializer {
+impl ser::SerializeSeq for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        value.serialize(&mut **self)
+        value.serialize(&mut **self)?;
+        if let Some(index) = self.list_indices.last_mut() {
+            *index += 1;
+        }
+        Ok(())
     }
 
     fn end(self) -> Result<Self::Ok> {
         write!(self.bytes, "{}", b'e' as char)?;
+        self.list_indices.pop();
         Ok(())
     }
 }
 
-impl<'a> ser::SerializeTuple for &'a mut Serializer {
+impl ser::SerializeTuple for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_element<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_element<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        ser::SerializeSeq::serialize_element(self, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        todo!()
+        ser::SerializeSeq::end(self)
     }
 }
 
-impl<'a> ser::SerializeTupleStruct for &'a mut Serializer {
+impl ser::SerializeTupleStruct for &mut Serializer {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        ser::SerializeSeq::serialize_element(self, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        todo!()
+        ser::SerializeSeq::end(self)
     }
 }
 
-impl<'a> ser::SerializeTupleVariant for &'a mut Serializer {
-    type Ok = ();
-    type Error = Error;
+pub struct SerializeMap<'a> {
+    serializer: &'a mut Serializer,
 
-    fn serialize_field<T: ?Sized>(&mut self, _value: &T) -> Result<()>
-    where
-        T: Serialize,
-    {
-        todo!()
-    }
+    // Raw keys and encoded values, written once they are all known.
+    entries: Vec<(Vec<u8>, Vec<u8>)>,
 
-    fn end(self) -> Result<Self::Ok> {
-        todo!()
-    }
+    // The key waiting for its value.
+    key: Option<Vec<u8>>,
 }
 
-impl<'a> ser::SerializeMap for &'a mut Serializer {
+impl ser::SerializeMap for SerializeMap<'_> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_key<T: ?Sized>(&mut self, _key: &T) -> Result<()>
+    fn serialize_key<T>(&mut self, key: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        self.key = Some(self.serializer.key_to_bytes(key)?);
+        Ok(())
     }
 
-    fn serialize_value<T: ?Sized>(&mut self, _value: &T) -> Result<()>
+    fn serialize_value<T>(&mut self, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        todo!()
+        let key = self
+            .key
+            .take()
+            .expect("serialize_value called before serialize_key");
+        let value = self.serializer.field_to_bytes(value)?;
+        self.serializer.insert_entry(&mut self.entries, key, value)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        todo!()
+        self.serializer.write_dict(self.entries)
     }
 }
 
 pub struct SerializeStruct<'a> {
-    // beencoded dictionaries require the fields to be in alpha order.
-    // store them here until we can sort and write them after all fields are known.
-    // We store the values pre-serialized so that we can work with any types.
-    //
-    // This will be kept empty except while processing a dict.
-    fields: std::collections::HashMap<&'static str, Vec<u8>>,
+    // Fields are held, pre-serialized so that we can work with any types, until all of them are
+    // known and can be written in order.
+    fields: Vec<(Vec<u8>, Vec<u8>)>,
 
     serializer: &'a mut Serializer,
 }
@@ -325,7 +660,7 @@ impl<'a> SerializeStruct<'a> {
     fn new(serializer: &'a mut Serializer) -> Self {
         SerializeStruct {
             serializer,
-            fields: Default::default(),
+            fields: Vec::new(),
         }
     }
 }
@@ -334,50 +669,60 @@ impl<'a> ser::SerializeStruct for SerializeStruct<'a> {
     type Ok = ();
     type Error = Error;
 
-    fn serialize_field<T: ?Sized>(&mut self, key: &'static str, value: &T) -> Result<()>
+    fn serialize_field<T>(&mut self, key: &'static str, value: &T) -> Result<()>
     where
-        T: Serialize,
+        T: ?Sized + Serialize,
     {
-        let bytes = to_bytes(&value)?;
-        self.fields.insert(key, bytes);
-        Ok(())
+        let bytes = self.serializer.field_to_bytes(value)?;
+        let key = self.serializer.scratch_copy(key.as_bytes());
+        self.serializer.insert_entry(&mut self.fields, key, bytes)
     }
 
     fn end(self) -> Result<Self::Ok> {
-        write!(self.serializer.bytes, "{}", b'd' as char)?;
-
-        // beencoded fields must be listed in alpha order.
-        let mut key_vec: Vec<&'static str> = self.fields.keys().copied().collect();
-        key_vec.sort_unstable();
-
-        for key in key_vec {
-            let buf = self.fields.get(key).unwrap();
-            if buf.is_empty() {
-                // We don't write empty fields.
-                continue;
-            }
-
-            self.serializer.serialize_str(key)?;
-            self.serializer.bytes.extend_from_slice(&buf);
-        }
-
-        write!(self.serializer.bytes, "{}", b'e' as char)?;
-        Ok(())
+        self.serializer.write_dict(self.fields)
     }
 }
 
-impl<'a> ser::SerializeStructVariant for &'a mut Serializer {
-    type Ok = ();
-    type Error = Error;
-
-    fn serialize_field<T: ?Sized>(&mut self, _key: &'static str, _value: &T) -> Result<()>
-    where
-        T: Serialize,
-    {
-        todo!()
-    }
-
-    fn end(self) -> Result<Self::Ok> {
-        todo!()
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct File {
+        length: u64,
+        path: Vec<&'static str>,
+    }
+
+    #[test]
+    fn test_scratch_reuse() {
+        let files: Vec<File> = (0..1000)
+            .map(|length| File {
+                length,
+                path: vec!["a", "b"],
+            })
+            .collect();
+        let mut serializer = Serializer::new();
+        serializer.append(&files).unwrap();
+
+        // Each file's two keys and values used the same four buffers as the file before.
+        assert_eq!(
+            PoolStats {
+                reused: 3996,
+                created: 4,
+                pooled: 4
+            },
+            serializer.pool_stats()
+        );
+        assert_eq!(
+            &b"ld6:lengthi0e4:pathl1:a1:bee"[..],
+            &serializer.bytes()[..28]
+        );
+
+        // Errors give the buffers back too.
+        let mut map = std::collections::BTreeMap::new();
+        map.insert(vec![1u8], 1);
+        assert!(serializer.append(&vec![map]).is_err());
+        assert!(serializer.pool_stats().pooled > 0);
+        assert!(serializer.pool_stats().pooled <= MAX_SCRATCH_BUFFERS);
     }
 }
@@ -0,0 +1,225 @@
+//! An immutable, cheaply cloneable form of `BEValue` for sharing across threads.
+//!
+//! Every string and container in a `SharedValue` is behind an `Arc`, so cloning a value, or any
+//! subtree of it, is a reference count increment. An `Interner` can also share identical strings
+//! between documents: keys like `length` and `path`, and tracker URLs, are repeated across most
+//! torrents an indexer holds.
+//!
+//! ```
+//! use beeenn::shared::{Interner, SharedValue};
+//! use beeenn::BEReader;
+//!
+//! let value = BEReader::new(&b"d4:infod6:lengthi7eee"[..])
+//!     .next_value()
+//!     .unwrap()
+//!     .unwrap();
+//! let shared = SharedValue::from(&value);
+//! let info = shared.get("info").unwrap().clone();
+//! std::thread::spawn(move || assert_eq!(Some(7), info.get("length").and_then(|v| v.as_int())))
+//!     .join()
+//!     .unwrap();
+//!
+//! let mut interner = Interner::default();
+//! let a = interner.intern_value(&value);
+//! let b = interner.intern_value(&value);
+//! assert_eq!(a, b);
+//! assert_eq!(2, interner.len()); // "info" and "length"
+//! ```
+
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use crate::value::BEValue;
+
+/// A shared, immutable `BEValue`. See the module documentation.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SharedValue {
+    Integer(i64),
+    String(Arc<[u8]>),
+    List(Arc<[SharedValue]>),
+    Dict(Arc<[(Arc<[u8]>, SharedValue)]>),
+    Invalid(Arc<[u8]>),
+}
+
+impl SharedValue {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            SharedValue::Integer(i) => Some(*i),
+            _ => None,
+        }
+    }
+
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            SharedValue::String(bytes) => Some(bytes),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes()
+            .and_then(|bytes| std::str::from_utf8(bytes).ok())
+    }
+
+    pub fn as_list(&self) -> Option<&[SharedValue]> {
+        match self {
+            SharedValue::List(list) => Some(list),
+            _ => None,
+        }
+    }
+
+    /// Looks up `key` in a dict. Returns `None` if the key is missing or if this is not a dict.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&SharedValue> {
+        match self {
+            SharedValue::Dict(dict) => dict
+                .iter()
+                .find(|(k, _)| &k[..] == key.as_ref())
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Follows a dotted path, as `BEValue::get_path` does.
+    pub fn get_path(&self, path: &str) -> Option<&SharedValue> {
+        path.split('.')
+            .try_fold(self, |value, component| match value {
+                SharedValue::List(list) => {
+                    component.parse::<usize>().ok().and_then(|i| list.get(i))
+                }
+                _ => value.get(component),
+            })
+    }
+
+    /// Copies this value back into an owned `BEValue`.
+    pub fn to_value(&self) -> BEValue {
+        match self {
+            SharedValue::Integer(i) => BEValue::BEInteger(*i),
+            SharedValue::String(bytes) => BEValue::BEString(bytes[..].into()),
+            SharedValue::List(list) => BEValue::BEList(list.iter().map(Self::to_value).collect()),
+            SharedValue::Dict(dict) => BEValue::BEDict(
+                dict.iter()
+                    .map(|(k, v)| (k[..].into(), v.to_value()))
+                    .collect(),
+            ),
+            SharedValue::Invalid(bytes) => BEValue::BEInvalid(bytes.to_vec()),
+        }
+    }
+}
+
+impl From<&BEValue> for SharedValue {
+    fn from(value: &BEValue) -> Self {
+        convert(value, &mut |bytes| Arc::from(bytes))
+    }
+}
+
+fn convert<F: FnMut(&[u8]) -> Arc<[u8]>>(value: &BEValue, string: &mut F) -> SharedValue {
+    match value {
+        BEValue::BEInteger(i) => SharedValue::Integer(*i),
+        BEValue::BEString(bytes) => SharedValue::String(string(bytes)),
+        BEValue::BEList(list) => {
+            SharedValue::List(list.iter().map(|v| convert(v, string)).collect())
+        }
+        BEValue::BEDict(dict) => SharedValue::Dict(
+            dict.iter()
+                .map(|(k, v)| (string(k), convert(v, string)))
+                .collect(),
+        ),
+        BEValue::BEInvalid(bytes) => SharedValue::Invalid(Arc::from(&bytes[..])),
+    }
+}
+
+/// A pool of strings shared between the `SharedValue`s built through it.
+///
+/// Strings stay in the pool until it is dropped or `clear`ed, even if no value uses them.
+#[derive(Debug, Default)]
+pub struct Interner {
+    strings: HashSet<Arc<[u8]>>,
+}
+
+impl Interner {
+    /// Returns the pooled copy of `bytes`, adding it if needed.
+    pub fn intern(&mut self, bytes: &[u8]) -> Arc<[u8]> {
+        if let Some(existing) = self.strings.get(bytes) {
+            return existing.clone();
+        }
+        let new: Arc<[u8]> = Arc::from(bytes);
+        self.strings.insert(new.clone());
+        new
+    }
+
+    /// Converts `value`, taking every key and string from the pool.
+    pub fn intern_value(&mut self, value: &BEValue) -> SharedValue {
+        convert(value, &mut |bytes| self.intern(bytes))
+    }
+
+    /// The number of distinct strings in the pool.
+    pub fn len(&self) -> usize {
+        self.strings.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.strings.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.strings.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reader::BESliceReader;
+
+    fn parse(bytes: &[u8]) -> BEValue {
+        BESliceReader::new(bytes).next_value().unwrap().unwrap()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let value = parse(b"d1:ali-1e3:fooe1:bd1:cleee");
+        let shared = SharedValue::from(&value);
+        assert_eq!(value, shared.to_value());
+        assert_eq!(Some(-1), shared.get_path("a.0").and_then(|v| v.as_int()));
+        assert_eq!(Some("foo"), shared.get_path("a.1").and_then(|v| v.as_str()));
+        assert_eq!(
+            Some(&[][..]),
+            shared.get_path("b.c").and_then(|v| v.as_list())
+        );
+        assert_eq!(None, shared.get_path("a.2"));
+        assert_eq!(None, shared.get("a").unwrap().get("x"));
+    }
+
+    #[test]
+    fn test_clones_share() {
+        let shared = SharedValue::from(&parse(b"l5:helloe"));
+        let clone = shared.clone();
+        match (&shared, &clone) {
+            (SharedValue::List(a), SharedValue::List(b)) => assert!(Arc::ptr_eq(a, b)),
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    fn test_interner() {
+        let mut interner = Interner::default();
+        let a = interner.intern_value(&parse(b"d6:lengthi1e4:path3:urle"));
+        let b = interner.intern_value(&parse(b"d6:lengthi2e4:path3:urle"));
+        // "length", "path" and "url".
+        assert_eq!(3, interner.len());
+
+        let (url_a, url_b) = (a.get("path").unwrap(), b.get("path").unwrap());
+        match (url_a, url_b) {
+            (SharedValue::String(x), SharedValue::String(y)) => assert!(Arc::ptr_eq(x, y)),
+            _ => unreachable!(),
+        }
+        interner.clear();
+        assert!(interner.is_empty());
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<SharedValue>();
+    }
+}
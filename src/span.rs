@@ -0,0 +1,131 @@
+//! Byte ranges for every node of a decoded `BEValue`.
+//!
+//! `BESliceReader::next_spanned` returns a value together with a `Spans` tree of the same shape,
+//! so tools can find exactly which input bytes hold any key or value.
+//!
+//! ```
+//! use beeenn::BESliceReader;
+//!
+//! let input = b"d4:infod6:lengthi7eee";
+//! let spanned = BESliceReader::new(input).next_spanned().unwrap().unwrap();
+//! let span = spanned.span_of("info").unwrap();
+//! assert_eq!(b"d6:lengthi7ee", &input[span]);
+//! ```
+
+use std::ops::Range;
+
+use crate::value::BEValue;
+
+/// Where a value, and each of its children, sits in the input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Spans {
+    /// The bytes of the whole value.
+    pub span: Range<usize>,
+    /// For a dict value, the bytes of the key it is stored under.
+    pub key: Option<Range<usize>>,
+    /// One entry per list element or dict entry of the value, in input order.
+    pub children: Vec<Spans>,
+}
+
+/// A `BEValue` with the byte ranges it was read from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Spanned {
+    pub value: BEValue,
+    pub spans: Spans,
+}
+
+impl Spanned {
+    // A value with no children.
+    pub(crate) fn leaf(value: BEValue, span: Range<usize>) -> Spanned {
+        Spanned {
+            value,
+            spans: Spans {
+                span,
+                key: None,
+                children: Vec::new(),
+            },
+        }
+    }
+
+    /// Follows a dotted path, as `BEValue::get_path` does, returning the value and its spans.
+    pub fn get_path(&self, path: &str) -> Option<(&BEValue, &Spans)> {
+        path.split('.')
+            .try_fold((&self.value, &self.spans), |(value, spans), component| {
+                let index = match value {
+                    BEValue::BEList(list) => component
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|i| *i < list.len())?,
+                    BEValue::BEDict(dict) => dict.iter().position(|(k, _)| k.eq_str(component))?,
+                    _ => return None,
+                };
+                let child = match value {
+                    BEValue::BEList(list) => &list[index],
+                    BEValue::BEDict(dict) => &dict[index].1,
+                    _ => unreachable!(),
+                };
+                Some((child, &spans.children[index]))
+            })
+    }
+
+    /// Returns the byte range of the value at `path`.
+    pub fn span_of(&self, path: &str) -> Option<Range<usize>> {
+        self.get_path(path).map(|(_, spans)| spans.span.clone())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::reader::{BEReaderOptions, BESliceReader, DuplicateKeys};
+
+    #[test]
+    fn test_spans() {
+        let input = b"i1ed1:ali-20e0:e2:bbi3ee";
+        let mut reader = BESliceReader::new(input);
+        reader.next_value().unwrap();
+        let spanned = reader.next_spanned().unwrap().unwrap();
+
+        assert_eq!(3..24, spanned.spans.span);
+        let a = &spanned.spans.children[0];
+        assert_eq!(Some(4..7), a.key);
+        assert_eq!(7..16, a.span);
+        assert_eq!(
+            vec![8..13, 13..15],
+            a.children
+                .iter()
+                .map(|c| c.span.clone())
+                .collect::<Vec<_>>()
+        );
+
+        let (value, spans) = spanned.get_path("bb").unwrap();
+        assert_eq!(3, value.integer());
+        assert_eq!(Some(16..20), spans.key);
+        assert_eq!(b"i3e", &input[spans.span.clone()]);
+
+        assert_eq!(Some(13..15), spanned.span_of("a.1"));
+        assert_eq!(None, spanned.span_of("a.2"));
+        assert_eq!(None, spanned.span_of("c"));
+        assert!(reader.next_spanned().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_spans_with_dropped_keys() {
+        let input = b"d1:ai1e1:ai22e1:bi3ee";
+        for policy in [DuplicateKeys::KeepFirst, DuplicateKeys::KeepLast] {
+            let options = BEReaderOptions::new().duplicate_keys(policy);
+            let spanned = options.slice_reader(input).next_spanned().unwrap().unwrap();
+            assert_eq!(0..21, spanned.spans.span);
+            assert_eq!(Some(17..20), spanned.span_of("b"));
+            assert_eq!(b"i3e", &input[spanned.span_of("b").unwrap()]);
+            assert_eq!(Some(14..17), spanned.get_path("b").unwrap().1.key);
+        }
+
+        let options = BEReaderOptions::new().duplicate_keys(DuplicateKeys::KeepFirst);
+        let spanned = options.slice_reader(input).next_spanned().unwrap().unwrap();
+        assert_eq!(Some(4..7), spanned.span_of("a"));
+        let options = BEReaderOptions::new().duplicate_keys(DuplicateKeys::KeepLast);
+        let spanned = options.slice_reader(input).next_spanned().unwrap().unwrap();
+        assert_eq!(Some(10..14), spanned.span_of("a"));
+        assert_eq!(Some(7..10), spanned.get_path("a").unwrap().1.key);
+    }
+}
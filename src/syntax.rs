@@ -0,0 +1,134 @@
+//! The bytes that make up bencode's syntax.
+//!
+//! Every value starts with a byte that says what it is: `i` for an integer, `l` for a list, `d`
+//! for a dict, or the first digit of a string's length. Integers, lists and dicts all end with
+//! `e`.
+//!
+//! ```
+//! use beeenn::syntax::{classify, is_value_start, TokenKind};
+//!
+//! assert_eq!(TokenKind::String, classify(b'4'));
+//! assert_eq!(TokenKind::End, classify(b'e'));
+//! assert!(is_value_start(b'd'));
+//! assert!(!is_value_start(b'e'));
+//! ```
+
+#[cfg(any(feature = "serde", feature = "value"))]
+use crate::error::Error;
+
+pub const DICT_PREFIX: u8 = b'd';
+pub const INTEGER_PREFIX: u8 = b'i';
+pub const LIST_PREFIX: u8 = b'l';
+pub const NEGATIVE_SIGN: u8 = b'-';
+pub const STRING_SEPARATOR: u8 = b':';
+/// Ends an integer, list or dict.
+pub const SUFFIX: u8 = b'e';
+
+/// What a byte at the start of a token means.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Integer,
+    /// A digit, starting a string's length.
+    String,
+    List,
+    Dict,
+    /// The end of a list or dict.
+    End,
+    /// Anything else, which is never valid where a token is expected.
+    Invalid,
+}
+
+/// Classifies the first byte of a token.
+pub fn classify(b: u8) -> TokenKind {
+    match b {
+        INTEGER_PREFIX => TokenKind::Integer,
+        b'0'..=b'9' => TokenKind::String,
+        LIST_PREFIX => TokenKind::List,
+        DICT_PREFIX => TokenKind::Dict,
+        SUFFIX => TokenKind::End,
+        _ => TokenKind::Invalid,
+    }
+}
+
+/// Returns true if `b` can start a value.
+pub fn is_value_start(b: u8) -> bool {
+    !matches!(classify(b), TokenKind::End | TokenKind::Invalid)
+}
+
+// The magnitude of an integer or string length, read one digit at a time. Every reader uses this,
+// so leading zeros and overflow are handled the same way everywhere and no number of digits can
+// go unchecked.
+#[cfg(any(feature = "serde", feature = "value"))]
+#[derive(Default)]
+pub(crate) struct Digits {
+    value: u64,
+    count: usize,
+}
+
+#[cfg(any(feature = "serde", feature = "value"))]
+impl Digits {
+    // Adds the ASCII digit `b`.
+    pub(crate) fn push(&mut self, b: u8) -> Result<(), Error> {
+        // Only a lone '0' may start with '0', so any digit after a leading zero is an error.
+        if self.count == 1 && self.value == 0 {
+            return Err(Error::UnexpectedZeroPrefix);
+        }
+        self.value = self
+            .value
+            .checked_mul(10)
+            .and_then(|v| v.checked_add((b - b'0') as u64))
+            .ok_or(Error::IntegerOverflow)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    #[cfg(feature = "value")]
+    pub(crate) fn value(&self) -> u64 {
+        self.value
+    }
+
+    // The value, or an error if there were no digits.
+    pub(crate) fn finish(&self) -> Result<u64, Error> {
+        if self.count == 0 {
+            return Err(Error::NoDigitsInNumber);
+        }
+        Ok(self.value)
+    }
+}
+
+// Parses a dict key that holds a decimal integer, for maps with integer keys. The digits follow
+// the same rules as an integer value's: no leading zeros and no "-0".
+#[cfg(feature = "serde")]
+pub(crate) fn parse_integer_key(key: &[u8]) -> Result<i128, Error> {
+    let not_integer = || Error::KeyNotInteger(String::from_utf8_lossy(key).into_owned());
+    let (negative, rest) = match key.split_first() {
+        Some((&NEGATIVE_SIGN, rest)) => (true, rest),
+        _ => (false, key),
+    };
+    let mut digits = Digits::default();
+    for &b in rest {
+        if !b.is_ascii_digit() {
+            return Err(not_integer());
+        }
+        digits.push(b).map_err(|_| not_integer())?;
+    }
+    let magnitude = digits.finish().map_err(|_| not_integer())? as i128;
+    match (negative, magnitude) {
+        (true, 0) => Err(not_integer()),
+        (true, m) => Ok(-m),
+        (false, m) => Ok(m),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_classify() {
+        let starts: Vec<u8> = (0..=255u8).filter(|b| is_value_start(*b)).collect();
+        assert_eq!(&b"0123456789dil"[..], &starts[..]);
+        assert_eq!(TokenKind::Invalid, classify(NEGATIVE_SIGN));
+        assert_eq!(TokenKind::Invalid, classify(STRING_SEPARATOR));
+    }
+}
@@ -0,0 +1,1886 @@
+//! Small helpers for working with torrent files and trackers.
+//!
+//! The crate has no typed model of a torrent, so these work on plain strings and numbers, or on
+//! a decoded `BEValue`.
+
+#[cfg(feature = "value")]
+use std::collections::{BTreeMap, HashSet};
+#[cfg(feature = "value")]
+use std::convert::TryFrom;
+use std::fmt;
+
+#[cfg(feature = "value")]
+use thiserror::Error as ThisError;
+
+#[cfg(feature = "value")]
+use crate::encode::{CanonicalViolation, KeyOrder};
+#[cfg(feature = "value")]
+use crate::error::Error;
+#[cfg(feature = "value")]
+use crate::reader::BESliceReader;
+#[cfg(feature = "value")]
+use crate::rewrite::replace_value;
+#[cfg(feature = "value")]
+use crate::value::BEValue;
+
+/// Problems the torrent checks find, or an `Error` reading a key they need.
+#[cfg(feature = "value")]
+#[derive(Debug, ThisError, PartialEq)]
+pub enum TorrentError {
+    /// An info dict's `pieces` doesn't hold one hash for each piece.
+    #[error("expected {expected} piece hashes, found {found}")]
+    PieceCountMismatch { expected: u64, found: usize },
+
+    #[error("piece length {0} is not a positive power of two")]
+    PieceLengthNotPowerOfTwo(i64),
+
+    /// A `pieces` string whose length isn't a multiple of the 20-byte SHA-1 hash size.
+    #[error("pieces is {0} bytes, which is not a whole number of 20-byte hashes")]
+    PiecesNotHashes(usize),
+
+    /// A `meta version` other than 1 or 2.
+    #[error("unsupported meta version {0}")]
+    UnsupportedMetaVersion(i64),
+
+    /// A key is missing or has the wrong type.
+    #[error(transparent)]
+    Value(#[from] Error),
+}
+
+// The size of a SHA-1 piece hash in `pieces`.
+#[cfg(feature = "value")]
+const HASH_LEN: usize = 20;
+
+// The smallest and largest piece lengths `recommend_piece_length` picks, and the most pieces it
+// aims for below the largest.
+const MIN_PIECE_LENGTH: u64 = 16 << 10;
+const MAX_PIECE_LENGTH: u64 = 16 << 20;
+const MAX_PIECES: u64 = 2000;
+
+/// Derives a tracker's scrape URL from its announce URL, by the usual convention of replacing
+/// `announce` at the start of the last path component with `scrape`.
+///
+/// Returns `None` when the URL has no path or its last component doesn't start with `announce`,
+/// since such a tracker has no conventional scrape URL. The query string is kept. UDP trackers scrape over the
+/// announce connection, so their result is only useful as a key.
+///
+/// ```
+/// use beeenn::torrent::scrape_url;
+///
+/// assert_eq!(
+///     Some("http://t.example/x/scrape.php?pk=1".to_string()),
+///     scrape_url("http://t.example/x/announce.php?pk=1")
+/// );
+/// assert_eq!(None, scrape_url("http://t.example/a"));
+/// ```
+pub fn scrape_url(announce: &str) -> Option<String> {
+    // Only the path counts: a '/' or "announce" in the host or the query string doesn't.
+    let path_end = announce.find('?').unwrap_or(announce.len());
+    let authority = announce[..path_end].find("://").map_or(0, |i| i + 3);
+    let path = authority + announce[authority..path_end].find('/')?;
+    let last = path + announce[path..path_end].rfind('/')? + 1;
+    let rest = announce[last..].strip_prefix("announce")?;
+    Some(format!("{}scrape{}", &announce[..last], rest))
+}
+
+/// The protocol a tracker is reached over, from the scheme of its announce URL.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TrackerProtocol {
+    Http,
+    Https,
+    Udp,
+    /// `wss`, for WebTorrent trackers.
+    WebSocket,
+}
+
+impl TrackerProtocol {
+    // The protocol for a scheme, or `None` for one trackers don't use.
+    fn from_scheme(scheme: &str) -> Option<Self> {
+        match scheme.to_ascii_lowercase().as_str() {
+            "http" => Some(TrackerProtocol::Http),
+            "https" => Some(TrackerProtocol::Https),
+            "udp" => Some(TrackerProtocol::Udp),
+            "wss" => Some(TrackerProtocol::WebSocket),
+            _ => None,
+        }
+    }
+
+    fn default_port(self) -> Option<u16> {
+        match self {
+            TrackerProtocol::Http => Some(80),
+            TrackerProtocol::Https | TrackerProtocol::WebSocket => Some(443),
+            TrackerProtocol::Udp => None,
+        }
+    }
+}
+
+/// The protocol of a tracker's announce URL, or `None` if its scheme isn't `http`, `https`,
+/// `udp` or `wss`. Surrounding whitespace and the case of the scheme are ignored.
+///
+/// Only the scheme is looked at. Use `normalize_tracker_url` to check the rest.
+///
+/// ```
+/// use beeenn::torrent::{tracker_protocol, TrackerProtocol};
+///
+/// assert_eq!(Some(TrackerProtocol::Udp), tracker_protocol("udp://t.example:1337/announce"));
+/// assert_eq!(None, tracker_protocol("ftp://t.example/announce"));
+/// ```
+pub fn tracker_protocol(url: &str) -> Option<TrackerProtocol> {
+    let (scheme, _) = url.trim().split_once("://")?;
+    TrackerProtocol::from_scheme(scheme)
+}
+
+/// Puts a tracker's announce URL in a standard form, so that the same tracker written two ways
+/// compares equal. Returns `None` if the URL isn't one a client could announce to.
+///
+/// Surrounding whitespace and any `#fragment` are removed, the scheme and host are lowercased,
+/// and the port is removed when it's the scheme's default. The path and query are kept as they
+/// are. The scheme must be one of those `tracker_protocol` accepts, there must be a host, a port
+/// must be a number, and UDP trackers, which have no default port, must give one.
+///
+/// ```
+/// use beeenn::torrent::normalize_tracker_url;
+///
+/// assert_eq!(
+///     Some("http://t.example/announce".to_string()),
+///     normalize_tracker_url(" HTTP://T.Example:80/announce ")
+/// );
+/// assert_eq!(None, normalize_tracker_url("udp://t.example/announce"));
+/// ```
+pub fn normalize_tracker_url(url: &str) -> Option<String> {
+    let (scheme, rest) = url.trim().split_once("://")?;
+    let protocol = TrackerProtocol::from_scheme(scheme)?;
+    // The fragment is never sent to the tracker.
+    let rest = rest.split('#').next().unwrap_or_default();
+    let (authority, path) = rest.split_at(rest.find(['/', '?']).unwrap_or(rest.len()));
+    let (user, host_port) = authority.split_at(authority.rfind('@').map_or(0, |i| i + 1));
+    // A ':' inside an IPv6 literal's brackets isn't a port separator.
+    let (host, port) = match host_port.rfind(':') {
+        Some(i) if !host_port[i..].contains(']') => (&host_port[..i], &host_port[i + 1..]),
+        _ => (host_port, ""),
+    };
+    if host.is_empty() {
+        return None;
+    }
+    let port = match port {
+        "" => None,
+        port => Some(port.parse::<u16>().ok()?),
+    };
+    let port = match (port, protocol.default_port()) {
+        (None, None) => return None,
+        (Some(port), Some(default)) if port == default => None,
+        (port, _) => port,
+    };
+    Some(format!(
+        "{}://{}{}{}{}",
+        scheme.to_ascii_lowercase(),
+        user,
+        host.to_ascii_lowercase(),
+        port.map(|port| format!(":{}", port)).unwrap_or_default(),
+        path
+    ))
+}
+
+/// Whether `torrent` is marked private, with `private` set to 1 in its info dict.
+///
+/// A `private` key anywhere else, such as at the top level, has no effect, because only the
+/// info dict is covered by the infohash.
+#[cfg(feature = "value")]
+pub fn is_private(torrent: &BEValue) -> bool {
+    matches!(
+        torrent.get_path("info.private"),
+        Some(BEValue::BEInteger(1))
+    )
+}
+
+/// Picks a piece length for a torrent of `total_size` bytes.
+///
+/// The result is the smallest power of two from 16 KiB that keeps the torrent to at most 2000
+/// pieces, up to 16 MiB. Larger torrents get 16 MiB pieces and more of them, since bigger pieces
+/// make each hash failure more costly to download again.
+///
+/// ```
+/// use beeenn::torrent::recommend_piece_length;
+///
+/// assert_eq!(16 << 10, recommend_piece_length(1_000_000));
+/// assert_eq!(4 << 20, recommend_piece_length(4 << 30));
+/// ```
+pub fn recommend_piece_length(total_size: u64) -> u64 {
+    let mut piece_length = MIN_PIECE_LENGTH;
+    while piece_length < MAX_PIECE_LENGTH && total_size.div_ceil(piece_length) > MAX_PIECES {
+        piece_length *= 2;
+    }
+    piece_length
+}
+
+/// Checks that an info dict's `piece length`, total size and `pieces` agree.
+///
+/// The piece length must be a positive power of two, and `pieces` must hold one 20-byte hash
+/// for each piece of the total size: `length` for a single-file torrent, or the sum of the
+/// `files` lengths.
+///
+/// ```
+/// use beeenn::torrent::{validate_piece_length, TorrentError};
+/// use beeenn::BESliceReader;
+///
+/// let info = b"d6:lengthi40000e12:piece lengthi32768e6:pieces40:\
+///              0123456789012345678901234567890123456789e";
+/// let info = BESliceReader::new(info).next_value().unwrap().unwrap();
+/// assert_eq!(Ok(()), validate_piece_length(&info));
+///
+/// let info = b"d6:lengthi80000e12:piece lengthi32768e6:pieces40:\
+///              0123456789012345678901234567890123456789e";
+/// let info = BESliceReader::new(info).next_value().unwrap().unwrap();
+/// assert_eq!(
+///     Err(TorrentError::PieceCountMismatch { expected: 3, found: 2 }),
+///     validate_piece_length(&info)
+/// );
+/// ```
+#[cfg(feature = "value")]
+pub fn validate_piece_length(info: &BEValue) -> Result<(), TorrentError> {
+    let piece_length = info.get_int("piece length")?;
+    if piece_length <= 0 || (piece_length as u64).count_ones() != 1 {
+        return Err(TorrentError::PieceLengthNotPowerOfTwo(piece_length));
+    }
+
+    let pieces = info.get_bytes("pieces")?;
+    if pieces.len() % HASH_LEN != 0 {
+        return Err(TorrentError::PiecesNotHashes(pieces.len()));
+    }
+
+    // Padding files are hashed like any other.
+    let expected = total_length(info, true)?.div_ceil(piece_length as u64);
+    let found = pieces.len() / HASH_LEN;
+    if expected != found as u64 {
+        return Err(TorrentError::PieceCountMismatch { expected, found });
+    }
+    Ok(())
+}
+
+/// Whether `file`, an entry of an info dict's `files`, is a BEP 47 padding file, with `p` in its
+/// `attr`. Padding files only align the next file to a piece boundary and are never shown to
+/// people.
+#[cfg(feature = "value")]
+pub fn is_padding(file: &BEValue) -> bool {
+    FileAttributes::of(file).padding
+}
+
+/// The size of the real content described by an info dict: `length` for a single file, or the
+/// sum of the `files` lengths, leaving padding files out.
+///
+/// ```
+/// use beeenn::torrent::content_length;
+/// use beeenn::BESliceReader;
+///
+/// let info = b"d5:filesld6:lengthi10eed4:attr1:p6:lengthi6eed6:lengthi16eeee";
+/// let info = BESliceReader::new(info).next_value().unwrap().unwrap();
+/// assert_eq!(Ok(26), content_length(&info));
+/// ```
+#[cfg(feature = "value")]
+pub fn content_length(info: &BEValue) -> Result<u64, Error> {
+    total_length(info, false)
+}
+
+// The size of the content described by an info dict, from `length` or the lengths in `files`.
+#[cfg(feature = "value")]
+fn total_length(info: &BEValue, with_padding: bool) -> Result<u64, Error> {
+    if info.get("files").is_none() {
+        return length(info, "length".to_string());
+    }
+    let mut total: u64 = 0;
+    for (i, file) in info.get_list("files")?.iter().enumerate() {
+        if !with_padding && is_padding(file) {
+            continue;
+        }
+        let file_length = length(file, format!("files.{}.length", i))?;
+        total = total
+            .checked_add(file_length)
+            .ok_or(Error::IntegerOverflow)?;
+    }
+    Ok(total)
+}
+
+// The `length` of a file, which can't be negative. `path` names it in the error.
+#[cfg(feature = "value")]
+fn length(file: &BEValue, path: String) -> Result<u64, Error> {
+    let length = file.get_int("length")?;
+    u64::try_from(length).map_err(|_| Error::IntegerOutOfRange {
+        value: length.into(),
+        target: "u64",
+        path,
+    })
+}
+
+/// The tracker URLs of a torrent, from `announce` and then each tier of `announce-list`, in
+/// order. Entries that aren't UTF-8 strings are skipped, and repeats are kept.
+///
+/// ```
+/// use beeenn::torrent::trackers;
+/// use beeenn::BESliceReader;
+///
+/// let torrent = b"d8:announce3:t:113:announce-listll3:t:1el3:t:23:t:3eee";
+/// let torrent = BESliceReader::new(torrent).next_value().unwrap().unwrap();
+/// assert_eq!(vec!["t:1", "t:1", "t:2", "t:3"], trackers(&torrent));
+/// ```
+#[cfg(feature = "value")]
+pub fn trackers(torrent: &BEValue) -> Vec<&str> {
+    let tiers = match torrent.get("announce-list") {
+        Some(BEValue::BEList(tiers)) => tiers.as_slice(),
+        _ => &[],
+    };
+    let listed = tiers.iter().flat_map(|tier| match tier {
+        BEValue::BEList(urls) => urls.as_slice(),
+        _ => &[],
+    });
+    torrent
+        .get("announce")
+        .into_iter()
+        .chain(listed)
+        .filter_map(|url| match url {
+            BEValue::BEString(url) => url.as_str(),
+            _ => None,
+        })
+        .collect()
+}
+
+/// The DHT bootstrap nodes of a trackerless torrent (BEP 5), from its `nodes` list of `[host,
+/// port]` pairs, in order.
+///
+/// Each entry is read on its own, so one malformed pair doesn't hide the rest: callers that only
+/// want usable nodes can keep the `Ok`s, and the errors name the entry that was wrong. A port
+/// written as a decimal string is accepted too, since some encoders emit one.
+///
+/// ```
+/// use beeenn::torrent::nodes;
+/// use beeenn::BESliceReader;
+///
+/// let torrent = b"d5:nodesll5:n.one4:6881el5:n.twoi6882eei3eee";
+/// let torrent = BESliceReader::new(torrent).next_value().unwrap().unwrap();
+/// let nodes = nodes(&torrent);
+/// assert_eq!(Ok(("n.one".to_string(), 6881)), nodes[0]);
+/// assert_eq!(Ok(("n.two".to_string(), 6882)), nodes[1]);
+/// assert!(nodes[2].is_err());
+/// ```
+#[cfg(feature = "value")]
+pub fn nodes(torrent: &BEValue) -> Vec<Result<(String, u16), Error>> {
+    let nodes = match torrent.get("nodes") {
+        Some(BEValue::BEList(nodes)) => nodes.as_slice(),
+        _ => &[],
+    };
+    nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node_value)| node(node_value, format!("nodes.{}", i)))
+        .collect()
+}
+
+// One `[host, port]` pair from a `nodes` list. `path` names it in the error.
+#[cfg(feature = "value")]
+fn node(node: &BEValue, path: String) -> Result<(String, u16), Error> {
+    let pair = match node {
+        BEValue::BEList(pair) if pair.len() == 2 => pair,
+        _ => return Err(Error::WrongType(path, "[host, port] pair")),
+    };
+    let host = match &pair[0] {
+        BEValue::BEString(host) => host.as_str().filter(|host| !host.is_empty()),
+        _ => None,
+    }
+    .ok_or_else(|| Error::WrongType(format!("{}.0", path), "host name"))?;
+    let port = match &pair[1] {
+        BEValue::BEInteger(port) => Some(i128::from(*port)),
+        BEValue::BEString(port) => port.as_str().and_then(|port| port.parse().ok()),
+        _ => None,
+    }
+    .ok_or_else(|| Error::WrongType(format!("{}.1", path), "port number"))?;
+    let port = u16::try_from(port).map_err(|_| Error::IntegerOutOfRange {
+        value: port,
+        target: "u16",
+        path: format!("{}.1", path),
+    })?;
+    Ok((host.to_string(), port))
+}
+
+/// Which BitTorrent metainfo versions a torrent carries.
+#[cfg(feature = "value")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TorrentVersion {
+    /// BEP 3: `pieces`, and `length` or `files`.
+    V1,
+    /// BEP 52: `meta version` 2 and a `file tree`.
+    V2,
+    /// Both, so that clients of either version can download it.
+    Hybrid,
+}
+
+/// A summary of a torrent, from `stats`.
+#[cfg(feature = "value")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TorrentStats {
+    pub version: TorrentVersion,
+    /// The size of the real content, leaving padding files out.
+    pub total_size: u64,
+    /// The number of files, leaving padding files out.
+    pub file_count: usize,
+    pub piece_length: u64,
+    /// The number of v1 piece hashes in `pieces`. 0 for a v2-only torrent.
+    pub piece_count: usize,
+    /// The number of different trackers, compared as `normalize_tracker_url` writes them.
+    pub tracker_count: usize,
+}
+
+/// Summarizes a whole torrent: its version, size, files, pieces and trackers.
+///
+/// Sizes and files come from the v1 `length` or `files` when the torrent has them, and from the
+/// v2 `file tree` otherwise.
+///
+/// ```
+/// use beeenn::torrent::{stats, TorrentVersion};
+/// use beeenn::BESliceReader;
+///
+/// let torrent = b"d8:announce10:udp://t:804:infod6:lengthi40000e4:name1:a\
+///                 12:piece lengthi32768e6:pieces40:\
+///                 0123456789012345678901234567890123456789ee";
+/// let torrent = BESliceReader::new(torrent).next_value().unwrap().unwrap();
+/// let stats = stats(&torrent).unwrap();
+/// assert_eq!(TorrentVersion::V1, stats.version);
+/// assert_eq!((40000, 1, 2, 1), (stats.total_size, stats.file_count, stats.piece_count, stats.tracker_count));
+/// ```
+#[cfg(feature = "value")]
+pub fn stats(torrent: &BEValue) -> Result<TorrentStats, TorrentError> {
+    let info = torrent.get_dict("info")?;
+    let v2 = match info.get("meta version") {
+        None => false,
+        Some(_) => match info.get_int("meta version")? {
+            1 => false,
+            2 => true,
+            version => return Err(TorrentError::UnsupportedMetaVersion(version)),
+        },
+    };
+    let v1 = info.get("pieces").is_some();
+    let version = match (v1, v2) {
+        (true, true) => TorrentVersion::Hybrid,
+        (false, true) => TorrentVersion::V2,
+        _ => TorrentVersion::V1,
+    };
+
+    // Walked for hybrid torrents too, so a missing or broken `file tree` is always an error.
+    let tree = if v2 {
+        file_tree_size(info.get_dict("file tree")?, "info.file tree")?
+    } else {
+        (0, 0)
+    };
+    let (total_size, file_count) = if version == TorrentVersion::V2 {
+        tree
+    } else {
+        let file_count = match info.get("files") {
+            Some(_) => info
+                .get_list("files")?
+                .iter()
+                .filter(|file| !is_padding(file))
+                .count(),
+            None => 1,
+        };
+        (content_length(info)?, file_count)
+    };
+    let piece_length = info.get_int("piece length")?;
+    let piece_length = u64::try_from(piece_length)
+        .map_err(|_| TorrentError::PieceLengthNotPowerOfTwo(piece_length))?;
+    let piece_count = if v1 {
+        info.get_bytes("pieces")?.len() / HASH_LEN
+    } else {
+        0
+    };
+    let tracker_count = trackers(torrent)
+        .into_iter()
+        .map(|url| normalize_tracker_url(url).unwrap_or_else(|| url.trim().to_string()))
+        .collect::<HashSet<_>>()
+        .len();
+
+    Ok(TorrentStats {
+        version,
+        total_size,
+        file_count,
+        piece_length,
+        piece_count,
+        tracker_count,
+    })
+}
+
+// The total size and number of files in a v2 `file tree` node. A file is a dict with an empty
+// key holding its `length`; anything else is a directory.
+#[cfg(feature = "value")]
+fn file_tree_size(node: &BEValue, path: &str) -> Result<(u64, usize), Error> {
+    if let Some(file) = node.get("") {
+        return Ok((length(file, format!("{}.length", path))?, 1));
+    }
+    let entries = match node {
+        BEValue::BEDict(entries) => entries.as_slice(),
+        _ => return Err(Error::WrongType(path.to_string(), "dict")),
+    };
+    let mut total = (0u64, 0);
+    for (name, child) in entries {
+        let path = format!("{}.{}", path, String::from_utf8_lossy(name));
+        let (size, files) = file_tree_size(child, &path)?;
+        total.0 = total.0.checked_add(size).ok_or(Error::IntegerOverflow)?;
+        total.1 += files;
+    }
+    Ok(total)
+}
+
+// The keys of a file's path in a v1 `files` list. `path.utf-8` is a common extension.
+#[cfg(feature = "value")]
+const PATH_KEYS: [&str; 2] = ["path", "path.utf-8"];
+
+/// Sets the name of a torrent's content: the file name of a single-file torrent, or the
+/// directory holding the files of a multi-file one. `name.utf-8` is set too if `info` has it.
+///
+/// `name` must be a single, non-empty path component. Any change to `info` changes the
+/// infohash, so trackers and peers see the result as a different torrent. Use `edit_info` to
+/// apply this to an encoded torrent.
+///
+/// ```
+/// use beeenn::torrent::rename;
+/// use beeenn::BESliceReader;
+///
+/// let info = b"d6:lengthi7e4:name3:old10:name.utf-83:olde";
+/// let mut info = BESliceReader::new(info).next_value().unwrap().unwrap();
+/// rename(&mut info, "new").unwrap();
+/// assert_eq!(Ok("new"), info.get_str("name"));
+/// assert_eq!(Ok("new"), info.get_str("name.utf-8"));
+/// ```
+#[cfg(feature = "value")]
+pub fn rename(info: &mut BEValue, name: &str) -> Result<(), Error> {
+    check_component(name, "name")?;
+    let dict = match info {
+        BEValue::BEDict(dict) => dict,
+        _ => return Err(Error::WrongType(String::new(), "dict")),
+    };
+    if dict.iter().any(|(key, _)| key.eq_str("name.utf-8")) {
+        info.insert("name.utf-8", name);
+    }
+    info.insert("name", name);
+    Ok(())
+}
+
+/// Moves files in a multi-file info dict from the directory `from` to the directory `to`, each
+/// given as path components, and returns how many files moved.
+///
+/// Every `path` (and `path.utf-8`) in `files` that starts with `from` has those components
+/// replaced by `to`. An empty `from` moves every file under `to`, and an empty `to` moves files
+/// up to the top level. Nothing changes if any file would be left with an empty path. A
+/// single-file info dict has no directories, so it is left as it is. v2 `file tree`s aren't
+/// rewritten, so a torrent with one is refused rather than left inconsistent.
+///
+/// As with `rename`, the infohash changes.
+///
+/// ```
+/// use beeenn::torrent::rebase;
+/// use beeenn::BESliceReader;
+///
+/// let info = b"d5:filesld6:lengthi1e4:pathl2:CD1:aeed6:lengthi2e4:pathl1:beee4:name1:xe";
+/// let mut info = BESliceReader::new(info).next_value().unwrap().unwrap();
+/// assert_eq!(Ok(1), rebase(&mut info, &["CD"], &["Disc 1"]));
+/// assert_eq!(Some(&"Disc 1".into()), info.get_path("files.0.path.0"));
+/// ```
+#[cfg(feature = "value")]
+pub fn rebase(info: &mut BEValue, from: &[&str], to: &[&str]) -> Result<usize, Error> {
+    for component in from.iter().chain(to) {
+        check_component(component, "")?;
+    }
+    if info.get("file tree").is_some() {
+        return Err(Error::InvalidValue {
+            unexpected: "a v2 file tree".to_string(),
+            expected: "only a v1 files list".to_string(),
+            path: "file tree".to_string(),
+        });
+    }
+    if info.get("files").is_none() {
+        return Ok(0);
+    }
+    info.get_list("files")?;
+
+    let starts_with_from = |path: &[BEValue]| {
+        path.len() >= from.len()
+            && path
+                .iter()
+                .zip(from)
+                .all(|(component, from)| match component {
+                    BEValue::BEString(component) => component.eq_str(from),
+                    _ => false,
+                })
+    };
+    let files = match info {
+        BEValue::BEDict(dict) => dict.iter_mut().find(|(key, _)| key.eq_str("files")),
+        _ => None,
+    };
+    let files = match files {
+        Some((_, BEValue::BEList(files))) => files,
+        _ => unreachable!(),
+    };
+
+    // Checked before anything changes, so an error leaves `info` as it was.
+    if to.is_empty() {
+        for (i, file) in files.iter().enumerate() {
+            for key in PATH_KEYS.iter() {
+                if let Some(BEValue::BEList(path)) = file.get(key) {
+                    if path.len() == from.len() && starts_with_from(path) {
+                        return Err(Error::InvalidLength {
+                            len: 0,
+                            expected: "a non-empty path".to_string(),
+                            path: format!("files.{}.{}", i, key),
+                        });
+                    }
+                }
+            }
+        }
+    }
+
+    let mut moved = 0;
+    for file in files.iter_mut() {
+        let mut changed = false;
+        if let BEValue::BEDict(entries) = file {
+            for (key, path) in entries.iter_mut() {
+                if let BEValue::BEList(path) = path {
+                    if PATH_KEYS.iter().any(|k| key.eq_str(k)) && starts_with_from(path) {
+                        path.splice(..from.len(), to.iter().map(|c| BEValue::from(*c)));
+                        changed = true;
+                    }
+                }
+            }
+        }
+        if changed {
+            moved += 1;
+        }
+    }
+    Ok(moved)
+}
+
+// Rejects a name that isn't a single path component. `path` names it in the error.
+#[cfg(feature = "value")]
+fn check_component(name: &str, path: &str) -> Result<(), Error> {
+    if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+        return Err(Error::InvalidValue {
+            unexpected: format!("'{}'", name),
+            expected: "a single file or directory name".to_string(),
+            path: path.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// Applies `edit` to the `info` dict of an encoded torrent and returns the new torrent.
+///
+/// Every byte outside `info` is kept exactly as it was, and `info` is encoded again with sorted
+/// keys. The new `info` has a new infohash, and any `signatures` over the old one no longer
+/// verify.
+///
+/// ```
+/// use beeenn::torrent::{edit_info, rename};
+///
+/// let torrent = b"d8:announce3:url4:infod6:lengthi7e4:name3:oldee";
+/// let renamed = edit_info(torrent, |info| rename(info, "new")).unwrap();
+/// assert_eq!(&b"d8:announce3:url4:infod6:lengthi7e4:name3:newee"[..], &renamed[..]);
+/// ```
+#[cfg(feature = "value")]
+pub fn edit_info<F>(torrent: &[u8], edit: F) -> Result<Vec<u8>, Error>
+where
+    F: FnOnce(&mut BEValue) -> Result<(), Error>,
+{
+    let value = BESliceReader::new(torrent)
+        .next_value()?
+        .ok_or(Error::Eof)?;
+    let mut info = value.get_dict("info")?.clone();
+    edit(&mut info)?;
+    replace_value(torrent, "info", &info.encode(KeyOrder::Sort)?)
+}
+
+/// One signer's entry in a torrent's `signatures` dict (BEP 35).
+#[cfg(feature = "value")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Signature {
+    /// The signer's X.509 certificate, DER encoded, if the torrent carries it.
+    pub certificate: Option<Vec<u8>>,
+    /// Extra data the signer signed along with the torrent's `info`.
+    pub info: Option<BEValue>,
+    /// The signature bytes.
+    pub signature: Vec<u8>,
+}
+
+/// The signatures of a torrent (BEP 35), by signer name.
+///
+/// A torrent without `signatures` has none. Signer names that aren't UTF-8 are converted
+/// lossily. Use `verify_signatures` to check them.
+///
+/// ```
+/// use beeenn::torrent::signatures;
+/// use beeenn::BESliceReader;
+///
+/// let torrent = b"d4:infod4:name1:xe10:signaturesd5:a.orgd9:signature3:sigeee";
+/// let torrent = BESliceReader::new(torrent).next_value().unwrap().unwrap();
+/// let signatures = signatures(&torrent).unwrap();
+/// assert_eq!(b"sig", &signatures["a.org"].signature[..]);
+/// assert_eq!(None, signatures["a.org"].certificate);
+/// ```
+#[cfg(feature = "value")]
+pub fn signatures(torrent: &BEValue) -> Result<BTreeMap<String, Signature>, Error> {
+    let entries = match torrent.get("signatures") {
+        None => return Ok(BTreeMap::new()),
+        Some(BEValue::BEDict(entries)) => entries,
+        Some(_) => return Err(Error::WrongType("signatures".to_string(), "dict")),
+    };
+    entries
+        .iter()
+        .map(|(name, entry)| {
+            let name = String::from_utf8_lossy(name).into_owned();
+            let signature = signature(entry, &format!("signatures.{}", name))?;
+            Ok((name, signature))
+        })
+        .collect()
+}
+
+// One entry of a `signatures` dict. `path` names it in the error.
+#[cfg(feature = "value")]
+fn signature(entry: &BEValue, path: &str) -> Result<Signature, Error> {
+    if !matches!(entry, BEValue::BEDict(_)) {
+        return Err(Error::WrongType(path.to_string(), "dict"));
+    }
+    let bytes = |key: &str| match entry.get(key) {
+        None => Ok(None),
+        Some(BEValue::BEString(bytes)) => Ok(Some(bytes.as_bytes().to_vec())),
+        Some(_) => Err(Error::WrongType(format!("{}.{}", path, key), "string")),
+    };
+    let info = match entry.get("info") {
+        None => None,
+        Some(info @ BEValue::BEDict(_)) => Some(info.clone()),
+        Some(_) => return Err(Error::WrongType(format!("{}.info", path), "dict")),
+    };
+    Ok(Signature {
+        certificate: bytes("certificate")?,
+        info,
+        signature: bytes("signature")?
+            .ok_or_else(|| Error::MissingKey(format!("{}.signature", path)))?,
+    })
+}
+
+/// Checks each signature of an encoded torrent with `verify`, and returns whether each one is
+/// valid, by signer name.
+///
+/// The crate does no cryptography itself. `verify` is called with the signer's name, the bytes
+/// the signature covers, and the parsed entry, and returns whether the signature is good. The
+/// signed bytes are the torrent's `info` dict exactly as it appears in `torrent`, followed by the
+/// entry's own `info` dict, if it has one, also as it appears. Encoding the parsed values again
+/// could give different bytes.
+///
+/// ```
+/// use beeenn::torrent::verify_signatures;
+///
+/// let torrent = b"d4:infod4:name1:xe10:signaturesd5:a.orgd9:signature3:sigeee";
+/// let verified = verify_signatures(torrent, |name, signed, signature| {
+///     assert_eq!(("a.org", &b"d4:name1:xe"[..]), (name, signed));
+///     signature.signature == b"sig" // A real check would use the certificate.
+/// })
+/// .unwrap();
+/// assert_eq!(Some(&true), verified.get("a.org"));
+/// ```
+#[cfg(feature = "value")]
+pub fn verify_signatures<F>(torrent: &[u8], mut verify: F) -> Result<BTreeMap<String, bool>, Error>
+where
+    F: FnMut(&str, &[u8], &Signature) -> bool,
+{
+    let spanned = BESliceReader::new(torrent)
+        .next_spanned()?
+        .ok_or(Error::Eof)?;
+    let info = spanned
+        .span_of("info")
+        .ok_or_else(|| Error::MissingKey("info".to_string()))?;
+    let signatures = signatures(&spanned.value)?;
+    let (entries, entry_spans) = match spanned.get_path("signatures") {
+        Some((BEValue::BEDict(entries), spans)) => (entries, &spans.children),
+        _ => return Ok(BTreeMap::new()),
+    };
+
+    let mut verified = BTreeMap::new();
+    for ((name, entry), spans) in entries.iter().zip(entry_spans) {
+        let mut signed = torrent[info.clone()].to_vec();
+        if let BEValue::BEDict(fields) = entry {
+            if let Some(i) = fields.iter().position(|(key, _)| key.eq_str("info")) {
+                signed.extend_from_slice(&torrent[spans.children[i].span.clone()]);
+            }
+        }
+        let name = String::from_utf8_lossy(name).into_owned();
+        let valid = verify(&name, &signed, &signatures[&name]);
+        verified.insert(name, valid);
+    }
+    Ok(verified)
+}
+
+// The longest `comment` `lint` accepts without a warning. Clients show it in a single field.
+#[cfg(feature = "value")]
+const MAX_COMMENT_LEN: usize = 1024;
+
+/// How serious a `Finding` is.
+#[cfg(feature = "value")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity {
+    /// The torrent works, but something about it is unusual or unhelpful.
+    Warning,
+    /// Clients will reject the torrent, or disagree about its infohash.
+    Error,
+}
+
+#[cfg(feature = "value")]
+impl fmt::Display for Severity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        })
+    }
+}
+
+/// One problem `lint` found in a torrent.
+#[cfg(feature = "value")]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    /// A short, stable name for the check, like `duplicate-tracker`, for filtering findings.
+    pub code: &'static str,
+    pub severity: Severity,
+    /// What was found, for people.
+    pub message: String,
+}
+
+#[cfg(feature = "value")]
+impl Finding {
+    fn new(code: &'static str, severity: Severity, message: String) -> Self {
+        Finding {
+            code,
+            severity,
+            message,
+        }
+    }
+}
+
+#[cfg(feature = "value")]
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} {}: {}", self.severity, self.code, self.message)
+    }
+}
+
+/// Runs every check in this module over a decoded torrent, and returns what they found, in
+/// order. An empty result means the torrent passed.
+///
+/// Findings with `Severity::Error` mean clients will refuse the torrent:
+///
+/// - `missing-info`: there is no `info` dict.
+/// - `invalid-pieces`: `piece length`, the total size and `pieces` disagree (see
+///   `validate_piece_length`).
+/// - `unsorted-key`, `duplicate-key` and `invalid-value` inside `info` (see
+///   `BEValue::canonical_violations`), which make the infohash ambiguous.
+///
+/// The rest are warnings:
+///
+/// - `unusual-piece-length`: a piece length outside 16 KiB to 16 MiB.
+/// - `unsorted-key`, `duplicate-key` and `invalid-value` outside `info`.
+/// - `invalid-tracker`: a tracker URL `normalize_tracker_url` can't make sense of.
+/// - `duplicate-tracker`: a tracker listed more than once, after normalizing. `announce` is only
+///   counted when there is no `announce-list`, since clients ignore it then.
+/// - `missing-creation-date`: no `creation date`.
+/// - `long-comment`: a `comment` longer than 1024 bytes.
+/// - `non-utf8-name`: a `name` that isn't UTF-8, with no `encoding` to say what it is.
+///
+/// The torrent must have been read without rejecting unsorted keys (with `recover`, for example)
+/// for the key checks to find anything.
+///
+/// ```
+/// use beeenn::torrent::{lint, Severity};
+/// use beeenn::BESliceReader;
+///
+/// let torrent = b"d13:announce-listll13:http://t.testel16:http://T.test:80ee\
+///                 4:infod6:lengthi7e4:name1:x12:piece lengthi16384e6:pieces20:\
+///                 01234567890123456789ee";
+/// let torrent = BESliceReader::new(torrent).next_value().unwrap().unwrap();
+/// let findings = lint(&torrent);
+/// let codes: Vec<_> = findings.iter().map(|finding| finding.code).collect();
+/// assert_eq!(vec!["duplicate-tracker", "missing-creation-date"], codes);
+/// assert!(findings.iter().all(|finding| finding.severity == Severity::Warning));
+/// ```
+#[cfg(feature = "value")]
+pub fn lint(torrent: &BEValue) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    let mut find = |code, severity, message| findings.push(Finding::new(code, severity, message));
+
+    match torrent.get_dict("info") {
+        Err(err) => find("missing-info", Severity::Error, err.to_string()),
+        Ok(info) => {
+            // A v2-only torrent has no `pieces`, so only its piece length can be checked.
+            let v2_only = info.get("pieces").is_none()
+                && matches!(info.get("meta version"), Some(BEValue::BEInteger(2)));
+            let pieces = if v2_only {
+                match info.get_int("piece length") {
+                    Ok(length) if length > 0 && (length as u64).count_ones() == 1 => Ok(()),
+                    Ok(length) => Err(TorrentError::PieceLengthNotPowerOfTwo(length)),
+                    Err(err) => Err(err.into()),
+                }
+            } else {
+                validate_piece_length(info)
+            };
+            if let Err(err) = &pieces {
+                find("invalid-pieces", Severity::Error, err.to_string());
+            }
+            // A length that isn't a positive power of two was reported above.
+            if let (Ok(()), Ok(length)) = (&pieces, info.get_int("piece length")) {
+                let length = length as u64;
+                if !(MIN_PIECE_LENGTH..=MAX_PIECE_LENGTH).contains(&length) {
+                    find(
+                        "unusual-piece-length",
+                        Severity::Warning,
+                        format!("piece length {} is outside 16 KiB to 16 MiB", length),
+                    );
+                }
+            }
+        }
+    }
+
+    for violation in torrent.canonical_violations() {
+        let (code, path, message) = match &violation {
+            CanonicalViolation::UnsortedKey(path) => ("unsorted-key", path, "is out of order"),
+            CanonicalViolation::DuplicateKey(path) => ("duplicate-key", path, "is repeated"),
+            CanonicalViolation::InvalidValue(path) => ("invalid-value", path, "is not bencode"),
+        };
+        let severity = if path == "info" || path.starts_with("info.") {
+            Severity::Error
+        } else {
+            Severity::Warning
+        };
+        find(code, severity, format!("'{}' {}", path, message));
+    }
+
+    // Clients ignore `announce` when there is an `announce-list` (BEP 12), which usually repeats
+    // it, so it isn't counted towards duplicates then.
+    let urls = trackers(torrent);
+    let announce = match torrent.get("announce") {
+        Some(BEValue::BEString(url)) => url.as_str().is_some(),
+        _ => false,
+    };
+    let ignored = if announce && urls.len() > 1 { 1 } else { 0 };
+    let mut seen = HashSet::new();
+    for (i, url) in urls.into_iter().enumerate() {
+        match normalize_tracker_url(url) {
+            None => find(
+                "invalid-tracker",
+                Severity::Warning,
+                format!("'{}' is not a tracker URL", url),
+            ),
+            Some(normalized) => {
+                if i >= ignored && !seen.insert(normalized) {
+                    find(
+                        "duplicate-tracker",
+                        Severity::Warning,
+                        format!("'{}' is listed more than once", url),
+                    );
+                }
+            }
+        }
+    }
+
+    if torrent.get("creation date").is_none() {
+        find(
+            "missing-creation-date",
+            Severity::Warning,
+            "there is no creation date".to_string(),
+        );
+    }
+    if let Some(BEValue::BEString(comment)) = torrent.get("comment") {
+        if comment.len() > MAX_COMMENT_LEN {
+            find(
+                "long-comment",
+                Severity::Warning,
+                format!(
+                    "comment is {} bytes, more than {}",
+                    comment.len(),
+                    MAX_COMMENT_LEN
+                ),
+            );
+        }
+    }
+    if let Some(BEValue::BEString(name)) = torrent.get_path("info.name") {
+        if name.as_str().is_none() && torrent.get("encoding").is_none() {
+            find(
+                "non-utf8-name",
+                Severity::Warning,
+                "name is not UTF-8, and there is no encoding".to_string(),
+            );
+        }
+    }
+    findings
+}
+
+const SIZE_UNITS: [&str; 7] = ["B", "KiB", "MiB", "GiB", "TiB", "PiB", "EiB"];
+
+/// Formats a size in bytes for people, in binary units with one decimal place.
+///
+/// ```
+/// use beeenn::torrent::format_size;
+///
+/// assert_eq!("117.7 MiB", format_size(123_456_789));
+/// assert_eq!("512 B", format_size(512));
+/// ```
+pub fn format_size(bytes: u64) -> String {
+    let mut unit = 0;
+    let mut scaled = bytes as f64;
+    // Anything that would round up to 1024.0 moves to the next unit.
+    while scaled >= 1023.95 && unit < SIZE_UNITS.len() - 1 {
+        scaled /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", scaled, SIZE_UNITS[unit])
+    }
+}
+
+/// Formats a piece length, which is almost always a power of two, without a decimal place when
+/// it's a whole number of units: "256 KiB" rather than "256.0 KiB". Other sizes are formatted
+/// as `format_size` does.
+///
+/// ```
+/// use beeenn::torrent::format_piece_size;
+///
+/// assert_eq!("256 KiB", format_piece_size(262_144));
+/// assert_eq!("1.5 MiB", format_piece_size(1_572_864));
+/// ```
+pub fn format_piece_size(bytes: u64) -> String {
+    let mut unit = 0;
+    let mut scaled = bytes;
+    while scaled >= 1024 && scaled % 1024 == 0 && unit < SIZE_UNITS.len() - 1 {
+        scaled /= 1024;
+        unit += 1;
+    }
+    if scaled < 1024 {
+        format!("{} {}", scaled, SIZE_UNITS[unit])
+    } else {
+        format_size(bytes)
+    }
+}
+
+/// The BEP 47 `attr` flags of a file in a torrent: one letter for each flag that is set.
+///
+/// Letters this crate doesn't know are kept, so a torrent's attributes can be read and written
+/// back without losing any.
+///
+/// ```
+/// use beeenn::torrent::FileAttributes;
+///
+/// let attr = FileAttributes::parse("xhq");
+/// assert!(attr.executable && attr.hidden && !attr.padding);
+/// assert_eq!("q", attr.unknown);
+/// assert_eq!("hxq", attr.to_string());
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FileAttributes {
+    /// `p`: the file only pads the previous file out to a piece boundary.
+    pub padding: bool,
+    /// `x`
+    pub executable: bool,
+    /// `h`
+    pub hidden: bool,
+    /// `l`: the file is a symlink, to the file's `symlink path`.
+    pub symlink: bool,
+    /// Any other letters, in the order they appeared.
+    pub unknown: String,
+}
+
+impl FileAttributes {
+    pub fn parse(attr: &str) -> Self {
+        let mut attributes = FileAttributes::default();
+        for c in attr.chars() {
+            match c {
+                'p' => attributes.padding = true,
+                'x' => attributes.executable = true,
+                'h' => attributes.hidden = true,
+                'l' => attributes.symlink = true,
+                c => attributes.unknown.push(c),
+            }
+        }
+        attributes
+    }
+
+    /// The attributes of `file`, an entry of an info dict's `files`. A missing `attr` has no
+    /// flags set, and one that isn't valid UTF-8 is read lossily.
+    #[cfg(feature = "value")]
+    pub fn of(file: &BEValue) -> Self {
+        match file.get("attr") {
+            Some(BEValue::BEString(attr)) => Self::parse(&String::from_utf8_lossy(attr)),
+            _ => FileAttributes::default(),
+        }
+    }
+}
+
+/// Writes the `attr` string: the known flags in the order `p`, `h`, `x`, `l`, then any unknown
+/// letters.
+impl fmt::Display for FileAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let flags = [
+            (self.padding, 'p'),
+            (self.hidden, 'h'),
+            (self.executable, 'x'),
+            (self.symlink, 'l'),
+        ];
+        for (set, letter) in flags.iter() {
+            if *set {
+                write!(f, "{}", letter)?;
+            }
+        }
+        f.write_str(&self.unknown)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    #[cfg(feature = "value")]
+    use crate::reader::BESliceReader;
+
+    #[test]
+    fn test_scrape_url() {
+        let cases = [
+            ("http://t.example/announce", Some("http://t.example/scrape")),
+            (
+                "https://t.example:443/a/announce",
+                Some("https://t.example:443/a/scrape"),
+            ),
+            (
+                "http://t.example/announce?x=/y",
+                Some("http://t.example/scrape?x=/y"),
+            ),
+            (
+                "udp://t.example:1337/announce",
+                Some("udp://t.example:1337/scrape"),
+            ),
+            (
+                "http://t.example/announcer",
+                Some("http://t.example/scraper"),
+            ),
+            ("http://t.example/x/announce/y", None),
+            ("http://t.example/a?announce", None),
+            ("http://t.example", None),
+            ("http://announce.example", None),
+            ("http://announce.example?a=/announce", None),
+            ("t.example/announce", Some("t.example/scrape")),
+            ("", None),
+        ];
+        for (announce, expected) in cases.iter().copied() {
+            assert_eq!(
+                expected.map(String::from),
+                scrape_url(announce),
+                "{}",
+                announce
+            );
+        }
+    }
+
+    #[test]
+    fn test_tracker_protocol() {
+        assert_eq!(
+            Some(TrackerProtocol::Http),
+            tracker_protocol("http://t.example/announce")
+        );
+        assert_eq!(
+            Some(TrackerProtocol::Https),
+            tracker_protocol(" HTTPS://t.example/announce")
+        );
+        assert_eq!(
+            Some(TrackerProtocol::WebSocket),
+            tracker_protocol("wss://t.example")
+        );
+        assert_eq!(None, tracker_protocol("ws://t.example"));
+        assert_eq!(None, tracker_protocol("t.example/announce"));
+        assert_eq!(None, tracker_protocol(""));
+    }
+
+    #[test]
+    fn test_normalize_tracker_url() {
+        let cases = [
+            (
+                "http://t.example/announce",
+                Some("http://t.example/announce"),
+            ),
+            (
+                "\tHTTP://T.EXAMPLE:80/Announce?Key=AB#top\n",
+                Some("http://t.example/Announce?Key=AB"),
+            ),
+            ("https://t.example:443", Some("https://t.example")),
+            ("https://t.example:8443/a", Some("https://t.example:8443/a")),
+            ("http://t.example:/a", Some("http://t.example/a")),
+            (
+                "udp://t.example:1337/announce",
+                Some("udp://t.example:1337/announce"),
+            ),
+            ("wss://T.example:443/", Some("wss://t.example/")),
+            (
+                "http://[2001:DB8::1]:80/announce",
+                Some("http://[2001:db8::1]/announce"),
+            ),
+            ("http://[2001:db8::1]/a", Some("http://[2001:db8::1]/a")),
+            ("http://User@t.example/a", Some("http://User@t.example/a")),
+            ("http://t.example?x=:1", Some("http://t.example?x=:1")),
+            ("udp://t.example/announce", None),
+            ("http://t.example:http/a", None),
+            ("http://t.example:99999/a", None),
+            ("http:///announce", None),
+            ("http://:80/announce", None),
+            ("ftp://t.example/announce", None),
+            ("t.example/announce", None),
+            ("", None),
+        ];
+        for (url, expected) in cases.iter().copied() {
+            assert_eq!(
+                expected.map(String::from),
+                normalize_tracker_url(url),
+                "{}",
+                url
+            );
+        }
+    }
+
+    #[test]
+    fn test_recommend_piece_length() {
+        assert_eq!(16 << 10, recommend_piece_length(0));
+        assert_eq!(16 << 10, recommend_piece_length(2000 * (16 << 10)));
+        assert_eq!(32 << 10, recommend_piece_length(2000 * (16 << 10) + 1));
+        assert_eq!(1 << 20, recommend_piece_length(1 << 30));
+        assert_eq!(16 << 20, recommend_piece_length(100 << 30));
+        assert_eq!(16 << 20, recommend_piece_length(u64::MAX));
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_validate_piece_length() {
+        use crate::encode::KeyOrder;
+
+        let info = |length: i64, piece_length: i64, pieces: usize| {
+            let mut info = BEValue::BEDict(Vec::new());
+            info.insert("length", length);
+            info.insert("piece length", piece_length);
+            info.insert("pieces", vec![0u8; pieces]);
+            info
+        };
+        assert_eq!(Ok(()), validate_piece_length(&info(16384, 16384, 20)));
+        assert_eq!(Ok(()), validate_piece_length(&info(16385, 16384, 40)));
+        assert_eq!(Ok(()), validate_piece_length(&info(0, 16384, 0)));
+        assert_eq!(
+            Err(TorrentError::PieceCountMismatch {
+                expected: 2,
+                found: 1
+            }),
+            validate_piece_length(&info(16385, 16384, 20))
+        );
+        assert_eq!(
+            Err(TorrentError::PieceLengthNotPowerOfTwo(10000)),
+            validate_piece_length(&info(16384, 10000, 40))
+        );
+        assert_eq!(
+            Err(TorrentError::PieceLengthNotPowerOfTwo(0)),
+            validate_piece_length(&info(16384, 0, 40))
+        );
+        assert_eq!(
+            Err(TorrentError::PiecesNotHashes(21)),
+            validate_piece_length(&info(16384, 16384, 21))
+        );
+        assert!(matches!(
+            validate_piece_length(&info(-1, 16384, 20)),
+            Err(TorrentError::Value(Error::IntegerOutOfRange {
+                value: -1,
+                ..
+            }))
+        ));
+
+        // Multi-file torrents add up their files.
+        let input = b"d5:filesld6:lengthi10000eed6:lengthi10000eee\
+                      12:piece lengthi16384e6:pieces40:\
+                      0123456789012345678901234567890123456789e";
+        let multi = BESliceReader::new(input).next_value().unwrap().unwrap();
+        assert_eq!(&input[..], &multi.encode(KeyOrder::Require).unwrap()[..]);
+        assert_eq!(Ok(()), validate_piece_length(&multi));
+
+        let missing = BESliceReader::new(b"d5:filesldee12:piece lengthi16384e6:pieces0:e")
+            .next_value()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            Err(TorrentError::Value(Error::MissingKey("length".to_string()))),
+            validate_piece_length(&missing)
+        );
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_padding() {
+        let value = |bytes: &[u8]| BESliceReader::new(bytes).next_value().unwrap().unwrap();
+        assert!(is_padding(&value(b"d4:attr1:p6:lengthi6ee")));
+        assert!(is_padding(&value(b"d4:attr2:xpe")));
+        assert!(!is_padding(&value(b"d4:attr1:x6:lengthi6ee")));
+        assert!(!is_padding(&value(b"d6:lengthi6ee")));
+
+        // Padding counts toward the pieces, but not the content.
+        let info = value(
+            b"d5:filesld6:lengthi16000eed4:attr1:p6:lengthi384eed6:lengthi1eee\
+              12:piece lengthi16384e6:pieces40:\
+              0123456789012345678901234567890123456789e",
+        );
+        assert_eq!(Ok(16001), content_length(&info));
+        assert_eq!(Ok(()), validate_piece_length(&info));
+        assert_eq!(Ok(7), content_length(&value(b"d6:lengthi7ee")));
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_trackers() {
+        let value = |bytes: &[u8]| BESliceReader::new(bytes).next_value().unwrap().unwrap();
+        assert!(trackers(&value(b"de")).is_empty());
+        assert_eq!(
+            vec!["a", "b"],
+            trackers(&value(b"d13:announce-listl1:xl1:ai1e1:\xffel1:beee"))
+        );
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_nodes() {
+        let value = |bytes: &[u8]| BESliceReader::new(bytes).next_value().unwrap().unwrap();
+        assert!(nodes(&value(b"de")).is_empty());
+        assert!(nodes(&value(b"d5:nodes3:abce")).is_empty());
+
+        // The same list `test_tuples` decodes with serde, plus the malformed entries serde rejects.
+        let torrent = value(
+            b"d5:nodesl\
+              l1:ai1ee\
+              l1:b2:22e\
+              i3e\
+              l1:ai1ei2ee\
+              li1ei1ee\
+              l0:i1ee\
+              l1:\xffi1ee\
+              l1:ai70000ee\
+              l1:ai-1ee\
+              l1:a2:xxe\
+              l1:al1:aee\
+              ee",
+        );
+        assert_eq!(
+            vec![
+                Ok(("a".to_string(), 1)),
+                Ok(("b".to_string(), 22)),
+                Err(Error::WrongType("nodes.2".to_string(), "[host, port] pair")),
+                Err(Error::WrongType("nodes.3".to_string(), "[host, port] pair")),
+                Err(Error::WrongType("nodes.4.0".to_string(), "host name")),
+                Err(Error::WrongType("nodes.5.0".to_string(), "host name")),
+                Err(Error::WrongType("nodes.6.0".to_string(), "host name")),
+                Err(Error::IntegerOutOfRange {
+                    value: 70000,
+                    target: "u16",
+                    path: "nodes.7.1".to_string()
+                }),
+                Err(Error::IntegerOutOfRange {
+                    value: -1,
+                    target: "u16",
+                    path: "nodes.8.1".to_string()
+                }),
+                Err(Error::WrongType("nodes.9.1".to_string(), "port number")),
+                Err(Error::WrongType("nodes.10.1".to_string(), "port number")),
+            ],
+            nodes(&torrent)
+        );
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_rename() {
+        let value = |bytes: &[u8]| BESliceReader::new(bytes).next_value().unwrap().unwrap();
+        let mut info = value(b"d6:lengthi7e4:name3:olde");
+        rename(&mut info, "new").unwrap();
+        assert_eq!(value(b"d6:lengthi7e4:name3:newe"), info);
+
+        for name in &["", ".", "..", "a/b"] {
+            assert!(matches!(
+                rename(&mut info, name),
+                Err(Error::InvalidValue { path, .. }) if path == "name"
+            ));
+        }
+        assert_eq!(
+            Err(Error::WrongType(String::new(), "dict")),
+            rename(&mut BEValue::BEInteger(1), "new")
+        );
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_rebase() {
+        let value = |bytes: &[u8]| BESliceReader::new(bytes).next_value().unwrap().unwrap();
+        let files = b"d5:filesl\
+            d6:lengthi1e4:pathl2:CD1:ae10:path.utf-8l2:CD1:aee\
+            d6:lengthi2e4:pathl2:CD1:bee\
+            d6:lengthi3e4:pathl2:CDee\
+            d6:lengthi4e4:pathl5:other2:CDee\
+            d6:lengthi5e4:pathli1eee\
+            e4:name1:xe";
+        let path = |info: &BEValue, i: usize, key: &str| {
+            info.get_path(&format!("files.{}", i))?.get(key).cloned()
+        };
+        let list = |components: &[&str]| {
+            Some(BEValue::BEList(
+                components.iter().map(|c| BEValue::from(*c)).collect(),
+            ))
+        };
+
+        let mut info = value(files);
+        assert_eq!(Ok(3), rebase(&mut info, &["CD"], &["Music", "Disc 1"]));
+        assert_eq!(list(&["Music", "Disc 1", "a"]), path(&info, 0, "path"));
+        assert_eq!(
+            list(&["Music", "Disc 1", "a"]),
+            path(&info, 0, "path.utf-8")
+        );
+        assert_eq!(list(&["Music", "Disc 1", "b"]), path(&info, 1, "path"));
+        assert_eq!(list(&["Music", "Disc 1"]), path(&info, 2, "path"));
+        assert_eq!(list(&["other", "CD"]), path(&info, 3, "path"));
+        assert_eq!(
+            Some(BEValue::BEList(vec![BEValue::BEInteger(1)])),
+            path(&info, 4, "path")
+        );
+
+        // Everything moves under an empty `from`.
+        let mut info = value(files);
+        assert_eq!(Ok(5), rebase(&mut info, &[], &["all"]));
+        assert_eq!(list(&["all", "other", "CD"]), path(&info, 3, "path"));
+
+        // `files.2` would be left with no path, so nothing changes.
+        let mut info = value(files);
+        assert_eq!(
+            Err(Error::InvalidLength {
+                len: 0,
+                expected: "a non-empty path".to_string(),
+                path: "files.2.path".to_string()
+            }),
+            rebase(&mut info, &["CD"], &[])
+        );
+        assert_eq!(value(files), info);
+        assert_eq!(Ok(1), rebase(&mut info, &["other"], &[]));
+        assert_eq!(list(&["CD"]), path(&info, 3, "path"));
+
+        assert!(rebase(&mut info, &["CD"], &["a/b"]).is_err());
+        assert_eq!(Ok(0), rebase(&mut value(b"d6:lengthi7ee"), &["a"], &["b"]));
+        assert!(rebase(&mut value(b"d5:files3:abce"), &["a"], &["b"]).is_err());
+        assert!(matches!(
+            rebase(&mut value(b"d9:file treedee"), &["a"], &["b"]),
+            Err(Error::InvalidValue { .. })
+        ));
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_edit_info() {
+        let torrent = b"d8:announce3:url7:comment0:4:infod6:lengthi7e4:name3:olde1:zli0eee";
+        let renamed = edit_info(torrent, |info| rename(info, "longer")).unwrap();
+        assert_eq!(
+            &b"d8:announce3:url7:comment0:4:infod6:lengthi7e4:name6:longere1:zli0eee"[..],
+            &renamed[..]
+        );
+
+        // `info` is encoded with sorted keys after `edit` runs.
+        let edited = edit_info(torrent, |info| {
+            info.insert("a", 1);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(
+            &b"d8:announce3:url7:comment0:4:infod1:ai1e6:lengthi7e4:name3:olde1:zli0eee"[..],
+            &edited[..]
+        );
+
+        assert_eq!(
+            Err(Error::MissingKey("info".to_string())),
+            edit_info(b"de", |_| Ok(()))
+        );
+        assert_eq!(Err(Error::Eof), edit_info(b"", |_| Ok(())));
+        assert_eq!(
+            Err(Error::TrailingInput),
+            edit_info(b"d4:infodeei1e", |_| Ok(()))
+        );
+        assert!(edit_info(torrent, |info| rename(info, "")).is_err());
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_signatures() {
+        // Stands in for real cryptography: the "signature" is a checksum of the signed bytes.
+        let sign = |signed: &[u8]| {
+            let sum = signed.iter().fold(0u32, |sum, b| {
+                sum.wrapping_mul(31).wrapping_add(u32::from(*b))
+            });
+            sum.to_string().into_bytes()
+        };
+        let string = |bytes: &[u8]| [format!("{}:", bytes.len()).as_bytes(), bytes].concat();
+        let info = &b"d6:lengthi7e4:name3:fooe"[..];
+        let extra = &b"d4:datei1600000000ee"[..];
+        let torrent = [
+            &b"d8:announce3:url4:info"[..],
+            info,
+            b"10:signaturesd",
+            b"5:a.orgd11:certificate4:cert9:signature",
+            &string(&sign(info)),
+            b"e5:b.orgd4:info",
+            extra,
+            b"9:signature",
+            &string(&sign(&[info, extra].concat())),
+            b"e5:c.orgd9:signature3:badee",
+            b"e",
+        ]
+        .concat();
+        let value = |bytes: &[u8]| BESliceReader::new(bytes).next_value().unwrap().unwrap();
+
+        let parsed = signatures(&value(&torrent)).unwrap();
+        assert_eq!(
+            vec!["a.org", "b.org", "c.org"],
+            parsed.keys().collect::<Vec<_>>()
+        );
+        assert_eq!(Some(b"cert".to_vec()), parsed["a.org"].certificate);
+        assert_eq!(None, parsed["a.org"].info);
+        assert_eq!(None, parsed["b.org"].certificate);
+        assert_eq!(Some(value(extra)), parsed["b.org"].info);
+        assert_eq!(b"bad".to_vec(), parsed["c.org"].signature);
+
+        let mut seen = Vec::new();
+        let verified = verify_signatures(&torrent, |name, signed, signature| {
+            seen.push((name.to_string(), signed.to_vec()));
+            signature.signature == sign(signed)
+        })
+        .unwrap();
+        assert_eq!(
+            vec![
+                ("a.org".to_string(), info.to_vec()),
+                ("b.org".to_string(), [info, extra].concat()),
+                ("c.org".to_string(), info.to_vec()),
+            ],
+            seen
+        );
+        let verified: Vec<_> = verified.into_iter().collect();
+        assert_eq!(
+            vec![
+                ("a.org".to_string(), true),
+                ("b.org".to_string(), true),
+                ("c.org".to_string(), false),
+            ],
+            verified
+        );
+
+        assert!(signatures(&value(b"de")).unwrap().is_empty());
+        assert!(verify_signatures(b"d4:infodee", |_, _, _| true)
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            Err(Error::MissingKey("info".to_string())),
+            verify_signatures(b"de", |_, _, _| true)
+        );
+        assert_eq!(
+            Err(Error::WrongType("signatures".to_string(), "dict")),
+            signatures(&value(b"d10:signaturesi1ee"))
+        );
+        assert_eq!(
+            Err(Error::WrongType("signatures.a".to_string(), "dict")),
+            signatures(&value(b"d10:signaturesd1:ai1eee"))
+        );
+        assert_eq!(
+            Err(Error::MissingKey("signatures.a.signature".to_string())),
+            signatures(&value(b"d10:signaturesd1:adeee"))
+        );
+        assert_eq!(
+            Err(Error::WrongType(
+                "signatures.a.signature".to_string(),
+                "string"
+            )),
+            signatures(&value(b"d10:signaturesd1:ad9:signaturei1eeee"))
+        );
+        assert_eq!(
+            Err(Error::WrongType("signatures.a.info".to_string(), "dict")),
+            signatures(&value(b"d10:signaturesd1:ad4:info1:x9:signature1:seee"))
+        );
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_lint() {
+        let codes = |torrent: &BEValue| {
+            lint(torrent)
+                .into_iter()
+                .map(|finding| (finding.code, finding.severity))
+                .collect::<Vec<_>>()
+        };
+        let mut info = BEValue::BEDict(Vec::new());
+        info.insert("length", 40000);
+        info.insert("name", "x");
+        info.insert("piece length", 32768);
+        info.insert("pieces", vec![0u8; 40]);
+        let mut torrent = BEValue::BEDict(Vec::new());
+        torrent.insert("announce", "http://t.test/announce");
+        torrent.insert("creation date", 1_600_000_000);
+        torrent.insert("info", info.clone());
+        assert!(lint(&torrent).is_empty());
+
+        use Severity::*;
+        let mut bad = torrent.clone();
+        bad.insert("announce", "ftp://t.test");
+        bad.insert("comment", vec![b'x'; 1025]);
+        bad.insert("creation date", BEValue::BEInvalid(b"i1".to_vec()));
+        assert_eq!(
+            vec![
+                ("invalid-value", Warning),
+                ("invalid-tracker", Warning),
+                ("long-comment", Warning)
+            ],
+            codes(&bad)
+        );
+        bad.insert("comment", vec![b'x'; 1024]);
+        assert_eq!(
+            vec![("invalid-value", Warning), ("invalid-tracker", Warning)],
+            codes(&bad)
+        );
+
+        // `announce` is ignored for duplicates once there is an `announce-list`.
+        let tier = |urls: &[&str]| {
+            urls.iter()
+                .map(|url| BEValue::from(*url))
+                .collect::<Vec<_>>()
+        };
+        let mut listed = torrent.clone();
+        listed.insert(
+            "announce-list",
+            vec![
+                BEValue::from(tier(&["http://t.test/announce"])),
+                BEValue::from(tier(&["udp://u.test:1", "udp://U.test:1", "x"])),
+            ],
+        );
+        assert_eq!(
+            vec![("duplicate-tracker", Warning), ("invalid-tracker", Warning)],
+            codes(&listed)
+        );
+
+        let mut bad_info = info.clone();
+        bad_info.insert("piece length", 65536);
+        if let BEValue::BEDict(entries) = &mut bad_info {
+            entries.swap(0, 1);
+        }
+        let mut bad = torrent.clone();
+        bad.insert("info", bad_info);
+        if let BEValue::BEDict(entries) = &mut bad {
+            entries.remove(1);
+            let info = entries.remove(1);
+            entries.insert(0, info);
+            entries.push(("comment".into(), "c".into()));
+        }
+        assert_eq!(
+            vec![
+                ("invalid-pieces", Error),
+                ("unsorted-key", Error),
+                ("unsorted-key", Warning),
+                ("missing-creation-date", Warning),
+            ],
+            codes(&bad)
+        );
+
+        let mut small = info.clone();
+        small.insert("piece length", 8192);
+        small.insert("pieces", vec![0u8; 100]);
+        small.insert("name", &b"\xff"[..]);
+        let mut bad = torrent.clone();
+        bad.insert("info", small);
+        assert_eq!(
+            vec![
+                ("unusual-piece-length", Warning),
+                ("non-utf8-name", Warning)
+            ],
+            codes(&bad)
+        );
+        bad.insert("encoding", "latin-1");
+        assert_eq!(vec![("unusual-piece-length", Warning)], codes(&bad));
+
+        let mut v2 = BEValue::BEDict(Vec::new());
+        v2.insert("meta version", 2);
+        v2.insert("piece length", 16384);
+        let mut bad = torrent.clone();
+        bad.insert("info", v2.clone());
+        assert!(lint(&bad).is_empty());
+        v2.insert("piece length", 1000);
+        bad.insert("info", v2);
+        assert_eq!(vec![("invalid-pieces", Error)], codes(&bad));
+
+        assert_eq!(
+            vec![("missing-info", Error), ("missing-creation-date", Warning)],
+            codes(&BEValue::BEDict(Vec::new()))
+        );
+        assert_eq!(
+            "warning missing-creation-date: there is no creation date",
+            lint(&BEValue::BEDict(Vec::new()))[1].to_string()
+        );
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_stats() {
+        let mut info = BEValue::BEDict(Vec::new());
+        let files: Vec<BEValue> = [(10, ""), (6, "p"), (16, "x")]
+            .iter()
+            .map(|(length, attr)| {
+                let mut file = BEValue::BEDict(Vec::new());
+                file.insert("attr", *attr);
+                file.insert("length", *length);
+                file
+            })
+            .collect();
+        info.insert("files", files);
+        info.insert("piece length", 16);
+        info.insert("pieces", vec![0u8; 40]);
+        let mut torrent = BEValue::BEDict(Vec::new());
+        torrent.insert("announce", "http://t.example:80/announce");
+        torrent.insert(
+            "announce-list",
+            vec![
+                BEValue::from(vec![BEValue::from("HTTP://t.example/announce")]),
+                BEValue::from(vec![
+                    BEValue::from("not a url"),
+                    BEValue::from(" not a url"),
+                ]),
+            ],
+        );
+        torrent.insert("info", info.clone());
+        assert_eq!(
+            Ok(TorrentStats {
+                version: TorrentVersion::V1,
+                total_size: 26,
+                file_count: 2,
+                piece_length: 16,
+                piece_count: 2,
+                tracker_count: 2,
+            }),
+            stats(&torrent)
+        );
+
+        info.insert("meta version", 3);
+        torrent.insert("info", info.clone());
+        assert_eq!(
+            Err(TorrentError::UnsupportedMetaVersion(3)),
+            stats(&torrent)
+        );
+        info.insert("meta version", 2);
+        torrent.insert("info", info.clone());
+        assert_eq!(
+            Err(TorrentError::Value(Error::MissingKey(
+                "file tree".to_string()
+            ))),
+            stats(&torrent)
+        );
+        let mut file = BEValue::BEDict(Vec::new());
+        file.insert("length", 32);
+        let mut node = BEValue::BEDict(Vec::new());
+        node.insert("", file);
+        let mut tree = BEValue::BEDict(Vec::new());
+        tree.insert("a", node);
+        info.insert("file tree", tree);
+        torrent.insert("info", info.clone());
+        assert_eq!(TorrentVersion::Hybrid, stats(&torrent).unwrap().version);
+        assert_eq!(26, stats(&torrent).unwrap().total_size);
+        if let BEValue::BEDict(entries) = &mut info {
+            entries.retain(|(key, _)| key != "pieces" && key != "files");
+        }
+        torrent.insert("info", info);
+        let v2 = stats(&torrent).unwrap();
+        assert_eq!(
+            (TorrentVersion::V2, 32, 1, 0),
+            (v2.version, v2.total_size, v2.file_count, v2.piece_count)
+        );
+        assert_eq!(
+            Err(TorrentError::Value(Error::MissingKey("info".to_string()))),
+            stats(&BEValue::BEDict(Vec::new()))
+        );
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!("0 B", format_size(0));
+        assert_eq!("1023 B", format_size(1023));
+        assert_eq!("1.0 KiB", format_size(1024));
+        assert_eq!("1.5 KiB", format_size(1536));
+        assert_eq!("1023.9 KiB", format_size(1023 * 1024 + 900));
+        assert_eq!("1.0 MiB", format_size(1024 * 1024 - 1));
+        assert_eq!("4.0 GiB", format_size(4 << 30));
+        assert_eq!("16.0 EiB", format_size(u64::MAX));
+    }
+
+    #[test]
+    fn test_format_piece_size() {
+        assert_eq!("16 KiB", format_piece_size(16 << 10));
+        assert_eq!("16 MiB", format_piece_size(16 << 20));
+        assert_eq!("1 GiB", format_piece_size(1 << 30));
+        assert_eq!("0 B", format_piece_size(0));
+        assert_eq!("1000 B", format_piece_size(1000));
+        assert_eq!("1.5 KiB", format_piece_size(1536));
+        assert_eq!("3 KiB", format_piece_size(3072));
+        assert_eq!("16.0 EiB", format_piece_size(u64::MAX));
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_is_private() {
+        let private =
+            |bytes: &[u8]| is_private(&BESliceReader::new(bytes).next_value().unwrap().unwrap());
+        assert!(private(b"d4:infod7:privatei1eee"));
+        assert!(!private(b"d4:infod7:privatei0eee"));
+        assert!(!private(b"d4:infod7:private1:1ee"));
+        assert!(!private(b"d4:infode7:privatei1ee"));
+        assert!(!private(b"le"));
+    }
+
+    #[test]
+    fn test_file_attributes() {
+        assert_eq!(FileAttributes::default(), FileAttributes::parse(""));
+        let all = FileAttributes::parse("lxhp");
+        assert_eq!(
+            FileAttributes {
+                padding: true,
+                executable: true,
+                hidden: true,
+                symlink: true,
+                unknown: String::new(),
+            },
+            all
+        );
+        assert_eq!("phxl", all.to_string());
+
+        let unknown = FileAttributes::parse("z\u{e9}pz");
+        assert!(unknown.padding);
+        assert_eq!("z\u{e9}z", unknown.unknown);
+        assert_eq!("pz\u{e9}z", unknown.to_string());
+        assert_eq!(unknown, FileAttributes::parse(&unknown.to_string()));
+    }
+
+    #[cfg(feature = "value")]
+    #[test]
+    fn test_file_attributes_of() {
+        let file = |bytes: &[u8]| BESliceReader::new(bytes).next_value().unwrap().unwrap();
+        assert!(FileAttributes::of(&file(b"d4:attr2:xpe")).padding);
+        assert!(FileAttributes::of(&file(b"d4:attr1:xe")).executable);
+        assert_eq!(
+            FileAttributes::default(),
+            FileAttributes::of(&file(b"d6:lengthi1ee"))
+        );
+        assert_eq!(
+            FileAttributes::default(),
+            FileAttributes::of(&file(b"d4:attri1ee"))
+        );
+        assert_eq!(
+            "\u{fffd}",
+            FileAttributes::of(&file(b"d4:attr1:\xffe")).unknown
+        );
+    }
+}
@@ -0,0 +1,559 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt;
+use std::mem;
+use std::ops::Index;
+
+use crate::be_bytes::BEBytes;
+use crate::error::Error;
+use crate::keys::cmp_keys;
+
+/// A decoded bencode value.
+///
+/// Dicts are stored as a list of `(key, value)` pairs in the order they were read (or inserted).
+/// `BEReader` only produces dicts with sorted, unique keys, but values built by hand may not
+/// follow that rule.
+///
+/// The `Debug` output shows strings as text when they are valid UTF-8 and lists dict entries in
+/// canonical key order, regardless of how they are stored.
+///
+/// ```
+/// use beeenn::encode::KeyOrder;
+/// use beeenn::BESliceReader;
+///
+/// // Replace a torrent's comment, leaving everything else as it was.
+/// let input = b"d8:announce5:udp:a7:comment3:old4:infod6:lengthi7eee";
+/// let mut torrent = BESliceReader::new(input).next_value().unwrap().unwrap();
+/// assert_eq!("old", torrent["comment"].string());
+/// torrent.insert("comment", "new");
+/// torrent.insert("created by", "me");
+/// assert_eq!(
+///     &b"d8:announce5:udp:a7:comment3:new10:created by2:me4:infod6:lengthi7eee"[..],
+///     &torrent.encode(KeyOrder::Require).unwrap()[..]
+/// );
+/// ```
+#[derive(Clone, PartialEq, Eq)]
+pub enum BEValue {
+    BEInteger(i64),
+    BEString(BEBytes),
+    BEList(Vec<BEValue>),
+    BEDict(Vec<(BEBytes, BEValue)>),
+    /// The raw bytes of a value that could not be parsed. Only produced by `recover::recover`;
+    /// it can't be encoded.
+    BEInvalid(Vec<u8>),
+}
+
+impl BEValue {
+    pub fn is_integer(&self) -> bool {
+        matches!(self, BEValue::BEInteger(_))
+    }
+
+    pub fn is_string(&self) -> bool {
+        matches!(self, BEValue::BEString(_))
+    }
+
+    pub fn is_list(&self) -> bool {
+        matches!(self, BEValue::BEList(_))
+    }
+
+    pub fn is_dict(&self) -> bool {
+        matches!(self, BEValue::BEDict(_))
+    }
+
+    pub fn is_invalid(&self) -> bool {
+        matches!(self, BEValue::BEInvalid(_))
+    }
+
+    /// Returns the integer value.
+    ///
+    /// Panics if the value is not a `BEInteger`.
+    pub fn integer(&self) -> i64 {
+        match self {
+            BEValue::BEInteger(i) => *i,
+            _ => panic!("integer() called on non-integer value: {:?}", self),
+        }
+    }
+
+    /// Returns the raw bytes of a string value.
+    ///
+    /// Panics if the value is not a `BEString`.
+    pub fn bytes(&self) -> &[u8] {
+        match self {
+            BEValue::BEString(bytes) => bytes,
+            _ => panic!("bytes() called on non-string value: {:?}", self),
+        }
+    }
+
+    /// Returns the value of a string as a `&str`.
+    ///
+    /// Panics if the value is not a `BEString` or is not valid UTF-8.
+    pub fn string(&self) -> &str {
+        match self {
+            BEValue::BEString(bytes) => bytes.as_str().unwrap(),
+            _ => panic!("string() called on non-string value: {:?}", self),
+        }
+    }
+
+    /// Returns the number of elements in a list or dict, or the number of bytes in a string.
+    ///
+    /// Panics if called on a `BEInteger` or `BEInvalid`.
+    pub fn len(&self) -> usize {
+        match self {
+            BEValue::BEString(bytes) => bytes.len(),
+            BEValue::BEList(list) => list.len(),
+            BEValue::BEDict(dict) => dict.len(),
+            BEValue::BEInteger(_) => panic!("len() called on integer value: {:?}", self),
+            BEValue::BEInvalid(_) => panic!("len() called on invalid value: {:?}", self),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Looks up `key` in a dict. Returns `None` if the key is missing or if this is not a dict.
+    pub fn get<K: AsRef<[u8]>>(&self, key: K) -> Option<&BEValue> {
+        match self {
+            BEValue::BEDict(dict) => dict
+                .iter()
+                .find(|(k, _)| k.as_bytes() == key.as_ref())
+                .map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    // Looks up `key` for the typed getters, converting it with `convert` or reporting which key
+    // was missing or had the wrong type.
+    fn get_typed<'a, K, T>(
+        &'a self,
+        key: K,
+        expected: &'static str,
+        convert: impl FnOnce(&'a BEValue) -> Option<T>,
+    ) -> Result<T, Error>
+    where
+        K: AsRef<[u8]>,
+    {
+        let key_name = || String::from_utf8_lossy(key.as_ref()).into_owned();
+        let value = self
+            .get(key.as_ref())
+            .ok_or_else(|| Error::MissingKey(key_name()))?;
+        convert(value).ok_or_else(|| Error::WrongType(key_name(), expected))
+    }
+
+    /// Looks up an integer in a dict.
+    pub fn get_int<K: AsRef<[u8]>>(&self, key: K) -> Result<i64, Error> {
+        self.get_typed(key, "integer", |value| match value {
+            BEValue::BEInteger(i) => Some(*i),
+            _ => None,
+        })
+    }
+
+    /// Looks up a byte string in a dict.
+    pub fn get_bytes<K: AsRef<[u8]>>(&self, key: K) -> Result<&[u8], Error> {
+        self.get_typed(key, "string", |value| match value {
+            BEValue::BEString(bytes) => Some(bytes.as_bytes()),
+            _ => None,
+        })
+    }
+
+    /// Looks up a UTF-8 string in a dict.
+    pub fn get_str<K: AsRef<[u8]>>(&self, key: K) -> Result<&str, Error> {
+        self.get_typed(key, "UTF-8 string", |value| match value {
+            BEValue::BEString(bytes) => bytes.as_str(),
+            _ => None,
+        })
+    }
+
+    /// Looks up a list in a dict.
+    pub fn get_list<K: AsRef<[u8]>>(&self, key: K) -> Result<&[BEValue], Error> {
+        self.get_typed(key, "list", |value| match value {
+            BEValue::BEList(list) => Some(list.as_slice()),
+            _ => None,
+        })
+    }
+
+    /// Looks up a dict in a dict. The result is still a `BEValue` so that lookups can be chained.
+    pub fn get_dict<K: AsRef<[u8]>>(&self, key: K) -> Result<&BEValue, Error> {
+        self.get_typed(key, "dict", |value| match value {
+            BEValue::BEDict(_) => Some(value),
+            _ => None,
+        })
+    }
+
+    /// Sets `key` in a dict to `value`, returning the value it replaces.
+    ///
+    /// New keys are inserted at their canonical position, so a dict built only with `insert`
+    /// encodes without sorting. Keys and values convert from anything with a `From` impl, so
+    /// `dict.insert("length", 42)` works.
+    ///
+    /// Panics if the value is not a `BEDict`.
+    pub fn insert<K, V>(&mut self, key: K, value: V) -> Option<BEValue>
+    where
+        K: Into<BEBytes>,
+        V: Into<BEValue>,
+    {
+        let dict = match self {
+            BEValue::BEDict(dict) => dict,
+            _ => panic!("insert() called on non-dict value: {:?}", self),
+        };
+        let key = key.into();
+        let value = value.into();
+        if let Some((_, existing)) = dict.iter_mut().find(|(k, _)| *k == key) {
+            return Some(std::mem::replace(existing, value));
+        }
+        let pos = dict
+            .iter()
+            .position(|(k, _)| cmp_keys(k, &key) == Ordering::Greater)
+            .unwrap_or(dict.len());
+        dict.insert(pos, (key, value));
+        None
+    }
+
+    /// Iterates over the entries of a dict with keys as text, in stored order.
+    ///
+    /// Keys that aren't valid UTF-8 are converted lossily. Yields nothing if this is not a dict.
+    pub fn entries_str(&self) -> impl Iterator<Item = (Cow<'_, str>, &BEValue)> {
+        let entries = match self {
+            BEValue::BEDict(dict) => dict.as_slice(),
+            _ => &[],
+        };
+        entries.iter().map(|(k, v)| (String::from_utf8_lossy(k), v))
+    }
+
+    /// Follows a dotted path such as `"info.files.0.length"` through nested dicts and lists.
+    ///
+    /// Each component is looked up as a dict key, or parsed as an index when the current value is
+    /// a list. Returns `None` if any component is missing.
+    pub fn get_path(&self, path: &str) -> Option<&BEValue> {
+        path.split('.')
+            .try_fold(self, |value, component| match value {
+                BEValue::BEList(list) => component.parse::<usize>().ok().and_then(|i| list.get(i)),
+                _ => value.get(component),
+            })
+    }
+
+    /// The approximate number of heap bytes used by this value and everything in it.
+    ///
+    /// Counts string and key buffers and the storage of lists and dicts at their capacity, but
+    /// not allocator overhead or the `size_of::<BEValue>()` bytes of this value itself. Short
+    /// strings are stored inline in the `BEValue` and count as 0.
+    pub fn deep_size_of(&self) -> usize {
+        match self {
+            BEValue::BEInteger(_) => 0,
+            BEValue::BEString(bytes) => bytes.heap_size(),
+            BEValue::BEInvalid(bytes) => bytes.capacity(),
+            BEValue::BEList(list) => {
+                list.capacity() * mem::size_of::<BEValue>()
+                    + list.iter().map(BEValue::deep_size_of).sum::<usize>()
+            }
+            BEValue::BEDict(dict) => {
+                dict.capacity() * mem::size_of::<(BEBytes, BEValue)>()
+                    + dict
+                        .iter()
+                        .map(|(k, v)| k.heap_size() + v.deep_size_of())
+                        .sum::<usize>()
+            }
+        }
+    }
+
+    /// The heap bytes used by each entry of a dict, in stored order: the entry itself, its key,
+    /// and `deep_size_of` its value. Empty if this is not a dict.
+    ///
+    /// The sizes add up to `deep_size_of` the dict, less any spare capacity.
+    pub fn deep_size_by_key(&self) -> Vec<(&[u8], usize)> {
+        let entries = match self {
+            BEValue::BEDict(dict) => dict.as_slice(),
+            _ => &[],
+        };
+        entries
+            .iter()
+            .map(|(k, v)| {
+                let size = mem::size_of::<(BEBytes, BEValue)>() + k.heap_size() + v.deep_size_of();
+                (k.as_bytes(), size)
+            })
+            .collect()
+    }
+}
+
+impl From<i64> for BEValue {
+    fn from(i: i64) -> Self {
+        BEValue::BEInteger(i)
+    }
+}
+
+impl From<&str> for BEValue {
+    fn from(s: &str) -> Self {
+        BEValue::BEString(s.into())
+    }
+}
+
+impl From<String> for BEValue {
+    fn from(s: String) -> Self {
+        BEValue::BEString(s.into())
+    }
+}
+
+impl From<&[u8]> for BEValue {
+    fn from(bytes: &[u8]) -> Self {
+        BEValue::BEString(bytes.into())
+    }
+}
+
+impl From<Vec<u8>> for BEValue {
+    fn from(bytes: Vec<u8>) -> Self {
+        BEValue::BEString(bytes.into())
+    }
+}
+
+impl From<BEBytes> for BEValue {
+    fn from(bytes: BEBytes) -> Self {
+        BEValue::BEString(bytes)
+    }
+}
+
+impl From<Vec<BEValue>> for BEValue {
+    fn from(list: Vec<BEValue>) -> Self {
+        BEValue::BEList(list)
+    }
+}
+
+// Formats a byte string as a `str` when possible, and as an escaped byte literal otherwise.
+struct DebugBytes<'a>(&'a [u8]);
+
+impl fmt::Debug for DebugBytes<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match std::str::from_utf8(self.0) {
+            Ok(s) => write!(f, "{:?}", s),
+            Err(_) => write!(f, "b\"{}\"", self.0.escape_ascii()),
+        }
+    }
+}
+
+struct DebugDict<'a>(&'a [(BEBytes, BEValue)]);
+
+impl fmt::Debug for DebugDict<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut entries: Vec<&(BEBytes, BEValue)> = self.0.iter().collect();
+        entries.sort_by(|a, b| cmp_keys(&a.0, &b.0));
+        f.debug_map()
+            .entries(entries.iter().map(|(k, v)| (k, v)))
+            .finish()
+    }
+}
+
+impl fmt::Debug for BEValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BEValue::BEInteger(i) => f.debug_tuple("BEInteger").field(i).finish(),
+            BEValue::BEString(bytes) => f.debug_tuple("BEString").field(bytes).finish(),
+            BEValue::BEList(list) => f.debug_tuple("BEList").field(list).finish(),
+            BEValue::BEDict(dict) => f.debug_tuple("BEDict").field(&DebugDict(dict)).finish(),
+            BEValue::BEInvalid(bytes) => f
+                .debug_tuple("BEInvalid")
+                .field(&DebugBytes(bytes))
+                .finish(),
+        }
+    }
+}
+
+impl Index<usize> for BEValue {
+    type Output = BEValue;
+
+    fn index(&self, index: usize) -> &Self::Output {
+        match self {
+            BEValue::BEList(list) => &list[index],
+            _ => panic!("cannot index into non-list value: {:?}", self),
+        }
+    }
+}
+
+impl Index<&str> for BEValue {
+    type Output = BEValue;
+
+    fn index(&self, key: &str) -> &Self::Output {
+        match self.get(key) {
+            Some(value) => value,
+            None => panic!("no key '{}' in value: {:?}", key, self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn dict() -> BEValue {
+        BEValue::BEDict(vec![
+            (b"len".into(), BEValue::BEInteger(3)),
+            (b"name".into(), BEValue::from("foo")),
+        ])
+    }
+
+    #[test]
+    fn test_deep_size_of() {
+        let entry = mem::size_of::<(BEBytes, BEValue)>();
+        assert_eq!(0, BEValue::BEInteger(7).deep_size_of());
+        assert_eq!(0, BEValue::from("foo").deep_size_of());
+        assert_eq!(40, BEValue::from("x".repeat(40)).deep_size_of());
+        assert_eq!(
+            2 * mem::size_of::<BEValue>() + 40,
+            BEValue::BEList(vec![BEValue::BEInteger(1), BEValue::from("x".repeat(40))])
+                .deep_size_of()
+        );
+
+        // Short keys are inline too.
+        let value = dict();
+        assert_eq!(2 * entry, value.deep_size_of());
+        assert_eq!(
+            vec![(&b"len"[..], entry), (&b"name"[..], entry)],
+            value.deep_size_by_key()
+        );
+        let mut long = BEValue::BEDict(Vec::new());
+        long.insert("k".repeat(40), 1);
+        assert_eq!(
+            vec![(&b"k".repeat(40)[..], entry + 40)],
+            long.deep_size_by_key()
+        );
+        assert!(BEValue::BEInteger(7).deep_size_by_key().is_empty());
+    }
+
+    #[test]
+    fn test_get() {
+        let value = dict();
+        assert_eq!(Some(&BEValue::BEInteger(3)), value.get("len"));
+        assert_eq!(Some(&BEValue::BEInteger(3)), value.get(b"len"));
+        assert_eq!(None, value.get("missing"));
+        assert_eq!(None, BEValue::BEInteger(3).get("len"));
+    }
+
+    #[test]
+    fn test_typed_getters() {
+        let value = BEValue::BEDict(vec![
+            (b"info".into(), dict()),
+            (b"list".into(), BEValue::BEList(vec![BEValue::BEInteger(1)])),
+        ]);
+        assert_eq!(3, value.get_dict("info").unwrap().get_int("len").unwrap());
+        assert_eq!(
+            "foo",
+            value.get_dict("info").unwrap().get_str("name").unwrap()
+        );
+        assert_eq!(b"foo", value["info"].get_bytes("name").unwrap());
+        assert_eq!(1, value.get_list("list").unwrap().len());
+    }
+
+    #[test]
+    fn test_typed_getter_errors() {
+        let value = dict();
+        match value.get_int("missing") {
+            Err(Error::MissingKey(key)) => assert_eq!("missing", key),
+            _ => panic!("expected MissingKey"),
+        }
+        match value.get_str("len") {
+            Err(Error::WrongType(key, expected)) => {
+                assert_eq!("len", key);
+                assert_eq!("UTF-8 string", expected);
+            }
+            _ => panic!("expected WrongType"),
+        }
+        assert!(value.get_dict("name").is_err());
+        assert!(value.get_list("name").is_err());
+
+        let not_utf8 = BEValue::BEDict(vec![(b"k".into(), BEValue::from(vec![0xff]))]);
+        assert!(not_utf8.get_str("k").is_err());
+        assert_eq!(&[0xff], not_utf8.get_bytes("k").unwrap());
+    }
+
+    #[test]
+    fn test_get_path() {
+        let value = BEValue::BEDict(vec![(
+            b"info".into(),
+            BEValue::BEDict(vec![(
+                b"files".into(),
+                BEValue::BEList(vec![dict(), dict()]),
+            )]),
+        )]);
+        assert_eq!(
+            Some(&BEValue::BEInteger(3)),
+            value.get_path("info.files.1.len")
+        );
+        assert_eq!(None, value.get_path("info.files.2.len"));
+        assert_eq!(None, value.get_path("info.missing"));
+        assert_eq!(None, value.get_path("info.files.x"));
+    }
+
+    #[test]
+    fn test_index() {
+        let value = dict();
+        assert_eq!(value["name"].string(), "foo");
+
+        let list = BEValue::BEList(vec![BEValue::BEInteger(5), dict()]);
+        assert_eq!(list[0].integer(), 5);
+        assert_eq!(list[1]["len"].integer(), 3);
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_index_missing_key() {
+        let _ = &dict()["missing"];
+    }
+
+    #[test]
+    fn test_debug() {
+        let value = BEValue::BEDict(vec![
+            ("\u{3a9}".as_bytes().into(), BEValue::from(vec![b'a', 0xff])),
+            (b"b".into(), BEValue::BEList(vec![BEValue::BEInteger(-1)])),
+            (b"B".into(), BEValue::from("text")),
+        ]);
+        assert_eq!(
+            "BEDict({\"B\": BEString(\"text\"), \"b\": BEList([BEInteger(-1)]), \
+             \"\u{3a9}\": BEString(b\"a\\xff\")})",
+            format!("{:?}", value)
+        );
+    }
+
+    #[test]
+    fn test_insert() {
+        let mut value = BEValue::BEDict(Vec::new());
+        assert_eq!(None, value.insert("name", "foo"));
+        assert_eq!(None, value.insert(String::from("len"), 3));
+        assert_eq!(None, value.insert(&b"z"[..], vec![BEValue::from(1)]));
+        assert_eq!(None, value.insert("B", b"\xff".to_vec()));
+        assert_eq!(
+            Some(BEValue::BEInteger(3)),
+            value.insert("len", String::from("x"))
+        );
+
+        let keys: Vec<Cow<str>> = value.entries_str().map(|(k, _)| k).collect();
+        assert_eq!(vec!["B", "len", "name", "z"], keys);
+        assert_eq!("x", value["len"].string());
+        assert_eq!(1, value["z"][0].integer());
+    }
+
+    #[test]
+    fn test_entries_str() {
+        let value = BEValue::BEDict(vec![
+            (b"len".into(), BEValue::BEInteger(3)),
+            (vec![b'a', 0xff].into(), BEValue::BEInteger(4)),
+        ]);
+        let entries: Vec<(Cow<str>, i64)> =
+            value.entries_str().map(|(k, v)| (k, v.integer())).collect();
+        assert_eq!(
+            vec![(Cow::from("len"), 3), (Cow::from("a\u{fffd}"), 4)],
+            entries
+        );
+        assert_eq!(0, BEValue::BEInteger(1).entries_str().count());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_insert_non_dict() {
+        BEValue::BEList(Vec::new()).insert("a", 1);
+    }
+
+    #[test]
+    fn test_len() {
+        assert_eq!(dict().len(), 2);
+        assert!(BEValue::BEList(vec![]).is_empty());
+        assert_eq!(BEValue::from("four").len(), 4);
+    }
+}
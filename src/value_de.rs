@@ -0,0 +1,522 @@
+//! Deserializing typed values out of an already parsed `BEValue`.
+//!
+//! Strings are borrowed from the `BEValue`, so a tree can be parsed once, inspected, and then
+//! extracted into `&str` and `&[u8]` fields without copying.
+//!
+//! ```
+//! use beeenn::{from_value, BEReader};
+//! use serde::Deserialize;
+//!
+//! #[derive(Deserialize)]
+//! struct Info<'a> {
+//!     name: &'a str,
+//!     pieces: &'a [u8],
+//! }
+//!
+//! let value = BEReader::new(&b"d4:infod4:name3:foo6:pieces2:\x01\x02ee"[..])
+//!     .next_value()
+//!     .unwrap()
+//!     .unwrap();
+//! let info: Info = from_value(&value["info"]).unwrap();
+//! assert_eq!("foo", info.name);
+//! assert_eq!(&[1, 2], info.pieces);
+//! ```
+
+use std::convert::TryFrom;
+
+use serde::de::{self, Deserialize, DeserializeSeed, MapAccess, SeqAccess, Unexpected, Visitor};
+use serde::forward_to_deserialize_any;
+
+use crate::be_bytes::BEBytes;
+use crate::error::{Error, Result};
+use crate::syntax::parse_integer_key;
+use crate::value::BEValue;
+
+/// Deserializes a `T` from `value`, borrowing strings from it where `T` allows.
+pub fn from_value<'v, T>(value: &'v BEValue) -> Result<T>
+where
+    T: Deserialize<'v>,
+{
+    T::deserialize(ValueDeserializer {
+        value,
+        path: &Path::Root,
+    })
+}
+
+// Where a value sits in the tree, for error messages. Each level borrows its parent, so building
+// it costs nothing until an error formats it.
+enum Path<'v, 'p> {
+    Root,
+    Key(&'p Path<'v, 'p>, &'v [u8]),
+    Index(&'p Path<'v, 'p>, usize),
+}
+
+impl Path<'_, '_> {
+    fn push_to(&self, out: &mut String) {
+        let (parent, component) = match self {
+            Path::Root => return,
+            Path::Key(parent, key) => (parent, String::from_utf8_lossy(key).into_owned()),
+            Path::Index(parent, index) => (parent, index.to_string()),
+        };
+        parent.push_to(out);
+        if !out.is_empty() {
+            out.push('.');
+        }
+        out.push_str(&component);
+    }
+
+    fn to_dotted(&self) -> String {
+        let mut out = String::new();
+        self.push_to(&mut out);
+        out
+    }
+}
+
+struct ValueDeserializer<'v, 'p> {
+    value: &'v BEValue,
+    path: &'p Path<'v, 'p>,
+}
+
+impl<'v> ValueDeserializer<'v, '_> {
+    fn unexpected(&self) -> Unexpected<'v> {
+        match self.value {
+            BEValue::BEInteger(i) => Unexpected::Signed(*i),
+            BEValue::BEString(bytes) => Unexpected::Bytes(bytes),
+            BEValue::BEList(_) => Unexpected::Seq,
+            BEValue::BEDict(_) => Unexpected::Map,
+            BEValue::BEInvalid(_) => Unexpected::Other("invalid value"),
+        }
+    }
+
+    fn invalid_type<V: Visitor<'v>>(&self, visitor: &V) -> Error {
+        let err: Error = de::Error::invalid_type(self.unexpected(), visitor);
+        err.with_path(|| self.path.to_dotted())
+    }
+
+    fn integer<V: Visitor<'v>>(&self, visitor: &V) -> Result<i64> {
+        match self.value {
+            BEValue::BEInteger(i) => Ok(*i),
+            _ => Err(self.invalid_type(visitor)),
+        }
+    }
+
+    fn out_of_range(&self, value: i64, target: &'static str) -> Error {
+        Error::IntegerOutOfRange {
+            value: value as i128,
+            target,
+            path: self.path.to_dotted(),
+        }
+    }
+}
+
+macro_rules! deserialize_integer {
+    ($($method:ident => $visit:ident($t:ty),)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'v>,
+            {
+                let value = self.integer(&visitor)?;
+                match <$t>::try_from(value) {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(self.out_of_range(value, stringify!($t))),
+                }
+            }
+        )*
+    };
+}
+
+impl<'v> de::Deserializer<'v> for ValueDeserializer<'v, '_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        match self.value {
+            BEValue::BEInteger(i) if *i < 0 => visitor.visit_i64(*i),
+            BEValue::BEInteger(i) => visitor.visit_u64(*i as u64),
+            BEValue::BEString(bytes) => visitor.visit_borrowed_bytes(bytes),
+            BEValue::BEList(list) => visitor
+                .visit_seq(List {
+                    iter: list.iter().enumerate(),
+                    path: self.path,
+                })
+                .map_err(|err| err.with_path(|| self.path.to_dotted())),
+            BEValue::BEDict(dict) => visitor
+                .visit_map(Dict {
+                    iter: dict.iter(),
+                    value: None,
+                    path: self.path,
+                })
+                .map_err(|err| err.with_path(|| self.path.to_dotted())),
+            BEValue::BEInvalid(_) => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    deserialize_integer! {
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+    }
+
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        match self.integer(&visitor)? {
+            i if i < 0 => Err(Error::UnexpectedSigned),
+            i => visitor.visit_bool(i != 0),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        match self.value {
+            BEValue::BEString(bytes) => visitor.visit_borrowed_str(std::str::from_utf8(bytes)?),
+            _ => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        match self.value {
+            BEValue::BEString(bytes) => visitor.visit_borrowed_bytes(bytes),
+            _ => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
+    fn deserialize_option<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        // Bencode has no null: a value that is present is always `Some`.
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_seq<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        match self.value {
+            BEValue::BEList(_) => self.deserialize_any(visitor),
+            _ => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        match self.value {
+            BEValue::BEDict(_) => self.deserialize_any(visitor),
+            _ => Err(self.invalid_type(&visitor)),
+        }
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        _name: &'static str,
+        _fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        self.deserialize_map(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        self.deserialize_str(visitor)
+    }
+
+    fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        visitor.visit_unit()
+    }
+
+    forward_to_deserialize_any! {
+        <W: Visitor<'v>>
+        i128 u128 f32 f64 char unit unit_struct newtype_struct tuple tuple_struct enum
+    }
+}
+
+struct List<'v, 'p> {
+    iter: std::iter::Enumerate<std::slice::Iter<'v, BEValue>>,
+    path: &'p Path<'v, 'p>,
+}
+
+impl<'v> SeqAccess<'v> for List<'v, '_> {
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>>
+    where
+        T: DeserializeSeed<'v>,
+    {
+        match self.iter.next() {
+            Some((index, value)) => {
+                let path = Path::Index(self.path, index);
+                seed.deserialize(ValueDeserializer { value, path: &path })
+                    .map(Some)
+                    .map_err(|err| err.with_path(|| path.to_dotted()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+struct Dict<'v, 'p> {
+    iter: std::slice::Iter<'v, (BEBytes, BEValue)>,
+    // The entry whose key was just returned.
+    value: Option<&'v (BEBytes, BEValue)>,
+    path: &'p Path<'v, 'p>,
+}
+
+impl<'v> MapAccess<'v> for Dict<'v, '_> {
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>>
+    where
+        K: DeserializeSeed<'v>,
+    {
+        match self.iter.next() {
+            Some(entry) => {
+                self.value = Some(entry);
+                let key = KeyDeserializer {
+                    key: &entry.0,
+                    path: self.path,
+                };
+                seed.deserialize(key)
+                    .map(Some)
+                    .map_err(|err| err.with_path(|| self.path.to_dotted()))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<T>(&mut self, seed: T) -> Result<T::Value>
+    where
+        T: DeserializeSeed<'v>,
+    {
+        let (key, value) = self
+            .value
+            .take()
+            .expect("next_value_seed called before next_key_seed");
+        let path = Path::Key(self.path, key);
+        seed.deserialize(ValueDeserializer { value, path: &path })
+            .map_err(|err| err.with_path(|| path.to_dotted()))
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.iter.len())
+    }
+}
+
+// A dict key, which is given to the target as bytes unless it wants an integer.
+struct KeyDeserializer<'v, 'p> {
+    key: &'v [u8],
+    // The dict's path.
+    path: &'p Path<'v, 'p>,
+}
+
+macro_rules! deserialize_integer_key {
+    ($($method:ident => $visit:ident($t:ty),)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value>
+            where
+                V: Visitor<'v>,
+            {
+                let value = parse_integer_key(self.key)?;
+                match <$t>::try_from(value) {
+                    Ok(v) => visitor.$visit(v),
+                    Err(_) => Err(Error::IntegerOutOfRange {
+                        value,
+                        target: stringify!($t),
+                        path: self.path.to_dotted(),
+                    }),
+                }
+            }
+        )*
+    };
+}
+
+impl<'v> de::Deserializer<'v> for KeyDeserializer<'v, '_> {
+    type Error = Error;
+
+    deserialize_integer_key! {
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+    }
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'v>,
+    {
+        visitor.visit_borrowed_bytes(self.key)
+    }
+
+    forward_to_deserialize_any! {
+        <W: Visitor<'v>>
+        bool i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        newtype_struct seq tuple tuple_struct map struct enum identifier ignored_any
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reader::BESliceReader;
+    use serde::Deserialize;
+    use std::collections::BTreeMap;
+
+    fn parse(bytes: &[u8]) -> BEValue {
+        BESliceReader::new(bytes).next_value().unwrap().unwrap()
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct File<'a> {
+        length: u32,
+        #[serde(borrow)]
+        path: Vec<&'a str>,
+        md5sum: Option<&'a [u8]>,
+    }
+
+    #[test]
+    fn test_borrowed() {
+        let value = parse(b"ld6:lengthi3e4:pathl1:a1:beee");
+        let files: Vec<File> = from_value(&value).unwrap();
+        assert_eq!(
+            vec![File {
+                length: 3,
+                path: vec!["a", "b"],
+                md5sum: None,
+            }],
+            files
+        );
+
+        // The strings point into the value.
+        let path = value[0]["path"][1].bytes();
+        assert_eq!(path.as_ptr(), files[0].path[1].as_ptr());
+    }
+
+    #[test]
+    fn test_owned_and_maps() {
+        let value = parse(b"d1:ai-1e1:bi2ee");
+        let map: BTreeMap<String, i64> = from_value(&value).unwrap();
+        assert_eq!(Some(&-1), map.get("a"));
+        assert_eq!(2, map.len());
+
+        let value = parse(b"li1ei0ee");
+        assert_eq!(vec![true, false], from_value::<Vec<bool>>(&value).unwrap());
+
+        let value = parse(b"d1:ad2:-1i1e1:7i2eee");
+        let map: BTreeMap<&str, BTreeMap<i8, u8>> = from_value(&value).unwrap();
+        assert_eq!(Some(&2), map["a"].get(&7));
+        assert_eq!(Some(&1), map["a"].get(&-1));
+        assert_eq!(
+            Error::IntegerOutOfRange {
+                value: -1,
+                target: "u8",
+                path: "a".to_string(),
+            },
+            from_value::<BTreeMap<&str, BTreeMap<u8, u8>>>(&value).unwrap_err()
+        );
+        assert_eq!(
+            Error::KeyNotInteger("a".to_string()),
+            from_value::<BTreeMap<u8, serde::de::IgnoredAny>>(&value).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn test_aliases() {
+        #[derive(Debug, PartialEq, Deserialize)]
+        struct Meta {
+            #[serde(rename = "created by", alias = "created_by")]
+            created_by: String,
+        }
+        for bytes in [&b"d10:created by2:mee"[..], &b"d10:created_by2:mee"[..]] {
+            let meta: Meta = from_value(&parse(bytes)).unwrap();
+            assert_eq!("me", meta.created_by);
+        }
+    }
+
+    #[test]
+    fn test_errors() {
+        let value = parse(b"ld6:lengthi-3e4:pathleee");
+        assert_eq!(
+            Error::IntegerOutOfRange {
+                value: -3,
+                target: "u32",
+                path: "0.length".to_string(),
+            },
+            from_value::<Vec<File>>(&value).unwrap_err()
+        );
+
+        let value = parse(b"d6:lengthi3e4:pathli1eee");
+        let err = from_value::<File>(&value).unwrap_err();
+        assert_eq!(
+            Error::InvalidType {
+                unexpected: "integer `1`".to_string(),
+                expected: "a borrowed string".to_string(),
+                path: "path.0".to_string(),
+            },
+            err
+        );
+        assert_eq!(
+            "invalid type: integer `1`, expected a borrowed string at 'path.0'",
+            err.to_string()
+        );
+        assert_eq!(
+            Error::MissingField {
+                field: "path",
+                path: "0".to_string(),
+            },
+            from_value::<Vec<File>>(&parse(b"ld6:lengthi1eee")).unwrap_err()
+        );
+
+        let value = parse(b"1:\xff");
+        assert!(matches!(
+            from_value::<&str>(&value).unwrap_err(),
+            Error::Utf8Error(_)
+        ));
+        assert!(from_value::<u8>(&BEValue::BEInvalid(Vec::new())).is_err());
+    }
+}
@@ -0,0 +1,94 @@
+//! Serializing a `BEValue` through serde, so DOM values can be fields of derived structs.
+//!
+//! ```
+//! use beeenn::{to_bytes, BEValue};
+//! use serde::Serialize;
+//!
+//! #[derive(Serialize)]
+//! struct Announce<'a> {
+//!     info_hash: &'a str,
+//!     extra: &'a BEValue,
+//! }
+//!
+//! let mut extra = BEValue::BEDict(Vec::new());
+//! extra.insert("z", 1);
+//! extra.insert("a", "x");
+//! let bytes = to_bytes(&Announce { info_hash: "abc", extra: &extra }).unwrap();
+//! assert_eq!(&b"d5:extrad1:a1:x1:zi1ee9:info_hash3:abce"[..], &bytes[..]);
+//! ```
+
+use serde::ser::{self, Serialize, SerializeMap, Serializer};
+
+use crate::be_bytes::BEBytes;
+use crate::keys::cmp_keys;
+use crate::value::BEValue;
+
+// Byte strings, which would otherwise serialize as a list of integers.
+struct Bytes<'a>(&'a [u8]);
+
+impl Serialize for Bytes<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(self.0)
+    }
+}
+
+/// Integers and strings map to serde's `i64` and bytes, lists to sequences, and dicts to maps
+/// with byte string keys. Dict entries are always written in canonical key order, whatever
+/// order they are stored in. A `BEInvalid` value is an error.
+impl Serialize for BEValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            BEValue::BEInteger(i) => serializer.serialize_i64(*i),
+            BEValue::BEString(bytes) => serializer.serialize_bytes(bytes),
+            BEValue::BEList(list) => serializer.collect_seq(list),
+            BEValue::BEDict(dict) => {
+                let mut entries: Vec<&(BEBytes, BEValue)> = dict.iter().collect();
+                entries.sort_by(|a, b| cmp_keys(&a.0, &b.0));
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(&Bytes(key), value)?;
+                }
+                map.end()
+            }
+            BEValue::BEInvalid(_) => Err(ser::Error::custom("cannot serialize an invalid value")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reader::BESliceReader;
+    use crate::{to_bytes, Error, Serializer};
+
+    #[test]
+    fn test_round_trip() {
+        let input = b"d1:ali-1e2:\xff\xffe1:bd0:lee1:ci9223372036854775807ee";
+        let value = BESliceReader::new(input).next_value().unwrap().unwrap();
+        assert_eq!(&input[..], &to_bytes(&value).unwrap()[..]);
+        assert_eq!(&input[..], &to_bytes(&&value).unwrap()[..]);
+    }
+
+    #[test]
+    fn test_canonical_order() {
+        let value = BEValue::BEDict(vec![
+            (b"b".into(), BEValue::BEInteger(1)),
+            (b"a".into(), BEValue::BEInteger(2)),
+        ]);
+        assert_eq!(&b"d1:ai2e1:bi1ee"[..], &to_bytes(&value).unwrap()[..]);
+
+        // Even when the serializer would keep the order it was given.
+        let mut serializer = Serializer::new().preserve_order(true);
+        serializer.append(&value).unwrap();
+        assert_eq!(&b"d1:ai2e1:bi1ee"[..], serializer.bytes());
+    }
+
+    #[test]
+    fn test_invalid() {
+        let value = BEValue::BEList(vec![BEValue::BEInvalid(b"?".to_vec())]);
+        assert_eq!(
+            Error::Message("cannot serialize an invalid value".to_string()),
+            to_bytes(&value).unwrap_err()
+        );
+    }
+}
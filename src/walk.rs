@@ -0,0 +1,257 @@
+//! Walking every node of a `BEValue` tree.
+//!
+//! Implement `Visitor` and pass it to `BEValue::walk` instead of writing the recursive match by
+//! hand. Each node is entered before its children and left after them, along with its dotted
+//! path in the form `BEValue::get_path` accepts. `VisitorMut` and `BEValue::walk_mut` do the
+//! same for visitors that change the tree as they go.
+//!
+//! ```
+//! use beeenn::walk::{Visitor, Walk};
+//! use beeenn::{BEReader, BEValue};
+//!
+//! // Collects the path of every integer.
+//! struct Integers(Vec<String>);
+//!
+//! impl Visitor for Integers {
+//!     fn enter(&mut self, path: &str, value: &BEValue) -> Walk {
+//!         if value.is_integer() {
+//!             self.0.push(path.to_string());
+//!         }
+//!         Walk::Continue
+//!     }
+//! }
+//!
+//! let value = BEReader::new(&b"d1:ali1e1:xe1:bi2ee"[..]).next_value().unwrap().unwrap();
+//! let mut integers = Integers(Vec::new());
+//! value.walk(&mut integers);
+//! assert_eq!(vec!["a.0", "b"], integers.0);
+//! ```
+
+use crate::value::BEValue;
+
+/// Whether `BEValue::walk` should visit the children of the node just entered.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Walk {
+    Continue,
+    /// Don't visit this node's children. Its `leave` is still called.
+    SkipChildren,
+}
+
+/// Callbacks for `BEValue::walk`.
+///
+/// `path` is empty for the root. Dict keys that aren't valid UTF-8 are converted lossily, and keys
+/// containing '.' make the path ambiguous, just as they do for `get_path`.
+pub trait Visitor {
+    /// Called before the children of `value`, if it has any.
+    fn enter(&mut self, _path: &str, _value: &BEValue) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called after the children of `value`.
+    fn leave(&mut self, _path: &str, _value: &BEValue) {}
+}
+
+/// Callbacks for `BEValue::walk_mut`.
+///
+/// `enter` may replace the value or change its children; the walk continues into whatever
+/// children it has when `enter` returns.
+pub trait VisitorMut {
+    /// Called before the children of `value`, if it has any.
+    fn enter(&mut self, _path: &str, _value: &mut BEValue) -> Walk {
+        Walk::Continue
+    }
+
+    /// Called after the children of `value`.
+    fn leave(&mut self, _path: &str, _value: &mut BEValue) {}
+}
+
+impl BEValue {
+    /// Visits this value and everything under it, depth first, in stored order.
+    pub fn walk<V: Visitor + ?Sized>(&self, visitor: &mut V) {
+        walk_value(self, &mut String::new(), visitor);
+    }
+
+    /// Like `walk`, but the visitor can change values as it goes.
+    pub fn walk_mut<V: VisitorMut + ?Sized>(&mut self, visitor: &mut V) {
+        walk_value_mut(self, &mut String::new(), visitor);
+    }
+}
+
+// Appends `component` to `path`, returning the length to truncate back to afterwards.
+pub(crate) fn push_component(path: &mut String, component: &str) -> usize {
+    let len = path.len();
+    if len > 0 {
+        path.push('.');
+    }
+    path.push_str(component);
+    len
+}
+
+fn walk_value<V: Visitor + ?Sized>(value: &BEValue, path: &mut String, visitor: &mut V) {
+    if visitor.enter(path, value) == Walk::Continue {
+        match value {
+            BEValue::BEList(list) => {
+                for (i, element) in list.iter().enumerate() {
+                    let len = push_component(path, &i.to_string());
+                    walk_value(element, path, visitor);
+                    path.truncate(len);
+                }
+            }
+            BEValue::BEDict(dict) => {
+                for (key, element) in dict {
+                    let len = push_component(path, &String::from_utf8_lossy(key));
+                    walk_value(element, path, visitor);
+                    path.truncate(len);
+                }
+            }
+            BEValue::BEInteger(_) | BEValue::BEString(_) | BEValue::BEInvalid(_) => {}
+        }
+    }
+    visitor.leave(path, value);
+}
+
+fn walk_value_mut<V: VisitorMut + ?Sized>(value: &mut BEValue, path: &mut String, visitor: &mut V) {
+    if visitor.enter(path, value) == Walk::Continue {
+        match value {
+            BEValue::BEList(list) => {
+                for (i, element) in list.iter_mut().enumerate() {
+                    let len = push_component(path, &i.to_string());
+                    walk_value_mut(element, path, visitor);
+                    path.truncate(len);
+                }
+            }
+            BEValue::BEDict(dict) => {
+                for (key, element) in dict {
+                    let len = push_component(path, &String::from_utf8_lossy(key));
+                    walk_value_mut(element, path, visitor);
+                    path.truncate(len);
+                }
+            }
+            BEValue::BEInteger(_) | BEValue::BEString(_) | BEValue::BEInvalid(_) => {}
+        }
+    }
+    visitor.leave(path, value);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::reader::BESliceReader;
+
+    // Records every callback.
+    struct Trace {
+        events: Vec<String>,
+        skip: &'static str,
+    }
+
+    impl Visitor for Trace {
+        fn enter(&mut self, path: &str, _value: &BEValue) -> Walk {
+            self.events.push(format!("enter {}", path));
+            if path == self.skip {
+                Walk::SkipChildren
+            } else {
+                Walk::Continue
+            }
+        }
+
+        fn leave(&mut self, path: &str, _value: &BEValue) {
+            self.events.push(format!("leave {}", path));
+        }
+    }
+
+    fn value() -> BEValue {
+        BESliceReader::new(b"d1:ali1ed1:bi2eee1:c3:fooe")
+            .next_value()
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_walk() {
+        let mut trace = Trace {
+            events: Vec::new(),
+            skip: "none",
+        };
+        value().walk(&mut trace);
+        assert_eq!(
+            vec![
+                "enter ",
+                "enter a",
+                "enter a.0",
+                "leave a.0",
+                "enter a.1",
+                "enter a.1.b",
+                "leave a.1.b",
+                "leave a.1",
+                "leave a",
+                "enter c",
+                "leave c",
+                "leave ",
+            ],
+            trace.events
+        );
+    }
+
+    #[test]
+    fn test_skip_children() {
+        let mut trace = Trace {
+            events: Vec::new(),
+            skip: "a",
+        };
+        value().walk(&mut trace);
+        assert_eq!(
+            vec!["enter ", "enter a", "leave a", "enter c", "leave c", "leave "],
+            trace.events
+        );
+    }
+
+    #[test]
+    fn test_walk_mut() {
+        // Negates every integer, and replaces lists with their length.
+        struct Change;
+        impl VisitorMut for Change {
+            fn enter(&mut self, _path: &str, value: &mut BEValue) -> Walk {
+                if let BEValue::BEInteger(i) = value {
+                    *i = -*i;
+                }
+                Walk::Continue
+            }
+
+            fn leave(&mut self, _path: &str, value: &mut BEValue) {
+                if let BEValue::BEList(list) = value {
+                    *value = BEValue::BEInteger(list.len() as i64);
+                }
+            }
+        }
+
+        let mut value = value();
+        value.walk_mut(&mut Change);
+        assert_eq!(2, value["a"].integer());
+        assert_eq!("foo", value["c"].string());
+
+        // Children replaced in `enter` are walked instead of the originals.
+        let mut value = BESliceReader::new(b"li1ee").next_value().unwrap().unwrap();
+        struct Grow;
+        impl VisitorMut for Grow {
+            fn enter(&mut self, path: &str, value: &mut BEValue) -> Walk {
+                if path.is_empty() {
+                    *value = BEValue::BEList(vec![BEValue::BEInteger(5); 3]);
+                } else {
+                    *value = BEValue::BEInteger(value.integer() + 1);
+                }
+                Walk::Continue
+            }
+        }
+        value.walk_mut(&mut Grow);
+        assert_eq!(BEValue::BEList(vec![BEValue::BEInteger(6); 3]), value);
+    }
+
+    #[test]
+    fn test_default_callbacks() {
+        struct Nothing;
+        impl Visitor for Nothing {}
+        value().walk(&mut Nothing);
+        impl VisitorMut for Nothing {}
+        value().walk_mut(&mut Nothing);
+    }
+}
@@ -0,0 +1,214 @@
+//! Modules for `#[serde(with = "...")]` covering field encodings that recur in torrent and
+//! tracker schemas.
+//!
+//! ```
+//! use serde::{Deserialize, Serialize};
+//! use std::time::{Duration, SystemTime, UNIX_EPOCH};
+//!
+//! #[derive(Debug, PartialEq, Serialize, Deserialize)]
+//! struct Info {
+//!     #[serde(rename = "creation date", with = "beeenn::unix_timestamp")]
+//!     creation_date: SystemTime,
+//!     #[serde(with = "beeenn::as_bool_int")]
+//!     private: bool,
+//!     #[serde(rename = "url-list", with = "beeenn::string_or_list")]
+//!     url_list: Vec<String>,
+//! }
+//!
+//! let info: Info = beeenn::from_bytes(b"d13:creation datei86400e7:privatei1e8:url-list3:urle").unwrap();
+//! assert_eq!(UNIX_EPOCH + Duration::from_secs(86400), info.creation_date);
+//! assert!(info.private);
+//! assert_eq!(vec!["url"], info.url_list);
+//! ```
+
+use std::fmt;
+
+use serde::de::{self, Deserializer, Visitor};
+use serde::ser::Serializer;
+
+/// A bool encoded as the integer 0 or 1. Any other integer is an error.
+pub mod as_bool_int {
+    use super::*;
+    use serde::Deserialize;
+
+    pub fn serialize<S: Serializer>(value: &bool, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u8(*value as u8)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<bool, D::Error> {
+        match i64::deserialize(deserializer)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(de::Error::invalid_value(
+                de::Unexpected::Signed(other),
+                &"0 or 1",
+            )),
+        }
+    }
+}
+
+/// A list of strings that may also appear as a single string, as `url-list` does. Always
+/// serialized as a list. See `OneOrMany` for other element types.
+pub mod string_or_list {
+    use super::*;
+    use crate::OneOrMany;
+    use serde::Deserialize;
+
+    pub fn serialize<S: Serializer>(value: &[String], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(value)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<String>, D::Error> {
+        OneOrMany::deserialize(deserializer).map(OneOrMany::into_inner)
+    }
+}
+
+/// Bytes encoded as a string of lowercase hex digits. Either case is accepted when reading.
+pub mod hex_bytes {
+    use super::*;
+
+    const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: AsRef<[u8]> + ?Sized,
+        S: Serializer,
+    {
+        let hex: String = value
+            .as_ref()
+            .iter()
+            .flat_map(|b| [DIGITS[(b >> 4) as usize], DIGITS[(b & 0xf) as usize]])
+            .map(char::from)
+            .collect();
+        serializer.serialize_str(&hex)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        deserializer.deserialize_bytes(HexVisitor)
+    }
+
+    fn nibble(b: u8) -> Option<u8> {
+        (b as char).to_digit(16).map(|d| d as u8)
+    }
+
+    struct HexVisitor;
+
+    impl<'de> Visitor<'de> for HexVisitor {
+        type Value = Vec<u8>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("an even number of hex digits")
+        }
+
+        fn visit_bytes<E: de::Error>(self, hex: &[u8]) -> Result<Vec<u8>, E> {
+            let invalid = || de::Error::invalid_value(de::Unexpected::Bytes(hex), &self);
+            if hex.len() % 2 != 0 {
+                return Err(invalid());
+            }
+            hex.chunks(2)
+                .map(|pair| Some(nibble(pair[0])? << 4 | nibble(pair[1])?))
+                .collect::<Option<Vec<u8>>>()
+                .ok_or_else(invalid)
+        }
+
+        fn visit_str<E: de::Error>(self, hex: &str) -> Result<Vec<u8>, E> {
+            self.visit_bytes(hex.as_bytes())
+        }
+    }
+}
+
+/// A `SystemTime` encoded as whole seconds since the Unix epoch, as `creation date` is. Times
+/// before the epoch are negative; fractions of a second are truncated.
+pub mod unix_timestamp {
+    use super::*;
+    use serde::Deserialize;
+    use std::convert::TryFrom;
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    pub fn serialize<S: Serializer>(value: &SystemTime, serializer: S) -> Result<S::Ok, S::Error> {
+        let secs = match value.duration_since(UNIX_EPOCH) {
+            Ok(after) => i64::try_from(after.as_secs()),
+            Err(before) => i64::try_from(before.duration().as_secs()).map(|secs| -secs),
+        };
+        match secs {
+            Ok(secs) => serializer.serialize_i64(secs),
+            Err(_) => Err(serde::ser::Error::custom(
+                "timestamp does not fit in an i64",
+            )),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<SystemTime, D::Error> {
+        let secs = i64::deserialize(deserializer)?;
+        let offset = Duration::from_secs(secs.unsigned_abs());
+        let time = if secs < 0 {
+            UNIX_EPOCH.checked_sub(offset)
+        } else {
+            UNIX_EPOCH.checked_add(offset)
+        };
+        time.ok_or_else(|| {
+            de::Error::invalid_value(de::Unexpected::Signed(secs), &"a representable time")
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{from_bytes, to_bytes, Error};
+    use serde::{Deserialize, Serialize};
+    use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Fields {
+        #[serde(with = "super::as_bool_int")]
+        b: bool,
+        #[serde(with = "super::hex_bytes")]
+        h: Vec<u8>,
+        #[serde(with = "super::string_or_list")]
+        s: Vec<String>,
+        #[serde(with = "super::unix_timestamp")]
+        t: SystemTime,
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let val = Fields {
+            b: true,
+            h: vec![0x00, 0xab, 0x7f],
+            s: vec!["one".to_string()],
+            t: UNIX_EPOCH - Duration::from_secs(5),
+        };
+        let bytes = to_bytes(&val).unwrap();
+        assert_eq!(&b"d1:bi1e1:h6:00ab7f1:sl3:onee1:ti-5ee"[..], &bytes[..]);
+        assert_eq!(val, from_bytes(&bytes).unwrap());
+    }
+
+    #[test]
+    fn test_lenient_input() {
+        let val: Fields = from_bytes(b"d1:bi0e1:h4:ABcd1:s1:x1:ti0ee").unwrap();
+        assert!(!val.b);
+        assert_eq!(vec![0xab, 0xcd], val.h);
+        assert_eq!(vec!["x"], val.s);
+        assert_eq!(UNIX_EPOCH, val.t);
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        fn message(input: &[u8]) -> String {
+            match from_bytes::<Fields>(input).unwrap_err() {
+                err @ Error::InvalidValue { .. } | err @ Error::InvalidType { .. } => {
+                    err.to_string()
+                }
+                other => panic!("unexpected error {:?}", other),
+            }
+        }
+
+        assert!(message(b"d1:bi2e1:h0:1:sle1:ti0ee").contains("0 or 1"));
+        assert!(message(b"d1:bi1e1:h3:abc1:sle1:ti0ee").contains("hex"));
+        assert!(message(b"d1:bi1e1:h2:zz1:sle1:ti0ee").contains("hex"));
+        assert!(message(b"d1:bi1e1:h0:1:s1:\xff1:ti0ee").contains("string"));
+        assert!(from_bytes::<Fields>(b"d1:bi1e1:h0:1:si1e1:ti0ee").is_err());
+    }
+}
@@ -0,0 +1,261 @@
+#![cfg(feature = "serde")]
+
+//! How serde's field attributes combine with the way bencode leaves out `None`.
+//!
+//! A `None` field is never written, with or without `skip_serializing_if`, so a struct written
+//! with a field missing must read back the same whether the field is an `Option` or has a
+//! `#[serde(default)]`.
+
+use beeenn::{from_bytes, to_bytes, Error};
+use serde::{Deserialize, Serialize};
+
+fn round_trip<T>(value: &T, expected: &[u8])
+where
+    T: Serialize + for<'de> Deserialize<'de> + PartialEq + std::fmt::Debug,
+{
+    let bytes = to_bytes(value).unwrap();
+    assert_eq!(
+        String::from_utf8_lossy(expected),
+        String::from_utf8_lossy(&bytes)
+    );
+    assert_eq!(value, &from_bytes::<T>(&bytes).unwrap());
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Options {
+    plain: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    skipped: Option<u8>,
+    #[serde(default)]
+    defaulted: Option<u8>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    both: Option<u8>,
+}
+
+#[test]
+fn test_option_attributes_agree() {
+    let none = Options {
+        plain: None,
+        skipped: None,
+        defaulted: None,
+        both: None,
+    };
+    round_trip(&none, b"de");
+
+    let some = Options {
+        plain: Some(1),
+        skipped: Some(2),
+        defaulted: Some(3),
+        both: Some(4),
+    };
+    round_trip(&some, b"d4:bothi4e9:defaultedi3e5:plaini1e7:skippedi2ee");
+}
+
+fn five() -> u32 {
+    5
+}
+
+fn some_seven() -> Option<u32> {
+    Some(7)
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Defaults {
+    required: u32,
+    #[serde(default)]
+    count: u32,
+    #[serde(default = "five")]
+    five: u32,
+    #[serde(default)]
+    list: Vec<String>,
+    #[serde(default = "some_seven")]
+    seven: Option<u32>,
+}
+
+#[test]
+fn test_missing_defaulted_fields() {
+    let value: Defaults = from_bytes(b"d8:requiredi1ee").unwrap();
+    assert_eq!(
+        Defaults {
+            required: 1,
+            count: 0,
+            five: 5,
+            list: vec![],
+            seven: Some(7),
+        },
+        value
+    );
+
+    // Without a default, a missing field is an error naming it.
+    assert!(matches!(
+        from_bytes::<Defaults>(b"d5:counti1ee"),
+        Err(Error::MissingField {
+            field: "required",
+            ..
+        })
+    ));
+}
+
+#[test]
+fn test_default_with_none() {
+    // A `None` is left out when written, so it comes back as the default, not as `None`.
+    let value = Defaults {
+        required: 1,
+        count: 0,
+        five: 5,
+        list: vec![],
+        seven: None,
+    };
+    let bytes = to_bytes(&value).unwrap();
+    assert_eq!(
+        &b"d5:counti0e4:fivei5e4:listle8:requiredi1ee"[..],
+        &bytes[..]
+    );
+    assert_eq!(Some(7), from_bytes::<Defaults>(&bytes).unwrap().seven);
+}
+
+#[derive(Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+struct AllDefault {
+    name: String,
+    length: u64,
+    private: Option<bool>,
+}
+
+#[test]
+fn test_container_default() {
+    round_trip(&AllDefault::default(), b"d6:lengthi0e4:name0:e");
+    assert_eq!(AllDefault::default(), from_bytes(b"de").unwrap());
+    assert_eq!(
+        AllDefault {
+            length: 3,
+            ..AllDefault::default()
+        },
+        from_bytes(b"d6:lengthi3ee").unwrap()
+    );
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Skips {
+    kept: u8,
+    #[serde(skip)]
+    skipped: u8,
+    #[serde(skip_serializing, default)]
+    read_only: u8,
+    #[serde(skip_deserializing)]
+    write_only: u8,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    tags: Vec<String>,
+}
+
+#[test]
+fn test_skip_attributes() {
+    let value = Skips {
+        kept: 1,
+        skipped: 2,
+        read_only: 3,
+        write_only: 4,
+        tags: vec![],
+    };
+    let bytes = to_bytes(&value).unwrap();
+    assert_eq!(&b"d4:kepti1e10:write_onlyi4ee"[..], &bytes[..]);
+
+    // Skipped fields come back as their defaults, and `write_only` is ignored when read.
+    let read: Skips = from_bytes(&bytes).unwrap();
+    assert_eq!(
+        Skips {
+            kept: 1,
+            skipped: 0,
+            read_only: 0,
+            write_only: 0,
+            tags: vec![],
+        },
+        read
+    );
+    let read: Skips = from_bytes(b"d4:kepti1e9:read_onlyi3e7:skippedi2e4:tagsl1:aee").unwrap();
+    assert_eq!((3, 0), (read.read_only, read.skipped));
+    assert_eq!(vec!["a".to_string()], read.tags);
+}
+
+#[test]
+fn test_empty_values_are_written() {
+    // Only `None` is left out. Empty strings, lists and dicts are values like any other.
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Empty {
+        list: Vec<u8>,
+        name: String,
+        nested: Option<AllDefault>,
+    }
+    round_trip(
+        &Empty {
+            list: vec![],
+            name: String::new(),
+            nested: Some(AllDefault::default()),
+        },
+        b"d4:listle4:name0:6:nestedd6:lengthi0e4:name0:ee",
+    );
+}
+
+#[test]
+fn test_nested_none() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Outer {
+        inner: Option<Options>,
+        list: Vec<Option<u8>>,
+    }
+    round_trip(
+        &Outer {
+            inner: None,
+            list: vec![],
+        },
+        b"d4:listlee",
+    );
+
+    // `None` has nowhere to go inside a list, so it's an error rather than a silent gap.
+    let err = to_bytes(&Outer {
+        inner: None,
+        list: vec![Some(1), None],
+    })
+    .unwrap_err();
+    assert!(matches!(
+        err,
+        Error::NoneInContainer {
+            container: "list",
+            index: 1
+        }
+    ));
+}
+
+#[test]
+fn test_flatten_with_options() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Torrent {
+        announce: String,
+        #[serde(flatten)]
+        extra: Options,
+    }
+    round_trip(
+        &Torrent {
+            announce: "url".to_string(),
+            extra: Options {
+                plain: None,
+                skipped: Some(2),
+                defaulted: None,
+                both: None,
+            },
+        },
+        b"d8:announce3:url7:skippedi2ee",
+    );
+}
+
+#[test]
+fn test_option_of_option() {
+    // `Some(None)` is written as nothing at all, so it can't be told apart from `None`.
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Nested {
+        value: Option<Option<u8>>,
+    }
+    let bytes = to_bytes(&Nested { value: Some(None) }).unwrap();
+    assert_eq!(&b"de"[..], &bytes[..]);
+    assert_eq!(Nested { value: None }, from_bytes(&bytes).unwrap());
+}
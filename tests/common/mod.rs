@@ -0,0 +1,28 @@
+use std::fs;
+use std::path::PathBuf;
+
+/// Returns the path of a file in `tests/fixtures`.
+pub fn fixture_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("fixtures")
+        .join(name)
+}
+
+/// Reads a fixture file into memory.
+pub fn fixture(name: &str) -> Vec<u8> {
+    let path = fixture_path(name);
+    fs::read(&path).unwrap_or_else(|err| panic!("reading {}: {}", path.display(), err))
+}
+
+/// Returns the names of every `.torrent` fixture, sorted.
+#[allow(dead_code)]
+pub fn torrent_fixtures() -> Vec<String> {
+    let mut names: Vec<String> = fs::read_dir(fixture_path(""))
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+        .filter(|name| name.ends_with(".torrent"))
+        .collect();
+    names.sort();
+    names
+}
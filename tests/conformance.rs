@@ -0,0 +1,159 @@
+#![cfg(all(feature = "serde", feature = "value"))]
+
+//! Acceptance cases collected from the test suites of other bencode implementations (bendy,
+//! serde_bencode, the BitTorrent spec's examples), run through every layer of this crate.
+//!
+//! Each case records whether the DOM reader (`validate`), the scanner (`scan::skip`) and the
+//! serde layer (`from_bytes::<IgnoredAny>`) accept the input as exactly one value. Where the
+//! layers disagree, it's intentional and the case says why.
+
+use beeenn::scan::skip;
+use beeenn::{from_bytes, validate, Limits};
+use serde::de::IgnoredAny;
+
+struct Case {
+    input: &'static [u8],
+    dom: bool,
+    scan: bool,
+    serde: bool,
+}
+
+const fn valid(input: &'static [u8]) -> Case {
+    Case {
+        input,
+        dom: true,
+        scan: true,
+        serde: true,
+    }
+}
+
+const fn invalid(input: &'static [u8]) -> Case {
+    Case {
+        input,
+        dom: false,
+        scan: false,
+        serde: false,
+    }
+}
+
+const CASES: &[Case] = &[
+    // Integers.
+    valid(b"i0e"),
+    valid(b"i42e"),
+    valid(b"i-42e"),
+    valid(b"i9223372036854775807e"),
+    valid(b"i-9223372036854775808e"),
+    invalid(b"ie"),
+    invalid(b"i-e"),
+    invalid(b"i-0e"),
+    invalid(b"i01e"),
+    invalid(b"i00e"),
+    invalid(b"i-01e"),
+    invalid(b"i+1e"),
+    invalid(b"i 1e"),
+    invalid(b"i1.5e"),
+    invalid(b"i1"),
+    invalid(b"i"),
+    invalid(b"i-9223372036854775809e"),
+    // The serde layer accepts anything that fits a u64, so that u64 fields can use the whole
+    // range. The DOM stores integers as i64.
+    Case {
+        input: b"i9223372036854775808e",
+        dom: false,
+        scan: false,
+        serde: true,
+    },
+    Case {
+        input: b"i18446744073709551615e",
+        dom: false,
+        scan: false,
+        serde: true,
+    },
+    // Strings.
+    valid(b"0:"),
+    valid(b"4:spam"),
+    valid(b"3:\0\xff\n"),
+    valid(b"10:0123456789"),
+    invalid(b"1:"),
+    invalid(b"5:spam"),
+    invalid(b"4spam"),
+    invalid(b"-1:a"),
+    invalid(b"01:a"),
+    invalid(b"00:"),
+    invalid(b":"),
+    invalid(b"4"),
+    // Lists.
+    valid(b"le"),
+    valid(b"l4:spami42ee"),
+    valid(b"llelee"),
+    valid(b"lli1eelde0:ee"),
+    invalid(b"l"),
+    invalid(b"li1e"),
+    invalid(b"lxe"),
+    // Dicts.
+    valid(b"de"),
+    valid(b"d3:bar4:spam3:fooi42ee"),
+    valid(b"d0:0:e"),
+    valid(b"d1:ad1:bleee"),
+    invalid(b"d"),
+    invalid(b"d1:a"),
+    invalid(b"d1:ae"),
+    invalid(b"di1ei2ee"),
+    invalid(b"dlei1ee"),
+    invalid(b"d1:ai1e"),
+    // Only the DOM reader insists on canonical key order, because it's the layer used to check
+    // and re-encode metainfo. The scanner and serde layer match keys by name.
+    Case {
+        input: b"d1:bi1e1:ai2ee",
+        dom: false,
+        scan: true,
+        serde: true,
+    },
+    Case {
+        input: b"d1:ai1e1:ai2ee",
+        dom: false,
+        scan: true,
+        serde: true,
+    },
+    // Exactly one value.
+    invalid(b""),
+    invalid(b"i1ei2e"),
+    invalid(b"dee"),
+    invalid(b"e"),
+    invalid(b"x"),
+];
+
+fn accepted_by_dom(input: &[u8]) -> bool {
+    validate(input, Limits::default()).is_ok()
+}
+
+fn accepted_by_scan(input: &[u8]) -> bool {
+    skip(input).is_ok_and(|len| len == input.len())
+}
+
+fn accepted_by_serde(input: &[u8]) -> bool {
+    from_bytes::<IgnoredAny>(input).is_ok()
+}
+
+#[test]
+fn test_conformance() {
+    let mut failures = Vec::new();
+    for case in CASES {
+        let layers = [
+            ("dom", case.dom, accepted_by_dom(case.input)),
+            ("scan", case.scan, accepted_by_scan(case.input)),
+            ("serde", case.serde, accepted_by_serde(case.input)),
+        ];
+        for (layer, expected, actual) in layers {
+            if expected != actual {
+                failures.push(format!(
+                    "{}: {} {}",
+                    case.input.escape_ascii(),
+                    layer,
+                    if actual { "accepted" } else { "rejected" }
+                ));
+            }
+        }
+    }
+    assert!(failures.is_empty(), "{:#?}", failures);
+}
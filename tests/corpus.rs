@@ -0,0 +1,209 @@
+#![cfg(all(feature = "serde", feature = "value"))]
+
+//! Parses every fixture with both the serde and DOM layers and checks that they agree.
+
+mod common;
+
+use beeenn::encode::KeyOrder;
+use beeenn::scan::value_len;
+use beeenn::{BEReader, BESliceReader, BEValue, Error};
+use serde::Deserialize;
+
+#[derive(Debug, Deserialize)]
+struct MetaInfo<'a> {
+    #[serde(borrow)]
+    announce: Option<&'a str>,
+    #[serde(borrow)]
+    info: Info<'a>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Info<'a> {
+    name: &'a [u8],
+    #[serde(rename = "piece length")]
+    piece_length: u64,
+    #[serde(borrow)]
+    pieces: Option<&'a [u8]>,
+    length: Option<u64>,
+    #[serde(borrow)]
+    files: Option<Vec<File<'a>>>,
+    #[serde(rename = "meta version")]
+    meta_version: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct File<'a> {
+    length: u64,
+    #[serde(borrow)]
+    path: Vec<&'a [u8]>,
+}
+
+fn read_dom(bytes: &[u8]) -> Result<BEValue, Error> {
+    let mut reader = BEReader::new(bytes);
+    let value = reader.next_value()?.expect("fixture is empty");
+    assert!(reader.next_value()?.is_none(), "trailing data in fixture");
+    Ok(value)
+}
+
+fn optional_int(value: &BEValue, path: &str) -> Option<u64> {
+    value.get_path(path).map(|v| v.integer() as u64)
+}
+
+// Checks that the typed serde result matches what the DOM saw.
+fn cross_check(name: &str, torrent: &MetaInfo, dom: &BEValue) {
+    let announce = dom.get_path("announce").map(|v| v.string());
+    assert_eq!(announce, torrent.announce, "{}: announce", name);
+
+    let info = &dom["info"];
+    assert_eq!(info["name"].bytes(), torrent.info.name, "{}: name", name);
+    assert_eq!(
+        info["piece length"].integer() as u64,
+        torrent.info.piece_length,
+        "{}: piece length",
+        name
+    );
+    assert_eq!(
+        info.get("pieces").map(|v| v.bytes()),
+        torrent.info.pieces,
+        "{}: pieces",
+        name
+    );
+    assert_eq!(
+        optional_int(info, "length"),
+        torrent.info.length,
+        "{}: length",
+        name
+    );
+    assert_eq!(
+        optional_int(info, "meta version"),
+        torrent.info.meta_version,
+        "{}: meta version",
+        name
+    );
+
+    match (info.get("files"), &torrent.info.files) {
+        (None, None) => {}
+        (Some(dom_files), Some(files)) => {
+            assert_eq!(dom_files.len(), files.len(), "{}: file count", name);
+            for (i, file) in files.iter().enumerate() {
+                let dom_file = &dom_files[i];
+                assert_eq!(dom_file["length"].integer() as u64, file.length);
+                let dom_path: Vec<&[u8]> = (0..dom_file["path"].len())
+                    .map(|j| dom_file["path"][j].bytes())
+                    .collect();
+                assert_eq!(dom_path, file.path, "{}: path of file {}", name, i);
+            }
+        }
+        _ => panic!("{}: layers disagree about 'files'", name),
+    }
+
+    // v1 pieces must cover the content exactly.
+    if let Some(pieces) = torrent.info.pieces {
+        let total: u64 = torrent.info.length.unwrap_or_else(|| {
+            torrent
+                .info
+                .files
+                .as_ref()
+                .unwrap()
+                .iter()
+                .map(|f| f.length)
+                .sum()
+        });
+        let piece_count = total.div_ceil(torrent.info.piece_length);
+        assert_eq!(pieces.len() as u64, piece_count * 20, "{}: pieces", name);
+    }
+}
+
+#[test]
+fn test_fixtures_agree() {
+    let names = common::torrent_fixtures();
+    assert!(names.len() >= 6, "missing fixtures: {:?}", names);
+
+    for name in names.iter().filter(|name| *name != "unsorted-keys.torrent") {
+        let bytes = common::fixture(name);
+        let torrent: MetaInfo = beeenn::from_bytes(&bytes)
+            .unwrap_or_else(|err| panic!("{}: serde failed: {}", name, err));
+        let dom = read_dom(&bytes).unwrap_or_else(|err| panic!("{}: DOM failed: {}", name, err));
+        cross_check(name, &torrent, &dom);
+
+        let spanned = BESliceReader::new(&bytes).next_spanned().unwrap().unwrap();
+        assert_eq!(dom, spanned.value, "{}", name);
+
+        // The raw bytes of 'info' are exactly its canonical encoding.
+        let info = &bytes[spanned.span_of("info").unwrap()];
+        assert_eq!(
+            info,
+            dom["info"].encode(KeyOrder::Require).unwrap(),
+            "{}",
+            name
+        );
+
+        assert_eq!(Some(bytes.len()), value_len(&bytes).unwrap(), "{}", name);
+
+        // Every canonical fixture re-encodes to exactly the same bytes.
+        assert!(dom.is_canonical(), "{}", name);
+        assert_eq!(bytes, dom.encode(KeyOrder::Require).unwrap(), "{}", name);
+    }
+}
+
+#[test]
+fn test_ubuntu() {
+    let bytes = std::fs::read(concat!(env!("CARGO_MANIFEST_DIR"), "/ubuntu.torrent")).unwrap();
+    let torrent: MetaInfo = beeenn::from_bytes(&bytes).unwrap();
+    let dom = read_dom(&bytes).unwrap();
+    cross_check("ubuntu.torrent", &torrent, &dom);
+    assert_eq!(bytes, dom.encode(KeyOrder::Require).unwrap());
+}
+
+#[test]
+fn test_v2_file_tree() {
+    let dom = read_dom(&common::fixture("v2.torrent")).unwrap();
+    // "b.txt" contains a '.', so it can't be reached with `get_path`.
+    let entry = &dom["info"]["file tree"]["dir"]["b.txt"][""];
+    assert_eq!(50000, entry.get_int("length").unwrap());
+    assert_eq!(32, entry.get_bytes("pieces root").unwrap().len());
+
+    let layers = &dom["piece layers"];
+    assert_eq!(1, layers.len());
+}
+
+#[test]
+fn test_non_utf8_name() {
+    let bytes = common::fixture("non-utf8-name.torrent");
+    let dom = read_dom(&bytes).unwrap();
+    assert!(dom.get_path("info.name").is_some());
+    assert!(dom["info"].get_str("name").is_err());
+
+    // A `String` name cannot hold Latin-1 bytes.
+    #[derive(Debug, Deserialize)]
+    struct StrictInfo {
+        #[allow(dead_code)]
+        name: String,
+    }
+    #[derive(Debug, Deserialize)]
+    struct Strict {
+        #[allow(dead_code)]
+        info: StrictInfo,
+    }
+    assert!(beeenn::from_bytes::<Strict>(&bytes).is_err());
+}
+
+#[test]
+fn test_unsorted_keys() {
+    let bytes = common::fixture("unsorted-keys.torrent");
+
+    // The DOM layer enforces canonical key order...
+    match read_dom(&bytes) {
+        Err(Error::KeysOutOfOrder(key)) => assert_eq!("name", key),
+        other => panic!("expected KeysOutOfOrder, got {:?}", other),
+    }
+
+    // ...while the serde layer matches fields by name and accepts it.
+    let torrent: MetaInfo = beeenn::from_bytes(&bytes).unwrap();
+    assert_eq!(
+        Some("http://tracker.example.com/announce"),
+        torrent.announce
+    );
+    assert_eq!(b"shuffled", torrent.info.name);
+    assert_eq!(Some(777), torrent.info.length);
+}
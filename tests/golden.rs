@@ -0,0 +1,175 @@
+#![cfg(all(feature = "serde", feature = "value"))]
+
+//! Locks in the serializer's canonical output byte for byte.
+//!
+//! Infohashes are computed over encoded bytes, so key order, integer formatting and which fields
+//! are left out must never change between versions or platforms. If a change to the serializer
+//! makes this test fail, the change is wrong, not the fixture. To regenerate the fixture after an
+//! intentional change to `structure()`, run with `BEEENN_BLESS=1`.
+
+mod common;
+
+use std::collections::{BTreeMap, HashMap};
+
+use beeenn::{to_bytes, validate, BESliceReader, Limits};
+use serde::{Serialize, Serializer};
+
+const FIXTURE: &str = "golden.bencode";
+
+// Raw bytes, serialized as a bencode string rather than a list of integers.
+struct Bytes(Vec<u8>);
+
+impl Serialize for Bytes {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_bytes(&self.0)
+    }
+}
+
+#[derive(Serialize)]
+struct Root {
+    announce: &'static str,
+    #[serde(rename = "created by")]
+    created_by: &'static str,
+    #[serde(rename = "creation date")]
+    creation_date: i64,
+    comment: Option<&'static str>,
+    info: Info,
+    integers: Integers,
+    // Iterated in a different order on every run.
+    hashed: HashMap<String, u32>,
+    // Sorted by raw bytes, so uppercase before lowercase and non-ASCII last.
+    keys: BTreeMap<&'static str, u8>,
+    #[serde(rename = "url-list", skip_serializing_if = "Vec::is_empty")]
+    url_list: Vec<&'static str>,
+}
+
+#[derive(Serialize)]
+struct Info {
+    name: &'static str,
+    #[serde(rename = "piece length")]
+    piece_length: u32,
+    pieces: Bytes,
+    private: Option<bool>,
+    files: Vec<File>,
+}
+
+#[derive(Serialize)]
+struct File {
+    path: Vec<String>,
+    length: u64,
+    md5sum: Option<Bytes>,
+    attr: Option<&'static str>,
+}
+
+#[derive(Serialize)]
+struct Integers {
+    zero: u8,
+    negative_one: i8,
+    i16_min: i16,
+    i32_max: i32,
+    i64_min: i64,
+    i64_max: i64,
+    u64_max: u64,
+    yes: bool,
+    no: bool,
+}
+
+fn structure() -> Root {
+    let files = (0..200u64)
+        .map(|i| File {
+            path: vec![format!("dir{}", i % 7), format!("file{}.bin", i)],
+            length: i * i * 1021,
+            md5sum: if i % 3 == 0 {
+                Some(Bytes((0..16).map(|b| (b * 17 + i) as u8).collect()))
+            } else {
+                None
+            },
+            attr: if i % 5 == 0 { Some("x") } else { None },
+        })
+        .collect();
+
+    Root {
+        announce: "http://tracker.example/announce",
+        created_by: "beeenn",
+        creation_date: 1700000000,
+        comment: None,
+        info: Info {
+            name: "golden",
+            piece_length: 262144,
+            pieces: Bytes((0..=255).chain(0..=255).collect()),
+            private: Some(true),
+            files,
+        },
+        integers: Integers {
+            zero: 0,
+            negative_one: -1,
+            i16_min: i16::MIN,
+            i32_max: i32::MAX,
+            i64_min: i64::MIN,
+            i64_max: i64::MAX,
+            u64_max: u64::MAX,
+            yes: true,
+            no: false,
+        },
+        hashed: (0..50).map(|i| (format!("k{}", i), i)).collect(),
+        keys: [
+            ("b", 1),
+            ("B", 2),
+            ("a", 3),
+            ("aa", 4),
+            ("a b", 5),
+            ("é", 6),
+            ("", 7),
+        ]
+        .iter()
+        .copied()
+        .collect(),
+        url_list: Vec::new(),
+    }
+}
+
+#[test]
+fn test_golden_output() {
+    let bytes = to_bytes(&structure()).unwrap();
+    if std::env::var_os("BEEENN_BLESS").is_some() {
+        std::fs::write(common::fixture_path(FIXTURE), &bytes).unwrap();
+    }
+    let golden = common::fixture(FIXTURE);
+    assert!(golden == bytes, "output differs from {}", FIXTURE);
+
+    // The same structure always produces the same bytes.
+    assert_eq!(bytes, to_bytes(&structure()).unwrap());
+}
+
+#[test]
+fn test_golden_fixture_is_canonical() {
+    let mut golden = common::fixture(FIXTURE);
+
+    // u64::MAX doesn't fit the DOM, so check its encoding directly and then swap it out.
+    let needle = b"7:u64_maxi18446744073709551615e";
+    let pos = golden.windows(needle.len()).position(|w| w == needle);
+    golden.splice(
+        pos.unwrap()..pos.unwrap() + needle.len(),
+        b"7:u64_maxi0e".iter().copied(),
+    );
+    validate(&golden, Limits::default()).unwrap();
+
+    let value = BESliceReader::new(&golden).next_value().unwrap().unwrap();
+    assert!(value.get("comment").is_none());
+    assert!(value.get("url-list").is_none());
+    assert_eq!(1, value["info"].get_int("private").unwrap());
+    assert_eq!(i64::MIN, value["integers"].get_int("i64_min").unwrap());
+    assert_eq!(0, value["integers"].get_int("no").unwrap());
+    assert_eq!(512, value["info"].get_bytes("pieces").unwrap().len());
+
+    let file = &value["info"]["files"][3];
+    assert_eq!(16, file.get_bytes("md5sum").unwrap().len());
+    assert!(file.get("attr").is_none());
+
+    let keys: Vec<&[u8]> = match &value["keys"] {
+        beeenn::BEValue::BEDict(dict) => dict.iter().map(|(k, _)| &k[..]).collect(),
+        _ => unreachable!(),
+    };
+    let expected: Vec<&[u8]> = vec![b"", b"B", b"a", b"a b", b"aa", b"b", "é".as_bytes()];
+    assert_eq!(expected, keys);
+}
@@ -0,0 +1,84 @@
+//! Checks what the decoders report to an installed `Metrics`.
+//!
+//! The hook is global, so everything is checked from a single test.
+
+use std::sync::Mutex;
+
+use beeenn::metrics::{set_metrics, Metrics};
+#[cfg(any(feature = "serde", feature = "value"))]
+use beeenn::{Error, Kind};
+
+#[derive(Debug, Default, PartialEq)]
+struct Report {
+    decoded: Vec<(usize, usize)>,
+    #[cfg(any(feature = "serde", feature = "value"))]
+    errors: Vec<(Kind, String)>,
+}
+
+struct Recorder(Mutex<Report>);
+
+impl Metrics for Recorder {
+    fn decoded(&self, len: usize, values: usize) {
+        self.0.lock().unwrap().decoded.push((len, values));
+    }
+
+    #[cfg(any(feature = "serde", feature = "value"))]
+    fn error(&self, error: &Error) {
+        self.0
+            .lock()
+            .unwrap()
+            .errors
+            .push((error.kind(), error.to_string()));
+    }
+}
+
+static RECORDER: Recorder = Recorder(Mutex::new(Report {
+    decoded: Vec::new(),
+    #[cfg(any(feature = "serde", feature = "value"))]
+    errors: Vec::new(),
+}));
+
+#[cfg(any(feature = "serde", feature = "value"))]
+fn take() -> Report {
+    std::mem::take(&mut *RECORDER.0.lock().unwrap())
+}
+
+#[test]
+fn test_reports() {
+    assert!(set_metrics(&RECORDER).is_ok());
+    assert!(set_metrics(&RECORDER).is_err());
+
+    #[cfg(feature = "value")]
+    {
+        let mut reader = beeenn::BESliceReader::new(b"d1:ali1ei2eee3:foo");
+        reader.next_value().unwrap();
+        reader.next_value().unwrap();
+        // Running out of input between values isn't decoding anything.
+        assert!(reader.next_value().unwrap().is_none());
+        assert!(beeenn::BEReader::new(&b"li1e"[..]).next_value().is_err());
+
+        let report = take();
+        assert_eq!(vec![(13, 5), (5, 1)], report.decoded);
+        assert_eq!(1, report.errors.len());
+        assert_eq!(Kind::Eof, report.errors[0].0);
+    }
+
+    #[cfg(feature = "serde")]
+    {
+        let value: Vec<u8> = beeenn::from_bytes(b"li1ei2ee").unwrap();
+        assert_eq!(vec![1, 2], value);
+        assert!(beeenn::from_bytes::<u8>(b"i1ei2e").is_err());
+        let limits = beeenn::Limits {
+            max_total_bytes: 2,
+            ..beeenn::Limits::default()
+        };
+        assert!(beeenn::from_bytes_with::<u8>(b"i1e", limits).is_err());
+
+        let report = take();
+        assert_eq!(vec![(8, 3)], report.decoded);
+        assert_eq!(2, report.errors.len());
+        assert_eq!(Kind::Syntax, report.errors[0].0);
+        assert!(report.errors[0].1.contains("trailing"));
+        assert_eq!(Kind::Limit, report.errors[1].0);
+    }
+}
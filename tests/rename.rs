@@ -0,0 +1,153 @@
+#![cfg(all(feature = "serde", feature = "value"))]
+
+//! Renamed struct fields must be written with their exact bytes, sorted by those bytes, and
+//! matched by them when reading, whichever layer reads them back.
+
+use beeenn::{from_bytes, to_bytes, BEReader, BEValue};
+use serde::{Deserialize, Serialize};
+
+// Reads `bytes` with the DOM layer, which rejects keys that aren't in canonical order.
+fn read_dom(bytes: &[u8]) -> BEValue {
+    BEReader::new(bytes).next_value().unwrap().unwrap()
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+struct Info {
+    length: u64,
+    name: String,
+    #[serde(rename = "piece length")]
+    piece_length: u64,
+    #[serde(rename = "magnet-info")]
+    magnet_info: Magnet,
+}
+
+#[derive(Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Magnet {
+    display_name: String,
+    exact_topic: String,
+    address_tracker: Option<String>,
+}
+
+fn info() -> Info {
+    Info {
+        length: 5,
+        name: "file".to_string(),
+        piece_length: 16384,
+        magnet_info: Magnet {
+            display_name: "name".to_string(),
+            exact_topic: "urn".to_string(),
+            address_tracker: None,
+        },
+    }
+}
+
+#[test]
+fn test_spaces_and_dashes() {
+    let bytes = to_bytes(&info()).unwrap();
+    assert_eq!(
+        &b"d6:lengthi5e11:magnet-infod12:display-name4:name11:exact-topic3:urne\
+           4:name4:file12:piece lengthi16384ee"[..],
+        &bytes[..]
+    );
+    assert_eq!(info(), from_bytes(&bytes).unwrap());
+
+    let dom = read_dom(&bytes);
+    assert_eq!(16384, dom["piece length"].integer());
+    assert_eq!("urn", dom["magnet-info"]["exact-topic"].string());
+    assert!(dom["magnet-info"].get("address-tracker").is_none());
+}
+
+#[test]
+fn test_rename_all() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+    struct Upper {
+        creation_date: u64,
+        b: u8,
+    }
+
+    // Upper case sorts before lower case.
+    let val = Upper {
+        creation_date: 1,
+        b: 2,
+    };
+    let bytes = to_bytes(&val).unwrap();
+    assert_eq!(&b"d1:Bi2e13:CREATION_DATEi1ee"[..], &bytes[..]);
+    assert_eq!(val, from_bytes(&bytes).unwrap());
+
+    // The original field names no longer match.
+    assert!(from_bytes::<Upper>(b"d1:bi2e13:creation_datei1ee").is_err());
+}
+
+#[test]
+fn test_unicode_keys() {
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Unicode {
+        #[serde(rename = "名前")]
+        name: String,
+        #[serde(rename = "ñ")]
+        enye: u8,
+        #[serde(rename = "émoji 🎉")]
+        emoji: u8,
+        z: u8,
+    }
+
+    let val = Unicode {
+        name: "値".to_string(),
+        enye: 1,
+        emoji: 2,
+        z: 3,
+    };
+    let bytes = to_bytes(&val).unwrap();
+    assert_eq!(
+        "d1:zi3e11:émoji 🎉i2e2:ñi1e6:名前3:値e".as_bytes(),
+        &bytes[..]
+    );
+    assert_eq!(val, from_bytes(&bytes).unwrap());
+
+    let dom = read_dom(&bytes);
+    assert_eq!(2, dom["émoji 🎉"].integer());
+    assert_eq!("値", dom["名前"].string());
+
+    // The serde layer matches keys by name, so hand-written input in another order works too.
+    let reordered = "d6:名前3:値1:zi3e2:ñi1e11:émoji 🎉i2ee";
+    assert_eq!(val, from_bytes(reordered.as_bytes()).unwrap());
+}
+
+#[test]
+fn test_keys_that_look_like_bencode() {
+    // Keys are length-prefixed, so no byte needs escaping.
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Tricky {
+        #[serde(rename = "")]
+        empty: u8,
+        #[serde(rename = "e")]
+        e: u8,
+        #[serde(rename = "3:abc")]
+        prefixed: u8,
+        #[serde(rename = "i1e")]
+        integer: u8,
+        #[serde(rename = "d:\u{0}e")]
+        nul: u8,
+    }
+
+    let val = Tricky {
+        empty: 1,
+        e: 2,
+        prefixed: 3,
+        integer: 4,
+        nul: 5,
+    };
+    let bytes = to_bytes(&val).unwrap();
+    assert_eq!(
+        &b"d0:i1e5:3:abci3e4:d:\0ei5e1:ei2e3:i1ei4ee"[..],
+        &bytes[..]
+    );
+    assert_eq!(val, from_bytes(&bytes).unwrap());
+
+    let dom = read_dom(&bytes);
+    assert_eq!(1, dom[""].integer());
+    assert_eq!(3, dom["3:abc"].integer());
+    assert_eq!(5, dom["d:\u{0}e"].integer());
+}
@@ -0,0 +1,95 @@
+//! Runs the `torrent` helpers over the fixtures.
+#![cfg(feature = "value")]
+
+mod common;
+
+use beeenn::recover::recover;
+use beeenn::torrent::{edit_info, lint, rename, stats, Severity, TorrentStats, TorrentVersion};
+use beeenn::{BESliceReader, BEValue};
+
+fn read(name: &str) -> BEValue {
+    let bytes = common::fixture(name);
+    BESliceReader::new(&bytes).next_value().unwrap().unwrap()
+}
+
+#[test]
+fn test_stats() {
+    use TorrentVersion::*;
+
+    // Name, version, total size, files, piece length, pieces, trackers.
+    let expected = [
+        ("single-file.torrent", V1, 100000, 1, 32768, 4, 1),
+        ("multi-file.torrent", V1, 110005, 3, 32768, 4, 2),
+        ("non-utf8-name.torrent", V1, 12345, 1, 32768, 1, 1),
+        ("v2.torrent", V2, 70000, 2, 16384, 0, 1),
+        ("hybrid.torrent", Hybrid, 65536, 1, 32768, 2, 1),
+    ];
+    for (name, version, total_size, file_count, piece_length, piece_count, tracker_count) in
+        expected.iter()
+    {
+        assert_eq!(
+            TorrentStats {
+                version: *version,
+                total_size: *total_size,
+                file_count: *file_count,
+                piece_length: *piece_length,
+                piece_count: *piece_count,
+                tracker_count: *tracker_count,
+            },
+            stats(&read(name)).unwrap(),
+            "{}",
+            name
+        );
+    }
+}
+
+#[test]
+fn test_rename_keeps_other_bytes() {
+    for name in common::torrent_fixtures() {
+        let torrent = common::fixture(&name);
+        let spanned = match BESliceReader::new(&torrent).next_spanned() {
+            Ok(spanned) => spanned.unwrap(),
+            // Rejected by the reader, so `edit_info` can't read it either.
+            Err(_) => continue,
+        };
+        let info = spanned.span_of("info").unwrap();
+
+        let renamed = edit_info(&torrent, |info| rename(info, "renamed")).unwrap();
+        let tail = torrent.len() - info.end;
+        assert_eq!(&torrent[..info.start], &renamed[..info.start], "{}", name);
+        assert_eq!(
+            &torrent[info.end..],
+            &renamed[renamed.len() - tail..],
+            "{}",
+            name
+        );
+
+        let renamed_info = &renamed[info.start..renamed.len() - tail];
+        let renamed_info = BESliceReader::new(renamed_info)
+            .next_value()
+            .unwrap()
+            .unwrap();
+        assert_eq!(Ok("renamed"), renamed_info.get_str("name"), "{}", name);
+    }
+}
+
+#[test]
+fn test_lint() {
+    for name in common::torrent_fixtures() {
+        // Read leniently, so unsorted keys reach `lint`.
+        let torrent = recover(&common::fixture(&name)).value.unwrap();
+        let errors: Vec<_> = lint(&torrent)
+            .into_iter()
+            .filter(|finding| finding.severity == Severity::Error)
+            .map(|finding| finding.to_string())
+            .collect();
+        if name == "unsorted-keys.torrent" {
+            assert_eq!(
+                vec!["error unsorted-key: 'info.name' is out of order"],
+                errors
+            );
+        } else {
+            assert!(errors.is_empty(), "{}: {:?}", name, errors);
+        }
+    }
+}